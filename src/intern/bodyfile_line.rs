@@ -0,0 +1,77 @@
+use crate::intern::Filetime;
+use std::fmt;
+
+///
+/// a line of a bodyfile in the format of TSK 3.x. Unlike
+/// `bodyfile::Bodyfile3Line`, the timestamps keep their fractions of a second
+/// and dates before 1970, both of which are accepted by `mactime`. Missing
+/// timestamps are written as `-1`.
+#[derive(Default)]
+pub struct BodyfileLine {
+    name: String,
+    inode: String,
+    size: u64,
+    atime: Option<Filetime>,
+    mtime: Option<Filetime>,
+    ctime: Option<Filetime>,
+    crtime: Option<Filetime>,
+}
+
+impl BodyfileLine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_owned_name(mut self, name: String) -> Self {
+        self.name = name;
+        self
+    }
+
+    pub fn with_owned_inode(mut self, inode: String) -> Self {
+        self.inode = inode;
+        self
+    }
+
+    pub fn with_size(mut self, size: u64) -> Self {
+        self.size = size;
+        self
+    }
+
+    pub fn with_atime(mut self, atime: Filetime) -> Self {
+        self.atime = Some(atime);
+        self
+    }
+
+    pub fn with_mtime(mut self, mtime: Filetime) -> Self {
+        self.mtime = Some(mtime);
+        self
+    }
+
+    pub fn with_ctime(mut self, ctime: Filetime) -> Self {
+        self.ctime = Some(ctime);
+        self
+    }
+
+    pub fn with_crtime(mut self, crtime: Filetime) -> Self {
+        self.crtime = Some(crtime);
+        self
+    }
+}
+
+impl fmt::Display for BodyfileLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let format_time = |time: &Option<Filetime>| match time {
+            Some(time) => time.to_string(),
+            None => "-1".to_owned(),
+        };
+        write!(f,
+            "0|{}|{}||0|0|{}|{}|{}|{}|{}",
+            self.name,
+            self.inode,
+            self.size,
+            format_time(&self.atime),
+            format_time(&self.mtime),
+            format_time(&self.ctime),
+            format_time(&self.crtime))
+    }
+}
@@ -0,0 +1,263 @@
+use crate::intern::complete_mft_entry::UsnJrnlEvent;
+use crate::intern::report::{csv_line, format_timestamp};
+use crate::intern::{Filetime, PreprocessedMft, UsnReason, UsnRecord};
+use std::collections::HashMap;
+use std::fmt;
+use winstructs::ntfs::mft_reference::MftReference;
+
+/// default length of the sliding window, in seconds
+pub const DEFAULT_BURST_WINDOW: i64 = 60;
+
+/// default number of different files which must be changed within the window
+pub const DEFAULT_BURST_MIN_FILES: usize = 20;
+
+/// shown in the extension statistics for files without an extension
+const NO_EXTENSION: &str = "(none)";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BurstKind {
+    /// many files have been renamed to the same new extension, or overwritten after such a rename
+    Encryption,
+
+    /// many files have been deleted
+    Deletion,
+}
+
+impl fmt::Display for BurstKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Encryption => write!(f, "encryption"),
+            Self::Deletion => write!(f, "deletion"),
+        }
+    }
+}
+
+/// a single change of a file, which may be part of a burst
+struct BurstEvent {
+    timestamp: Filetime,
+    file: MftReference,
+    directory: String,
+
+    /// the extension of the file before it has been renamed
+    extension: Option<String>,
+}
+
+impl BurstEvent {
+    fn new(mft: &PreprocessedMft, record: &UsnRecord, extension: Option<String>) -> Self {
+        Self {
+            timestamp: Filetime::from(record.filetime()),
+            file: *record.file_reference(),
+            directory: mft.tagged_path(mft.get_path_at(record.parent_reference(), record.usn())),
+            extension,
+        }
+    }
+}
+
+///
+/// a period of time in which many files have been changed in the same way,
+/// like it happens if ransomware encrypts files or if files are wiped.
+pub struct Burst {
+    kind: BurstKind,
+    extension: Option<String>,
+    start: Filetime,
+    end: Filetime,
+    files: usize,
+    events: usize,
+    directories: Vec<(String, usize)>,
+    extensions: Vec<(String, usize)>,
+}
+
+impl Burst {
+    pub const CSV_HEADER: &'static str = "kind,extension,start,end,files,events,directories,extensions";
+
+    pub fn kind(&self) -> BurstKind { self.kind }
+
+    /// returns the extension which has been added to the files of an encryption burst
+    pub fn extension(&self) -> Option<&str> { self.extension.as_deref() }
+    pub fn start(&self) -> Filetime { self.start }
+    pub fn end(&self) -> Filetime { self.end }
+
+    /// returns the number of different files which have been changed
+    pub fn files(&self) -> usize { self.files }
+    pub fn events(&self) -> usize { self.events }
+
+    /// returns the affected directories and their number of events, most affected directories first
+    pub fn directories(&self) -> &[(String, usize)] { &self.directories }
+
+    /// returns the original extensions of the affected files and their number of events
+    pub fn extensions(&self) -> &[(String, usize)] { &self.extensions }
+
+    fn from_events(kind: BurstKind, extension: Option<String>, events: &[BurstEvent]) -> Self {
+        let mut files = events.iter().map(|e| e.file).collect::<Vec<_>>();
+        files.sort_by_key(|f| (f.entry, f.sequence));
+        files.dedup();
+        Self {
+            kind,
+            extension,
+            start: events.first().map_or_else(Filetime::default, |e| e.timestamp),
+            end: events.last().map_or_else(Filetime::default, |e| e.timestamp),
+            files: files.len(),
+            events: events.len(),
+            directories: Self::count(events.iter().map(|e| e.directory.as_str())),
+            extensions: Self::count(events.iter().map(|e| e.extension.as_deref().unwrap_or(NO_EXTENSION))),
+        }
+    }
+
+    fn count<'a, I>(values: I) -> Vec<(String, usize)> where I: Iterator<Item=&'a str> {
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for value in values {
+            *counts.entry(value).or_default() += 1;
+        }
+        let mut counts: Vec<(String, usize)> = counts.into_iter().map(|(v, c)| (v.to_owned(), c)).collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        counts
+    }
+
+    pub fn to_csv_line(&self) -> String {
+        let format_counts = |counts: &[(String, usize)]| {
+            counts
+                .iter()
+                .map(|(value, count)| format!("{}={}", value, count))
+                .collect::<Vec<String>>()
+                .join("; ")
+        };
+        csv_line(&[
+            self.kind.to_string(),
+            self.extension.clone().unwrap_or_default(),
+            format_timestamp(Some(self.start)),
+            format_timestamp(Some(self.end)),
+            self.files.to_string(),
+            self.events.to_string(),
+            format_counts(&self.directories),
+            format_counts(&self.extensions),
+        ])
+    }
+}
+
+///
+/// searches the `$UsnJrnl` records for bursts of changes, which are typical
+/// for ransomware:
+///
+///  - renames which add the same new extension to many files, together with
+///    `DATA_OVERWRITE` records of these files, and
+///  - `FILE_DELETE` records of many files.
+///
+/// A burst is found if at least `min_files` different files are changed
+/// within a sliding window of `window` seconds. Overlapping windows are
+/// combined into a single burst.
+#[derive(Debug, Clone, Copy)]
+pub struct BurstDetector {
+    window: i64,
+    min_files: usize,
+}
+
+impl Default for BurstDetector {
+    fn default() -> Self {
+        Self {
+            window: DEFAULT_BURST_WINDOW,
+            min_files: DEFAULT_BURST_MIN_FILES,
+        }
+    }
+}
+
+impl BurstDetector {
+    /// sets the length of the sliding window, in seconds
+    pub fn with_window(mut self, window: i64) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// sets the number of different files which must be changed within the window
+    pub fn with_min_files(mut self, min_files: usize) -> Self {
+        self.min_files = min_files;
+        self
+    }
+
+    pub fn window(&self) -> i64 { self.window }
+    pub fn min_files(&self) -> usize { self.min_files }
+
+    /// returns all bursts found in `mft`, ordered by their start time
+    pub fn detect(&self, mft: &PreprocessedMft) -> Vec<Burst> {
+        let mut encryptions: HashMap<String, Vec<BurstEvent>> = HashMap::new();
+        let mut deletions = Vec::new();
+
+        for entry in mft.all_entries() {
+            let events = entry.usnjrnl_events();
+
+            /* new extensions of this file, together with its original extension */
+            let mut new_extensions: Vec<(String, Option<String>)> = Vec::new();
+            for event in events.iter() {
+                if let UsnJrnlEvent::Rename(old, new) = event {
+                    let old_extension = extension_of(old.filename());
+                    match extension_of(new.filename()) {
+                        Some(extension) if Some(&extension) != old_extension.as_ref() && new.filetime() != 0 => {
+                            encryptions.entry(extension.clone()).or_default().push(BurstEvent::new(mft, new, old_extension.clone()));
+                            new_extensions.push((extension, old_extension));
+                        }
+                        _ => (),
+                    }
+                }
+            }
+            new_extensions.dedup_by(|a, b| a.0 == b.0);
+
+            for event in events.iter() {
+                let record = match event {
+                    UsnJrnlEvent::Record(record) if record.filetime() != 0 => record,
+                    _ => continue,
+                };
+                if record.reason().has_flag(UsnReason::DATA_OVERWRITE) {
+                    for (extension, old_extension) in new_extensions.iter() {
+                        encryptions.entry(extension.clone()).or_default().push(BurstEvent::new(mft, record, old_extension.clone()));
+                    }
+                }
+                if record.reason().has_flag(UsnReason::FILE_DELETE) {
+                    deletions.push(BurstEvent::new(mft, record, extension_of(record.filename())));
+                }
+            }
+        }
+
+        let mut bursts = Vec::new();
+        for (extension, events) in encryptions.into_iter() {
+            self.find_bursts(BurstKind::Encryption, Some(extension), events, &mut bursts);
+        }
+        self.find_bursts(BurstKind::Deletion, None, deletions, &mut bursts);
+        bursts.sort_by(|a, b| a.start.cmp(&b.start).then_with(|| a.extension.cmp(&b.extension)));
+        bursts
+    }
+
+    fn find_bursts(&self, kind: BurstKind, extension: Option<String>, mut events: Vec<BurstEvent>, bursts: &mut Vec<Burst>) {
+        events.sort_by_key(|e| e.timestamp);
+        let mut files: HashMap<MftReference, usize> = HashMap::new();
+        let mut ranges: Vec<(usize, usize)> = Vec::new();
+        let mut start = 0;
+        for end in 0..events.len() {
+            *files.entry(events[end].file).or_default() += 1;
+            while events[end].timestamp.timestamp() - events[start].timestamp.timestamp() > self.window {
+                if let Some(count) = files.get_mut(&events[start].file) {
+                    *count -= 1;
+                    if *count == 0 {
+                        files.remove(&events[start].file);
+                    }
+                }
+                start += 1;
+            }
+            if files.len() >= self.min_files {
+                match ranges.last_mut() {
+                    Some(range) if range.1 + 1 >= start => range.1 = end,
+                    _ => ranges.push((start, end)),
+                }
+            }
+        }
+        for (start, end) in ranges {
+            bursts.push(Burst::from_events(kind, extension.clone(), &events[start..=end]));
+        }
+    }
+}
+
+/// returns the lowercase extension of `filename`, if it has one
+fn extension_of(filename: &str) -> Option<String> {
+    match filename.rsplit_once('.') {
+        Some((name, extension)) if !name.is_empty() && !extension.is_empty() => Some(extension.to_lowercase()),
+        _ => None,
+    }
+}
@@ -0,0 +1,67 @@
+use std::io::{ErrorKind, Read};
+
+/// data are read in blocks of this size
+const BLOCK_SIZE: usize = 0x100000;
+
+///
+/// buffers data from a forward-only stream, so that a carver can look ahead
+/// of its current position without seeking
+pub(crate) struct CarvingReader<R> where R: Read {
+    reader: R,
+    buffer: Vec<u8>,
+    position: usize,
+    buffer_offset: u64,
+    eof: bool,
+}
+
+impl<R> CarvingReader<R> where R: Read {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buffer: Vec::new(),
+            position: 0,
+            buffer_offset: 0,
+            eof: false,
+        }
+    }
+
+    /// makes sure that at least `size` bytes are available at the current position.
+    /// Returns `false` if the end of the stream has been reached before.
+    pub fn fill(&mut self, size: usize) -> bool {
+        if self.buffer.len() - self.position >= size {
+            return true;
+        }
+
+        self.buffer.drain(..self.position);
+        self.buffer_offset += self.position as u64;
+        self.position = 0;
+
+        let mut block = vec![0; std::cmp::max(size, BLOCK_SIZE)];
+        while !self.eof && self.buffer.len() < size {
+            match self.reader.read(&mut block) {
+                Ok(0) => self.eof = true,
+                Ok(count) => self.buffer.extend_from_slice(&block[..count]),
+                Err(why) if why.kind() == ErrorKind::Interrupted => continue,
+                Err(why) => {
+                    log::error!("error while carving at offset {:#x}: {}", self.buffer_offset + self.buffer.len() as u64, why);
+                    self.eof = true;
+                }
+            }
+        }
+        self.buffer.len() >= size
+    }
+
+    /// returns the buffered data, starting at the current position
+    pub fn data(&self) -> &[u8] {
+        &self.buffer[self.position..]
+    }
+
+    /// returns the offset of the current position in the stream
+    pub fn offset(&self) -> u64 {
+        self.buffer_offset + self.position as u64
+    }
+
+    pub fn advance(&mut self, count: usize) {
+        self.position = std::cmp::min(self.position + count, self.buffer.len());
+    }
+}
@@ -1,16 +1,19 @@
-use crate::intern::PreprocessedMft;
+use crate::intern::{AttributeContent, AttributeRecords, BodyfileLine, Filetime, LogFileEvent, PreprocessedMft, SlackAttribute, SlackAttributeContent, TimestampClass, TimestompingFinding, UsnReason, UsnRecord};
+use crate::intern::timestamp_class::classify_entry;
 use crate::{FilenameInfo, TimestampTuple};
 use anyhow::Result;
-use bodyfile::Bodyfile3Line;
 use likely_stable::unlikely;
 use mft::attribute::{MftAttributeContent, MftAttributeType};
 use mft::MftEntry;
 use num::ToPrimitive;
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::cmp;
-use usnjrnl::{CommonUsnRecord, UsnRecordData};
 use winstructs::ntfs::mft_reference::MftReference;
 
+/// limits the recursion when resolving historical paths, in case of circular parent references
+const MAX_PATH_DEPTH: usize = 256;
+
 ///
 /// Represents the set of all $MFT entries that make up a files metadata.
 /// The idea is to store only the minimum required data to generate
@@ -32,6 +35,9 @@ use winstructs::ntfs::mft_reference::MftReference;
 ///    which are being displayed as well.
 ///
 ///  - the `$STANDARD_INFORMATION` attribute. This attribute contains four timestamps.
+///
+///  - `$STANDARD_INFORMATION` and `$FILE_NAME` attributes found in the slack of
+///    the entries, unless they are copies of the current attributes
 pub struct CompleteMftEntry {
     base_entry: MftReference,
     file_name_attribute: Option<FilenameInfo>,
@@ -39,9 +45,30 @@ pub struct CompleteMftEntry {
     full_path: RefCell<String>,
     is_allocated: bool,
     deletion_status: RefCell<&'static str>,
-    usnjrnl_records: Vec<CommonUsnRecord>,
+    usnjrnl_records: Vec<UsnRecord>,
+    logfile_events: Vec<LogFileEvent>,
     streams: Vec<StreamAttribute>,
     is_directory: bool,
+    carved_offset: Option<u64>,
+    slack_attributes: Vec<SlackAttribute>,
+}
+
+/// an event of the `$UsnJrnl`, which is shown as a single line
+pub(crate) enum UsnJrnlEvent<'a> {
+    Record(&'a UsnRecord),
+
+    /// a `RENAME_OLD_NAME` record, followed by its `RENAME_NEW_NAME` record
+    Rename(&'a UsnRecord, &'a UsnRecord),
+}
+
+impl UsnJrnlEvent<'_> {
+    /// returns the last record of this event
+    pub fn record(&self) -> &UsnRecord {
+        match self {
+            Self::Record(record) => record,
+            Self::Rename(_, new) => new,
+        }
+    }
 }
 
 pub struct StreamAttribute {
@@ -59,6 +86,7 @@ impl CompleteMftEntry {
             full_path: RefCell::new(String::new()),
             is_allocated: entry.is_allocated(),
             usnjrnl_records: Vec::new(),
+            logfile_events: Vec::new(),
             deletion_status: RefCell::new(if entry.is_allocated() {
                 ""
             } else {
@@ -66,6 +94,29 @@ impl CompleteMftEntry {
             }),
             streams: Vec::new(),
             is_directory: entry.is_dir(),
+            carved_offset: None,
+            slack_attributes: Vec::new(),
+        };
+        c.update_attributes(&entry);
+        c
+    }
+
+    /// creates an entry from a base entry which has been carved at `offset`
+    /// from some data other than the `$MFT`. Such entries are never treated as allocated.
+    pub fn from_carved_entry(entry_reference: MftReference, entry: MftEntry, offset: u64) -> Self {
+        let mut c = Self {
+            base_entry: entry_reference,
+            file_name_attribute: None,
+            standard_info_timestamps: None,
+            full_path: RefCell::new(String::new()),
+            is_allocated: false,
+            usnjrnl_records: Vec::new(),
+            logfile_events: Vec::new(),
+            deletion_status: RefCell::new(""),
+            streams: Vec::new(),
+            is_directory: entry.is_dir(),
+            carved_offset: Some(offset),
+            slack_attributes: Vec::new(),
         };
         c.update_attributes(&entry);
         c
@@ -79,17 +130,20 @@ impl CompleteMftEntry {
             full_path: RefCell::new(String::new()),
             is_allocated: false,
             usnjrnl_records: Vec::new(),
+            logfile_events: Vec::new(),
             deletion_status: RefCell::new(" (deleted)"),
             streams: Vec::new(),
             is_directory: false,
+            carved_offset: None,
+            slack_attributes: Vec::new(),
         };
         c.add_nonbase_entry(entry);
         c
     }
 
-    pub fn from_usnjrnl_records(_entry_ref: MftReference, records: Vec<CommonUsnRecord>) -> Self {
+    pub fn from_usnjrnl_records(_entry_ref: MftReference, records: Vec<UsnRecord>) -> Self {
         let mut records = records;
-        records.sort_by(|a, b| a.data.timestamp().partial_cmp(b.data.timestamp()).unwrap());
+        records.sort_by_key(|r| r.usn());
 
         Self {
             base_entry: _entry_ref,
@@ -98,9 +152,32 @@ impl CompleteMftEntry {
             full_path: RefCell::new(String::new()),
             is_allocated: false,
             usnjrnl_records: records,
+            logfile_events: Vec::new(),
             deletion_status: RefCell::new(" (deleted)"),
             streams: Vec::new(),
             is_directory: false,
+            carved_offset: None,
+            slack_attributes: Vec::new(),
+        }
+    }
+
+    pub fn from_logfile_events(entry_ref: MftReference, events: Vec<LogFileEvent>) -> Self {
+        let mut events = events;
+        events.sort_by_key(|e| e.lsn());
+
+        Self {
+            base_entry: entry_ref,
+            file_name_attribute: None,
+            standard_info_timestamps: None,
+            full_path: RefCell::new(String::new()),
+            is_allocated: false,
+            usnjrnl_records: Vec::new(),
+            logfile_events: events,
+            deletion_status: RefCell::new(" (deleted)"),
+            streams: Vec::new(),
+            is_directory: false,
+            carved_offset: None,
+            slack_attributes: Vec::new(),
         }
     }
 
@@ -112,6 +189,18 @@ impl CompleteMftEntry {
         self.is_allocated
     }
 
+    /// returns the offset where this entry has been carved from, if it has been carved
+    pub fn carved_offset(&self) -> Option<u64> {
+        self.carved_offset
+    }
+
+    fn carved_status(&self) -> String {
+        match self.carved_offset {
+            Some(offset) => format!(" (carved at {:#x})", offset),
+            None => String::new(),
+        }
+    }
+
     pub fn set_base_entry(&mut self, entry_ref: MftReference, entry: MftEntry) {
         assert_eq!(self.base_entry, entry_ref);
 
@@ -124,18 +213,40 @@ impl CompleteMftEntry {
         self.update_attributes(&e);
     }
 
-    pub fn add_usnjrnl_records(&mut self, records: Vec<CommonUsnRecord>) {
-        let mut records = records;
-        records.sort_by(|a, b| a.data.timestamp().partial_cmp(b.data.timestamp()).unwrap());
-
+    pub fn add_usnjrnl_records(&mut self, records: Vec<UsnRecord>) {
         if self.usnjrnl_records.is_empty() {
             self.usnjrnl_records = records;
         } else {
             self.usnjrnl_records.extend(records);
         }
+        self.usnjrnl_records.sort_by_key(|r| r.usn());
+    }
+
+    /// returns the first `$UsnJrnl` record (except range records) which has
+    /// been written at or after `usn`. Its filename and parent are the ones
+    /// the file had at the time of `usn`.
+    pub fn usnjrnl_record_at(&self, usn: i64) -> Option<&UsnRecord> {
+        let index = self.usnjrnl_records.partition_point(|r| r.usn() < usn);
+        self.usnjrnl_records[index..].iter().find(|r| !r.is_range_record())
+    }
+
+    pub fn add_logfile_events(&mut self, events: Vec<LogFileEvent>) {
+        self.logfile_events.extend(events);
+        self.logfile_events.sort_by_key(|e| e.lsn());
     }
 
     fn update_attributes(&mut self, entry: &MftEntry) {
+        let mut current_timestamps = Vec::new();
+
+        /* the `mft` crate rounds timestamps to microseconds, so they are read from the raw values */
+        let values: HashMap<u16, &[u8]> = AttributeRecords::from(entry)
+            .filter_map(|r| match r.content() {
+                AttributeContent::Resident(value) => Some((r.instance(), *value)),
+                _ => None,
+            })
+            .collect();
+        let value = |instance: u16| values.get(&instance).copied().unwrap_or_default();
+
         for attr_result in entry
             .iter_attributes_matching(Some(vec![
                 MftAttributeType::StandardInformation,
@@ -161,32 +272,46 @@ impl CompleteMftEntry {
                 continue;
             }
 
+            let instance = attr_result.header.instance;
             match attr_result.data {
                 MftAttributeContent::AttrX10(standard_info_attribute) => {
+                    let timestamps = || TimestampTuple::from_standard_info_value(value(instance))
+                        .unwrap_or_else(|| TimestampTuple::from(&standard_info_attribute));
+                    current_timestamps.push(timestamps());
                     if self.standard_info_timestamps.is_none() {
-                        self.standard_info_timestamps =
-                            Some(TimestampTuple::from(&standard_info_attribute));
+                        self.standard_info_timestamps = Some(timestamps());
                     } else {
-                        panic!("multiple standard information attributes found")
+                        log::warn!("ignoring multiple standard information attributes in $MFT entry {}", entry.header.record_number);
                     }
                 }
 
                 MftAttributeContent::AttrX30(file_name_attribute) => {
+                    let timestamps = || TimestampTuple::from_file_name_value(value(instance))
+                        .unwrap_or_else(|| TimestampTuple::from(&file_name_attribute));
+                    current_timestamps.push(timestamps());
                     match self.file_name_attribute {
                         None => {
                             self.file_name_attribute = Some(FilenameInfo::from(
                                 &file_name_attribute,
                                 &attr_result.header,
+                                timestamps(),
                             ))
                         }
                         Some(ref mut name_attr) => {
-                            name_attr.update(&file_name_attribute, &attr_result.header)
+                            name_attr.update(&file_name_attribute, &attr_result.header, timestamps())
                         }
                     }
                 }
                 _ => panic!("filter for iter_attributes_matching() isn't working"),
             }
         }
+
+        /* ignore copies of the current attributes, which remain if the entry shrinks */
+        self.slack_attributes.extend(
+            SlackAttribute::from_entry(entry)
+                .into_iter()
+                .filter(|a| !current_timestamps.contains(a.timestamps())),
+        );
     }
 
     pub fn parent(&self) -> Option<&MftReference> {
@@ -224,7 +349,7 @@ impl CompleteMftEntry {
                     Some(p) => self.set_folder_name(mft, p, name.filename()),
                 },
                 None => {
-                    let my_name = match self.filename_from_usnjrnl() {
+                    let my_name = match self.filename_from_usnjrnl().or_else(|| self.filename_from_logfile()) {
                         Some(name) => name.to_owned(),
                         None => format!(
                             "unnamed_{}_{}",
@@ -232,7 +357,7 @@ impl CompleteMftEntry {
                         ),
                     };
 
-                    match self.parent_from_usnjrnl() {
+                    match self.parent_from_usnjrnl().or_else(|| self.parent_from_logfile()) {
                         None => *self.full_path.borrow_mut() = my_name,
                         Some(p) => self.set_folder_name(mft, &p, &my_name),
                     };
@@ -242,16 +367,68 @@ impl CompleteMftEntry {
         self.full_path.borrow().to_string()
     }
 
+    /// returns the `$UsnJrnl` records of this file. Every `RENAME_OLD_NAME` record
+    /// which is directly followed by a `RENAME_NEW_NAME` record is combined with it.
+    pub(crate) fn usnjrnl_events(&self) -> Vec<UsnJrnlEvent<'_>> {
+        let mut events = Vec::new();
+        let mut records = self.usnjrnl_records.iter().filter(|r| !r.is_range_record()).peekable();
+        while let Some(record) = records.next() {
+            if record.reason().has_flag(UsnReason::RENAME_OLD_NAME) {
+                if let Some(new) = records.next_if(|r| r.reason().has_flag(UsnReason::RENAME_NEW_NAME) && r.is_carved() == record.is_carved()) {
+                    events.push(UsnJrnlEvent::Rename(record, new));
+                    continue;
+                }
+            }
+            events.push(UsnJrnlEvent::Record(record));
+        }
+        events.extend(self.usnjrnl_records.iter().filter(|r| r.is_range_record()).map(UsnJrnlEvent::Record));
+        events
+    }
+
+    /// returns the path of this file at the time of `usn`, replaying the
+    /// `$UsnJrnl` records of the file and its parents backwards from their
+    /// current state in the `$MFT`
+    pub fn get_path_at(&self, mft: &PreprocessedMft, usn: i64) -> String {
+        self.path_at(mft, usn, 0)
+    }
+
+    pub(crate) fn path_at(&self, mft: &PreprocessedMft, usn: i64, depth: usize) -> String {
+        if self.base_entry.entry == 5 {
+            return String::from("/");
+        }
+        match self.usnjrnl_record_at(usn) {
+            Some(record) if depth < MAX_PATH_DEPTH => self.path_of_record(mft, record, depth),
+            _ => self.get_full_path(mft),
+        }
+    }
+
+    /// returns the path of this file at the time when `record` has been written
+    fn path_of_record(&self, mft: &PreprocessedMft, record: &UsnRecord, depth: usize) -> String {
+        if record.parent_reference() == &self.base_entry {
+            return self.get_full_path(mft);
+        }
+        let mut path = mft.path_at(record.parent_reference(), record.usn(), depth + 1);
+        if !path.ends_with('/') {
+            path.push('/');
+        }
+        path.push_str(record.filename());
+        path
+    }
+
     fn filename_from_usnjrnl(&self) -> Option<&str> {
-        self.usnjrnl_records.last().map(|r| r.data.filename())
+        self.usnjrnl_records.iter().rfind(|r| !r.is_range_record()).map(|r| r.filename())
     }
 
     fn parent_from_usnjrnl(&self) -> Option<MftReference> {
-        self.usnjrnl_records.last().and_then(|r| match &r.data {
-            UsnRecordData::V2(data) => Some(data.ParentFileReferenceNumber),
-            #[allow(unreachable_patterns)]
-            _ => None,
-        })
+        self.usnjrnl_records.iter().rfind(|r| !r.is_range_record()).map(|r| *r.parent_reference())
+    }
+
+    fn filename_from_logfile(&self) -> Option<&str> {
+        self.logfile_events.iter().rev().find_map(|e| e.filename()).map(|f| f.as_str())
+    }
+
+    fn parent_from_logfile(&self) -> Option<MftReference> {
+        self.logfile_events.iter().rev().find_map(|e| e.parent()).copied()
     }
 
     pub fn filesize(&self) -> u64 {
@@ -268,8 +445,8 @@ impl CompleteMftEntry {
         attribute_id: u32,
         instance_id: u16,
     ) -> String {
-        Bodyfile3Line::new()
-            .with_owned_name(format!("{}{}", display_name, self.deletion_status.borrow()))
+        BodyfileLine::new()
+            .with_owned_name(format!("{}{}{}", display_name, self.deletion_status.borrow(), self.carved_status()))
             .with_owned_inode(format!(
                 "{}-{}-{}",
                 self.base_entry().entry,
@@ -287,7 +464,7 @@ impl CompleteMftEntry {
     fn format_fn(&self, mft: &PreprocessedMft) -> Option<String> {
         self.file_name_attribute.as_ref().map(|fn_attr| {
             self.format(
-                format!("{} ($FILE_NAME)", self.get_full_path(mft)),
+                format!("{} ($FILE_NAME)", mft.tagged_path(self.get_full_path(mft))),
                 fn_attr.timestamps(),
                 MftAttributeType::FileName.to_u32().unwrap(),
                 fn_attr.instance_id(),
@@ -301,18 +478,54 @@ impl CompleteMftEntry {
         stream_name: Option<&String>,
         attribute_id: u32,
         instance_id: u16,
+        annotation: &str,
     ) -> Option<String> {
         self.standard_info_timestamps.as_ref().map(|si| {
             let name = match stream_name {
                 None => self.get_full_path(mft),
                 Some(n) => format!("{}:{}", self.get_full_path(mft), n),
             };
+            let name = mft.tagged_path(name) + annotation;
             self.format(name, si, attribute_id, instance_id)
         })
     }
 
+    fn format_slack(&self, mft: &PreprocessedMft, attribute: &SlackAttribute) -> String {
+        let (name, attribute_id, size) = match attribute.content() {
+            SlackAttributeContent::StandardInformation(_) => (
+                format!("{} ($STANDARD_INFORMATION, slack)", self.get_full_path(mft)),
+                MftAttributeType::StandardInformation,
+                0,
+            ),
+            SlackAttributeContent::FileName(fn_attr) => {
+                let mut path = mft.get_full_path(fn_attr.parent()).full_path;
+                if !path.ends_with('/') {
+                    path.push('/');
+                }
+                path.push_str(fn_attr.filename());
+                (format!("{} ($FILE_NAME, slack)", path), MftAttributeType::FileName, fn_attr.logical_size())
+            }
+        };
+
+        let timestamps = attribute.timestamps();
+        BodyfileLine::new()
+            .with_owned_name(mft.tagged_path(name))
+            .with_owned_inode(format!(
+                "{}-{}-{}",
+                self.base_entry().entry,
+                attribute_id.to_u32().unwrap(),
+                attribute.instance()
+            ))
+            .with_size(size)
+            .with_atime(timestamps.accessed())
+            .with_mtime(timestamps.mft_modified())
+            .with_ctime(timestamps.modified())
+            .with_crtime(timestamps.created())
+            .to_string()
+    }
+
     /// returns the filename stored in the `$MFT`, if any, or None
-    fn mft_filename(&self) -> Option<&String> {
+    pub fn mft_filename(&self) -> Option<&String> {
         match &self.file_name_attribute {
             Some(fni) => Some(fni.filename()),
             None => None,
@@ -322,76 +535,163 @@ impl CompleteMftEntry {
     fn format_usnjrnl(
         &self,
         mft: &PreprocessedMft,
-        record: &CommonUsnRecord,
+        event: &UsnJrnlEvent,
         usnjrnl_longflags: bool,
     ) -> String {
-        match &record.data {
-            UsnRecordData::V2(data) => {
-                let filename_info = match self.mft_filename() {
-                    None => format!(" filename={}", data.FileName),
-                    Some(f) => {
-                        if f != &data.FileName {
-                            format!(" filename={}", data.FileName)
-                        } else {
-                            "".to_owned()
-                        }
-                    }
-                };
-
-                let reason_info = if usnjrnl_longflags {
-                    format!(" reason={:+}", data.Reason)
+        let record = event.record();
+        let rename_info = match event {
+            UsnJrnlEvent::Record(_) => "".to_owned(),
+            UsnJrnlEvent::Rename(old, new) => {
+                let old_folder = mft.get_path_at(old.parent_reference(), old.usn());
+                let new_folder = mft.get_path_at(new.parent_reference(), new.usn());
+                if old_folder != new_folder {
+                    format!(" renamed '{}' -> '{}' (from {} to {})", old.filename(), new.filename(), old_folder, new_folder)
                 } else {
-                    format!(" reason={}", data.Reason)
-                };
-
-                let parent_info = mft.get_full_path(&data.ParentFileReferenceNumber);
-                let parent_info = match &parent_info.reference {
-                    Some(parent_ref) => {
-                        if parent_ref == &data.ParentFileReferenceNumber
-                            || !parent_info.is_allocated
-                                && parent_ref
-                                    == &MftReference::new(
-                                        data.ParentFileReferenceNumber.entry,
-                                        data.ParentFileReferenceNumber.sequence + 1,
-                                    )
-                        {
-                            "".to_owned()
-                        } else {
-                            format!(
-                                " parent={}-{}/{}-{}/'{}'",
-                                parent_ref.entry,
-                                parent_ref.sequence,
-                                data.ParentFileReferenceNumber.entry,
-                                data.ParentFileReferenceNumber.sequence,
-                                parent_info.full_path
-                            )
-                        }
-                    }
-                    None => format!(" parent='{}'", parent_info.full_path),
-                };
-
-                let display_name = format!(
-                    "{} ($UsnJrnl{}{}{})",
-                    self.get_full_path(mft),
-                    filename_info,
-                    parent_info,
-                    reason_info
-                );
-                let timestamp = data.TimeStamp.timestamp();
-                Bodyfile3Line::new()
-                    .with_owned_name(display_name)
-                    .with_atime(timestamp)
-                    .with_owned_inode(format!(
-                        "{mft_entry}-{attr_type}-{usn_number}",
-                        mft_entry = data.FileReferenceNumber.entry,
-                        attr_type = "???",
-                        usn_number = data.FileReferenceNumber.sequence
-                    ))
-                    .to_string()
+                    format!(" renamed '{}' -> '{}'", old.filename(), new.filename())
+                }
+            }
+        };
+
+        let reason_info = if usnjrnl_longflags {
+            format!(" reason={:+}", record.reason())
+        } else {
+            format!(" reason={}", record.reason())
+        };
+
+        /* V4 records have neither attributes nor a security id */
+        let mut details_info = String::new();
+        if !record.source_info().is_empty() {
+            if usnjrnl_longflags {
+                details_info.push_str(&format!(" source={:+}", record.source_info()));
+            } else {
+                details_info.push_str(&format!(" source={}", record.source_info()));
+            }
+        }
+        if !record.file_attributes().is_empty() {
+            if usnjrnl_longflags {
+                details_info.push_str(&format!(" attributes={:+}", record.file_attributes()));
+            } else {
+                details_info.push_str(&format!(" attributes={}", record.file_attributes()));
             }
         }
+        if record.security_id() != 0 {
+            details_info.push_str(&format!(" security_id={}", record.security_id()));
+        }
+
+        let extents_info = if record.is_range_record() {
+            let extents: Vec<String> = record
+                .extents()
+                .iter()
+                .map(|e| format!("{:#x}+{:#x}", e.offset(), e.length()))
+                .collect();
+            let remaining_info = match record.remaining_extents() {
+                0 => "".to_owned(),
+                remaining => format!(" (+{} more)", remaining),
+            };
+            format!(" extents={}{}", extents.join(","), remaining_info)
+        } else {
+            "".to_owned()
+        };
+
+        /* carved records are not stored in `$J`, so their offset is shown separately */
+        let (position_info, carved_info) = if record.is_carved() {
+            (format!(" usn={:#x}", record.usn()), format!(" (carved at {:#x})", record.offset()))
+        } else {
+            /* records of the first `$J` file are not numbered, so that the output of a single file does not change */
+            let journal_info = match record.journal_index() {
+                0 => "".to_owned(),
+                index => format!(" journal={}", index + 1),
+            };
+            (format!(" usn={:#x} offset={:#x}{}", record.usn(), record.offset(), journal_info), "".to_owned())
+        };
+
+        /* range records have no filename, so their path is taken from the next regular record */
+        let path = if record.is_range_record() {
+            self.get_path_at(mft, record.usn())
+        } else {
+            self.path_of_record(mft, record, 0)
+        };
+        let current_path = self.get_full_path(mft);
+        let current_info = if current_path != path {
+            format!(" current='{}'", current_path)
+        } else {
+            "".to_owned()
+        };
+
+        let display_name = format!(
+            "{} ($UsnJrnl{}{}{}{}{}{}){}",
+            mft.tagged_path(path),
+            rename_info,
+            current_info,
+            reason_info,
+            details_info,
+            extents_info,
+            position_info,
+            carved_info
+        );
+        let line = BodyfileLine::new()
+            .with_owned_name(display_name)
+            .with_owned_inode(format!(
+                "{}-{}",
+                record.file_reference().entry,
+                record.file_reference().sequence
+            ));
+
+        /* range records without any other record of the same file have no timestamp */
+        if record.filetime() == 0 {
+            line.to_string()
+        } else {
+            line.with_atime(Filetime::from(record.filetime())).to_string()
+        }
     }
  
+    fn format_logfile(&self, mft: &PreprocessedMft, event: &LogFileEvent) -> String {
+        let filename_info = match event.filename() {
+            Some(f) if self.mft_filename() != Some(f) => format!(" filename={}", f),
+            _ => "".to_owned(),
+        };
+        let parent_info = match event.parent() {
+            Some(p) if self.parent() != Some(p) => format!(" parent='{}'", mft.get_full_path(p).full_path),
+            _ => "".to_owned(),
+        };
+
+        let display_name = format!(
+            "{} ($LogFile op={}{}{} lsn={:#x})",
+            mft.tagged_path(self.get_full_path(mft)),
+            event.operation(),
+            filename_info,
+            parent_info,
+            event.lsn()
+        );
+
+        let timestamps = event.timestamps();
+        let mut line = BodyfileLine::new()
+            .with_owned_name(display_name)
+            .with_owned_inode(format!(
+                "{}-{}-{}",
+                self.base_entry().entry,
+                event.attribute_type(),
+                self.base_entry().sequence
+            ));
+        if let Some(accessed) = timestamps.accessed() {
+            line = line.with_atime(accessed);
+        }
+        if let Some(mft_modified) = timestamps.mft_modified() {
+            line = line.with_mtime(mft_modified);
+        }
+        if let Some(modified) = timestamps.modified() {
+            line = line.with_ctime(modified);
+        }
+        if let Some(created) = timestamps.created() {
+            line = line.with_crtime(created);
+        }
+        line.to_string()
+    }
+
+    pub fn standard_info_timestamps(&self) -> Option<&TimestampTuple> {
+        self.standard_info_timestamps.as_ref()
+    }
+
     pub fn filename_info(&self) -> &Option<FilenameInfo> {
         if self.file_name_attribute.is_none() && self.is_allocated {
             #[cfg(debug_assertions)]
@@ -418,6 +718,11 @@ impl CompleteMftEntry {
     }
 
     pub fn bodyfile_lines(&self, mft: &PreprocessedMft, usnjrnl_longflags: bool) -> BodyfileLines {
+        let annotation = if mft.detect_timestomping() {
+            self.timestomping_annotation(mft)
+        } else {
+            String::new()
+        };
         let mut lines: Vec<String> = Vec::new();
         for d in self.streams.iter() {
             // hide default directory index name
@@ -430,29 +735,59 @@ impl CompleteMftEntry {
             };
 
             if let Some(line) =
-                self.format_si(mft, name, d.attribute_type.to_u32().unwrap(), d.instance)
+                self.format_si(mft, name, d.attribute_type.to_u32().unwrap(), d.instance, &annotation)
             {
                 lines.push(line);
             }
         }
 
         if lines.is_empty() {
-            if let Some(line) = self.format_si(mft, None, 0, 0) {
+            if let Some(line) = self.format_si(mft, None, 0, 0, &annotation) {
                 lines.push(line);
             }
         }
 
+        if mft.show_slack() {
+            lines.extend(self.slack_attributes.iter().map(|a| self.format_slack(mft, a)));
+        }
+
         BodyfileLines {
             standard_info: lines,
             filename_info: self.format_fn(mft),
             usnjrnl_records: self
-                .usnjrnl_records
+                .usnjrnl_events()
+                .iter()
+                .map(|e| self.format_usnjrnl(mft, e, usnjrnl_longflags))
+                .collect(),
+            logfile_events: self
+                .logfile_events
                 .iter()
-                .map(|r| self.format_usnjrnl(mft, r, usnjrnl_longflags))
+                .map(|e| self.format_logfile(mft, e))
                 .collect(),
         }
     }
  
+    /// classifies the timestamps of `$STANDARD_INFORMATION` and `$FILE_NAME`
+    pub fn timestamp_classes(&self, mft: &PreprocessedMft) -> Vec<TimestampClass> {
+        classify_entry(self, mft).into_iter().map(|(_, _, _, class)| class).collect()
+    }
+
+    /// lists the timestomping rules which match this entry, like `(timestomping: zero_fraction)`
+    fn timestomping_annotation(&self, mft: &PreprocessedMft) -> String {
+        let mut rules: Vec<String> = Vec::new();
+        for finding in TimestompingFinding::check(self, mft) {
+            let rule = finding.rule().to_string();
+            if !rules.contains(&rule) {
+                rules.push(rule);
+            }
+        }
+        if rules.is_empty() {
+            String::new()
+        } else {
+            format!(" (timestomping: {})", rules.join(", "))
+        }
+    }
+
     pub fn bodyfile_lines_count(&self) -> usize {
         (match &self.standard_info_timestamps {
             Some(_) => cmp::min(self.streams.len(), 1),
@@ -460,7 +795,12 @@ impl CompleteMftEntry {
         } + match &self.file_name_attribute {
             Some(_) => 1,
             None => 0,
-        } + self.usnjrnl_records.len())
+        } + self.usnjrnl_events().len()
+            + self.logfile_events.len())
+    }
+
+    pub fn slack_lines_count(&self) -> usize {
+        self.slack_attributes.len()
     }
 }
 
@@ -468,6 +808,7 @@ pub struct BodyfileLines {
     standard_info: Vec<String>,
     filename_info: Option<String>,
     usnjrnl_records: Vec<String>,
+    logfile_events: Vec<String>,
 }
 
 impl Iterator for BodyfileLines {
@@ -479,6 +820,9 @@ impl Iterator for BodyfileLines {
         if self.filename_info.is_some() {
             return self.filename_info.take();
         }
-        self.usnjrnl_records.pop()
+        if !self.usnjrnl_records.is_empty() {
+            return self.usnjrnl_records.pop();
+        }
+        self.logfile_events.pop()
     }
 }
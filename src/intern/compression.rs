@@ -0,0 +1,108 @@
+use anyhow::{Result, bail};
+use std::fmt;
+use std::fs::File;
+use std::io::{Cursor, Read};
+use std::path::Path;
+
+///
+/// compression formats which can be used for `$MFT` and `$UsnJrnl:$J` files.
+/// The format is detected by the magic bytes at the beginning of the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+    Xz,
+    Bzip2,
+    Lz4,
+}
+
+impl Compression {
+    pub fn from_magic(magic: &[u8]) -> Self {
+        if magic.starts_with(&[0x1f, 0x8b]) {
+            Self::Gzip
+        } else if magic.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Self::Zstd
+        } else if magic.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            Self::Xz
+        } else if magic.starts_with(b"BZh") {
+            Self::Bzip2
+        } else if magic.starts_with(&[0x04, 0x22, 0x4d, 0x18]) {
+            Self::Lz4
+        } else {
+            Self::None
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let mut magic = [0; 6];
+        let bytes = File::open(path)?.read(&mut magic)?;
+        Ok(Self::from_magic(&magic[..bytes]))
+    }
+
+    /// wraps `reader` into a decoder for this compression format
+    pub fn decoder<R: Read + Send + 'static>(&self, reader: R) -> Result<Box<dyn Read + Send>> {
+        Ok(match self {
+            Self::None => Box::new(reader),
+
+            #[cfg(feature = "gzip")]
+            Self::Gzip => Box::new(flate2::read::MultiGzDecoder::new(reader)),
+
+            #[cfg(feature = "zstd")]
+            Self::Zstd => Box::new(zstd::stream::read::Decoder::new(reader)?),
+
+            #[cfg(feature = "xz")]
+            Self::Xz => Box::new(xz2::read::XzDecoder::new_multi_decoder(reader)),
+
+            #[cfg(feature = "bzip2")]
+            Self::Bzip2 => Box::new(bzip2::read::MultiBzDecoder::new(reader)),
+
+            #[cfg(feature = "lz4")]
+            Self::Lz4 => Box::new(lz4_flex::frame::FrameDecoder::new(reader)),
+
+            #[allow(unreachable_patterns)]
+            _ => bail!("support for {} compressed files has not been enabled (feature '{}')", self, self.feature()),
+        })
+    }
+
+    /// opens `path` and returns a reader for the decompressed data
+    pub fn open(path: &Path) -> Result<(Self, Box<dyn Read + Send>)> {
+        let compression = Self::from_path(path)?;
+        if compression != Self::None {
+            log::info!("{} is {} compressed", path.display(), compression);
+        }
+        Ok((compression, compression.decoder(File::open(path)?)?))
+    }
+
+    /// detects the compression of data read from `reader`, which need not be seekable,
+    /// and returns a reader for the decompressed data
+    pub fn open_reader<R: Read + Send + 'static>(mut reader: R) -> Result<(Self, Box<dyn Read + Send>)> {
+        let mut magic = Vec::with_capacity(6);
+        (&mut reader).take(6).read_to_end(&mut magic)?;
+        let compression = Self::from_magic(&magic);
+        if compression != Self::None {
+            log::info!("input is {} compressed", compression);
+        }
+        Ok((compression, compression.decoder(Cursor::new(magic).chain(reader))?))
+    }
+
+    fn feature(&self) -> &'static str {
+        match self {
+            Self::None => "",
+            Self::Gzip => "gzip",
+            Self::Zstd => "zstd",
+            Self::Xz => "xz",
+            Self::Bzip2 => "bzip2",
+            Self::Lz4 => "lz4",
+        }
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::None => write!(f, "uncompressed"),
+            _ => write!(f, "{}", self.feature()),
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use std::fmt;
+
+/// all attribute flags, in the order in which they are displayed
+const ATTRIBUTE_FLAGS: [(u32, &str); 21] = [
+    (0x00000001, "FILE_ATTRIBUTE_READONLY"),
+    (0x00000002, "FILE_ATTRIBUTE_HIDDEN"),
+    (0x00000004, "FILE_ATTRIBUTE_SYSTEM"),
+    (0x00000010, "FILE_ATTRIBUTE_DIRECTORY"),
+    (0x00000020, "FILE_ATTRIBUTE_ARCHIVE"),
+    (0x00000040, "FILE_ATTRIBUTE_DEVICE"),
+    (0x00000080, "FILE_ATTRIBUTE_NORMAL"),
+    (0x00000100, "FILE_ATTRIBUTE_TEMPORARY"),
+    (0x00000200, "FILE_ATTRIBUTE_SPARSE_FILE"),
+    (0x00000400, "FILE_ATTRIBUTE_REPARSE_POINT"),
+    (0x00000800, "FILE_ATTRIBUTE_COMPRESSED"),
+    (0x00001000, "FILE_ATTRIBUTE_OFFLINE"),
+    (0x00002000, "FILE_ATTRIBUTE_NOT_CONTENT_INDEXED"),
+    (0x00004000, "FILE_ATTRIBUTE_ENCRYPTED"),
+    (0x00008000, "FILE_ATTRIBUTE_INTEGRITY_STREAM"),
+    (0x00010000, "FILE_ATTRIBUTE_VIRTUAL"),
+    (0x00020000, "FILE_ATTRIBUTE_NO_SCRUB_DATA"),
+    (0x00040000, "FILE_ATTRIBUTE_RECALL_ON_OPEN"),
+    (0x00080000, "FILE_ATTRIBUTE_PINNED"),
+    (0x00100000, "FILE_ATTRIBUTE_UNPINNED"),
+    (0x00400000, "FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS"),
+];
+
+const FILE_ATTRIBUTE_PREFIX: &str = "FILE_ATTRIBUTE_";
+
+///
+/// the `FileAttributes` field of an `$UsnJrnl` record. When displayed, the
+/// flags are separated by `+`. The `FILE_ATTRIBUTE_` prefix is removed, unless
+/// the `+` flag is used in the format string (`{:+}`). Unknown bits are shown
+/// as a hexadecimal number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileAttributes(u32);
+
+impl From<u32> for FileAttributes {
+    fn from(value: u32) -> Self {
+        Self(value)
+    }
+}
+
+impl FileAttributes {
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+
+    pub fn has_flag(&self, flag: u32) -> bool {
+        self.0 & flag != 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl fmt::Display for FileAttributes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let longflags = f.sign_plus();
+        let mut flags: Vec<String> = ATTRIBUTE_FLAGS
+            .iter()
+            .filter(|(flag, _)| self.has_flag(*flag))
+            .map(|(_, name)| if longflags { name.to_string() } else { name[FILE_ATTRIBUTE_PREFIX.len()..].to_string() })
+            .collect();
+        let known_flags = ATTRIBUTE_FLAGS.iter().fold(0, |flags, (flag, _)| flags | flag);
+        if self.0 & !known_flags != 0 {
+            flags.push(format!("{:#x}", self.0 & !known_flags));
+        }
+        write!(f, "{}", flags.join("+"))
+    }
+}
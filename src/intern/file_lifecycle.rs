@@ -0,0 +1,139 @@
+use crate::intern::report::{csv_line, format_timestamp};
+use crate::intern::{CompleteMftEntry, Filetime, PreprocessedMft, UsnReason};
+use crate::intern::complete_mft_entry::UsnJrnlEvent;
+use winstructs::ntfs::mft_reference::MftReference;
+
+/// reasons of `$UsnJrnl` records which indicate a modification of the file content
+const DATA_CHANGE: u32 = UsnReason::DATA_OVERWRITE | UsnReason::DATA_EXTEND | UsnReason::DATA_TRUNCATION;
+
+/// a name or folder change of a file, found in `$UsnJrnl`
+pub struct NameChange {
+    timestamp: Filetime,
+    from: String,
+    to: String,
+}
+
+impl NameChange {
+    pub fn timestamp(&self) -> Filetime { self.timestamp }
+    pub fn from(&self) -> &str { &self.from }
+    pub fn to(&self) -> &str { &self.to }
+}
+
+///
+/// a summary of the life of a single file, which combines the timestamps of
+/// `$STANDARD_INFORMATION` and `$FILE_NAME` with the records of `$UsnJrnl`.
+///
+///  - `first_seen` is the oldest timestamp of all of them,
+///  - `created` is the time of the `FILE_CREATE` record, or the creation time
+///    of `$FILE_NAME` or `$STANDARD_INFORMATION` if there is no such record,
+///  - `renames` and `moves` are taken from pairs of `RENAME_OLD_NAME` and
+///    `RENAME_NEW_NAME` records,
+///  - `last_data_change` is the latest record which changed the content of
+///    the file, or the modification time of `$STANDARD_INFORMATION`, and
+///  - `deleted` is the time of the `FILE_DELETE` record.
+pub struct FileLifecycle {
+    reference: MftReference,
+    path: String,
+    is_allocated: bool,
+    first_seen: Option<Filetime>,
+    created: Option<Filetime>,
+    renames: Vec<NameChange>,
+    moves: Vec<NameChange>,
+    last_data_change: Option<Filetime>,
+    deleted: Option<Filetime>,
+}
+
+impl FileLifecycle {
+    pub const CSV_HEADER: &'static str = "reference,path,status,first_seen,created,renames,moves,last_data_change,deleted";
+
+    pub fn reference(&self) -> &MftReference { &self.reference }
+    pub fn path(&self) -> &str { &self.path }
+    pub fn is_allocated(&self) -> bool { self.is_allocated }
+    pub fn first_seen(&self) -> Option<Filetime> { self.first_seen }
+    pub fn created(&self) -> Option<Filetime> { self.created }
+    pub fn renames(&self) -> &[NameChange] { &self.renames }
+
+    /// returns all moves to a different folder, with the old and the new folder
+    pub fn moves(&self) -> &[NameChange] { &self.moves }
+    pub fn last_data_change(&self) -> Option<Filetime> { self.last_data_change }
+    pub fn deleted(&self) -> Option<Filetime> { self.deleted }
+
+    pub fn from_entry(entry: &CompleteMftEntry, mft: &PreprocessedMft) -> Self {
+        let si_timestamps = entry.standard_info_timestamps();
+        let fn_timestamps = entry.filename_info().as_ref().map(|f| f.timestamps());
+        let mut timestamps: Vec<Filetime> = si_timestamps
+            .into_iter()
+            .chain(fn_timestamps)
+            .flat_map(|t| vec![t.created(), t.modified(), t.mft_modified(), t.accessed()])
+            .filter(|t| !t.is_zero())
+            .collect();
+
+        let mut lifecycle = Self {
+            reference: *entry.base_entry(),
+            path: mft.tagged_path(entry.get_full_path(mft)),
+            is_allocated: entry.is_allocated(),
+            first_seen: None,
+            created: None,
+            renames: Vec::new(),
+            moves: Vec::new(),
+            last_data_change: si_timestamps.map(|t| t.modified()).filter(|t| !t.is_zero()),
+            deleted: None,
+        };
+
+        for event in entry.usnjrnl_events() {
+            let record = event.record();
+            let timestamp = Filetime::from(record.filetime());
+            if timestamp.is_zero() {
+                continue;
+            }
+            timestamps.push(timestamp);
+
+            if record.reason().has_flag(UsnReason::FILE_CREATE) && lifecycle.created.is_none() {
+                lifecycle.created = Some(timestamp);
+            }
+            if record.reason().has_flag(DATA_CHANGE) {
+                lifecycle.last_data_change = lifecycle.last_data_change.max(Some(timestamp));
+            }
+            if record.reason().has_flag(UsnReason::FILE_DELETE) {
+                lifecycle.deleted = Some(timestamp);
+            }
+            if let UsnJrnlEvent::Rename(old, new) = event {
+                if old.filename() != new.filename() {
+                    lifecycle.renames.push(NameChange { timestamp, from: old.filename().to_owned(), to: new.filename().to_owned() });
+                }
+                let old_folder = mft.get_path_at(old.parent_reference(), old.usn());
+                let new_folder = mft.get_path_at(new.parent_reference(), new.usn());
+                if old_folder != new_folder {
+                    lifecycle.moves.push(NameChange { timestamp, from: old_folder, to: new_folder });
+                }
+            }
+        }
+
+        if lifecycle.created.is_none() {
+            lifecycle.created = fn_timestamps.or(si_timestamps).map(|t| t.created()).filter(|t| !t.is_zero());
+        }
+        lifecycle.first_seen = timestamps.into_iter().min();
+        lifecycle
+    }
+
+    pub fn to_csv_line(&self) -> String {
+        let format_changes = |changes: &[NameChange]| {
+            changes
+                .iter()
+                .map(|c| format!("{} '{}' -> '{}'", format_timestamp(Some(c.timestamp)), c.from, c.to))
+                .collect::<Vec<String>>()
+                .join("; ")
+        };
+        csv_line(&[
+            format!("{}-{}", self.reference.entry, self.reference.sequence),
+            self.path.clone(),
+            if self.is_allocated { "allocated" } else { "deleted" }.to_owned(),
+            format_timestamp(self.first_seen),
+            format_timestamp(self.created),
+            format_changes(&self.renames),
+            format_changes(&self.moves),
+            format_timestamp(self.last_data_change),
+            format_timestamp(self.deleted),
+        ])
+    }
+}
@@ -23,38 +23,38 @@ impl FilenameInfo {
         self.namespace == FileNamespace::Win32AndDos
     }
 
-    pub fn from(attr: &FileNameAttr, header: &MftAttributeHeader) ->FilenameInfo {
+    pub fn from(attr: &FileNameAttr, header: &MftAttributeHeader, timestamps: TimestampTuple) ->FilenameInfo {
         FilenameInfo {
             filename: attr.name.clone(),
             namespace: attr.namespace.clone(),
-            timestamps: TimestampTuple::from(attr),
+            timestamps,
             parent: attr.parent,
             logical_size: attr.logical_size,
             instance_id: header.instance,
         }
     }
 
-    pub fn update(&mut self, attr: &FileNameAttr, header: &MftAttributeHeader) {
+    pub fn update(&mut self, attr: &FileNameAttr, header: &MftAttributeHeader, timestamps: TimestampTuple) {
         match attr.namespace {
-            FileNamespace::Win32AndDos => self.do_update(attr, header),
+            FileNamespace::Win32AndDos => self.do_update(attr, header, timestamps),
             FileNamespace::Win32 => {
                 if self.namespace != FileNamespace::Win32AndDos {
-                    self.do_update(attr, header)
+                    self.do_update(attr, header, timestamps)
                 }
             }
             FileNamespace::POSIX => {
                 if self.namespace == FileNamespace::DOS {
-                    self.do_update(attr, header)
+                    self.do_update(attr, header, timestamps)
                 }
             }
             FileNamespace::DOS => {}
         }
     }
 
-    fn do_update (&mut self, attr: &FileNameAttr, header: &MftAttributeHeader) {
+    fn do_update (&mut self, attr: &FileNameAttr, header: &MftAttributeHeader, timestamps: TimestampTuple) {
         self.filename = attr.name.clone();
         self.namespace = attr.namespace.clone();
-        self.timestamps = TimestampTuple::from(attr);
+        self.timestamps = timestamps;
         self.parent = attr.parent;
         self.logical_size = attr.logical_size;
         self.instance_id = header.instance;
@@ -0,0 +1,83 @@
+use chrono::{DateTime, Utc};
+use std::fmt;
+
+/// number of 100ns intervals per second
+const INTERVALS_PER_SECOND: i128 = 10_000_000;
+
+/// number of 100ns intervals between 1601-01-01 and 1970-01-01
+const UNIX_EPOCH: i128 = 116_444_736_000_000_000;
+
+///
+/// a raw Windows `FILETIME`, which counts 100ns intervals since 1601-01-01.
+/// In contrast to a unix timestamp, it keeps fractions of a second and
+/// dates before 1970. Values above `0x7fffffffffffffff` are treated as
+/// negative, i.e. as dates before 1601, because Windows cannot convert them.
+///
+/// The [`Display`](fmt::Display) implementation writes the number of seconds
+/// since 1970-01-01, with a fraction if necessary, which is the format used
+/// in bodyfiles (e.g. `1630356522.1234567` or `-11644473600`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Filetime(i64);
+
+impl Filetime {
+    pub fn new(filetime: u64) -> Self {
+        Self(filetime as i64)
+    }
+
+    /// returns the raw `FILETIME` value
+    pub fn raw(&self) -> u64 { self.0 as u64 }
+
+    pub fn is_zero(&self) -> bool { self.0 == 0 }
+
+    pub fn is_before_1601(&self) -> bool { self.0 < 0 }
+
+    /// returns the 100ns intervals since 1970-01-01, which are negative for earlier dates
+    fn unix_intervals(&self) -> i128 {
+        self.0 as i128 - UNIX_EPOCH
+    }
+
+    /// returns the whole seconds since 1970-01-01, rounded down
+    pub fn timestamp(&self) -> i64 {
+        self.unix_intervals().div_euclid(INTERVALS_PER_SECOND) as i64
+    }
+
+    /// returns the fraction of the second, in 100ns intervals
+    pub fn subsec_intervals(&self) -> u32 {
+        self.unix_intervals().rem_euclid(INTERVALS_PER_SECOND) as u32
+    }
+
+    pub fn to_datetime(&self) -> Option<DateTime<Utc>> {
+        DateTime::<Utc>::from_timestamp(self.timestamp(), self.subsec_intervals() * 100)
+    }
+}
+
+impl From<u64> for Filetime {
+    fn from(filetime: u64) -> Self {
+        Self::new(filetime)
+    }
+}
+
+impl From<&DateTime<Utc>> for Filetime {
+    /// restores the `FILETIME` from which the `mft` crate has created `datetime`
+    fn from(datetime: &DateTime<Utc>) -> Self {
+        let intervals = datetime.timestamp() as i128 * INTERVALS_PER_SECOND
+            + (datetime.timestamp_subsec_nanos() / 100) as i128
+            + UNIX_EPOCH;
+        Self(intervals.clamp(i64::MIN as i128, i64::MAX as i128) as i64)
+    }
+}
+
+impl fmt::Display for Filetime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let intervals = self.unix_intervals();
+        let sign = if intervals < 0 { "-" } else { "" };
+        let seconds = intervals.abs() / INTERVALS_PER_SECOND;
+        let fraction = intervals.abs() % INTERVALS_PER_SECOND;
+        if fraction == 0 {
+            write!(f, "{}{}", sign, seconds)
+        } else {
+            let fraction = format!("{:07}", fraction);
+            write!(f, "{}{}.{}", sign, seconds, fraction.trim_end_matches('0'))
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use byteorder::{ByteOrder, LittleEndian};
+
+/// size of the sectors which are protected by the update sequence array
+const SECTOR_SIZE: usize = 512;
+
+/// replaces the last two bytes of every sector of a multi-sector record
+/// (like INDX or RCRD records) with the values stored in its update sequence
+/// array. Returns `false` if the record is incomplete, i.e. if not all of its
+/// sectors have been written at the same time.
+pub(crate) fn apply_fixups(record: &mut [u8]) -> bool {
+    let usa_offset = LittleEndian::read_u16(&record[0x04..]) as usize;
+    let usa_count = LittleEndian::read_u16(&record[0x06..]) as usize;
+    if usa_count != record.len() / SECTOR_SIZE + 1 || usa_offset + 2 * usa_count > record.len() {
+        return false;
+    }
+
+    let usn = [record[usa_offset], record[usa_offset + 1]];
+    for sector in 1..usa_count {
+        let end = sector * SECTOR_SIZE;
+        if record[end - 2..end] != usn {
+            return false;
+        }
+        let fixup = usa_offset + 2 * sector;
+        record[end - 2] = record[fixup];
+        record[end - 1] = record[fixup + 1];
+    }
+    true
+}
@@ -0,0 +1,428 @@
+use anyhow::{Result, anyhow, bail};
+use byteorder::{ByteOrder, LittleEndian};
+use flate2::read::ZlibDecoder;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use super::read_bytes;
+use super::image_format::{EWF1_SIGNATURE, EWF2_SIGNATURE};
+
+
+const EWF1_FILE_HEADER_SIZE: u64 = 13;
+const EWF1_SECTION_DESCRIPTOR_SIZE: usize = 76;
+const EWF2_FILE_HEADER_SIZE: u64 = 32;
+const EWF2_SECTION_DESCRIPTOR_SIZE: usize = 64;
+
+const EWF2_SECTION_DEVICE_INFORMATION: u32 = 0x01;
+const EWF2_SECTION_CASE_DATA: u32 = 0x02;
+const EWF2_SECTION_SECTOR_TABLE: u32 = 0x04;
+const EWF2_SECTION_NEXT: u32 = 0x0d;
+
+const EWF2_CHUNK_COMPRESSED: u32 = 0x01;
+const EWF2_CHUNK_PATTERN_FILL: u32 = 0x04;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum EwfVersion {
+    V1,
+    V2,
+}
+
+enum ChunkData {
+    Stored { segment: usize, offset: u64, size: u64 },
+    Compressed { segment: usize, offset: u64, size: u64 },
+    Pattern([u8; 8]),
+}
+
+///
+/// Reads the media data stored in an Expert Witness Compression Format
+/// container (`.E01` or `.Ex01`). All segment files (`.E02`, `.E03`, ...)
+/// are opened automatically.
+pub struct EwfReader {
+    segments: Vec<File>,
+    chunks: Vec<ChunkData>,
+    chunk_size: u64,
+    size: u64,
+    position: u64,
+    cached_chunk: Option<(usize, Vec<u8>)>,
+}
+
+/// the properties of the media, which are stored in the first segment file
+#[derive(Default)]
+struct MediaInfo {
+    sectors_per_chunk: u64,
+    bytes_per_sector: u64,
+    number_of_sectors: u64,
+}
+
+impl EwfReader {
+    pub fn open(first_segment: &Path) -> Result<Self> {
+        let mut signature = [0; 8];
+        File::open(first_segment)?.read_exact(&mut signature)?;
+        let version = match &signature[..] {
+            EWF1_SIGNATURE => EwfVersion::V1,
+            EWF2_SIGNATURE => EwfVersion::V2,
+            _ => bail!("{} is not an EWF file", first_segment.display()),
+        };
+
+        let mut reader = Self {
+            segments: Vec::new(),
+            chunks: Vec::new(),
+            chunk_size: 0,
+            size: 0,
+            position: 0,
+            cached_chunk: None,
+        };
+        let mut media_info = MediaInfo::default();
+
+        for segment_path in SegmentFiles::new(first_segment, version)? {
+            let mut segment = File::open(&segment_path)?;
+            let segment_idx = reader.segments.len();
+            log::info!("reading EWF segment {}", segment_path.display());
+            let is_last_segment = match version {
+                EwfVersion::V1 => reader.read_ewf1_segment(&mut segment, segment_idx, &mut media_info)?,
+                EwfVersion::V2 => reader.read_ewf2_segment(&mut segment, segment_idx, &mut media_info)?,
+            };
+            reader.segments.push(segment);
+            if is_last_segment {
+                break;
+            }
+        }
+
+        if media_info.sectors_per_chunk == 0 || media_info.bytes_per_sector == 0 {
+            bail!("missing media information in {}", first_segment.display());
+        }
+        reader.chunk_size = media_info.sectors_per_chunk * media_info.bytes_per_sector;
+        let chunks_size = reader.chunks.len() as u64 * reader.chunk_size;
+        reader.size = match media_info.number_of_sectors * media_info.bytes_per_sector {
+            0 => chunks_size,
+            size => std::cmp::min(size, chunks_size),
+        };
+        Ok(reader)
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// reads all sections of an EWF1 segment file. Returns `true` if this is the last segment.
+    fn read_ewf1_segment(&mut self, segment: &mut File, segment_idx: usize, media_info: &mut MediaInfo) -> Result<bool> {
+        let segment_size = segment.seek(SeekFrom::End(0))?;
+        let mut offset = EWF1_FILE_HEADER_SIZE;
+        let mut sectors_end = None;
+
+        while offset + EWF1_SECTION_DESCRIPTOR_SIZE as u64 <= segment_size {
+            let descriptor = read_bytes(segment, offset, EWF1_SECTION_DESCRIPTOR_SIZE)?;
+            if adler32(&descriptor[..72]) != LittleEndian::read_u32(&descriptor[72..76]) {
+                bail!("invalid checksum of EWF section descriptor at offset {:#x}", offset);
+            }
+            let section_type: String = descriptor[0..16].iter().take_while(|c| **c != 0).map(|c| *c as char).collect();
+            let next_offset = LittleEndian::read_u64(&descriptor[16..24]);
+            let section_size = LittleEndian::read_u64(&descriptor[24..32]);
+            let data_offset = offset + EWF1_SECTION_DESCRIPTOR_SIZE as u64;
+
+            match &section_type[..] {
+                "volume" | "disk" | "data" => {
+                    let data = read_bytes(segment, data_offset, 24)?;
+                    if media_info.sectors_per_chunk == 0 {
+                        media_info.sectors_per_chunk = LittleEndian::read_u32(&data[8..12]) as u64;
+                        media_info.bytes_per_sector = LittleEndian::read_u32(&data[12..16]) as u64;
+                        media_info.number_of_sectors = LittleEndian::read_u64(&data[16..24]);
+                    }
+                }
+                "sectors" => sectors_end = Some(offset + section_size),
+                "table" => {
+                    let header = read_bytes(segment, data_offset, 24)?;
+                    let entries_count = LittleEndian::read_u32(&header[0..4]) as u64;
+                    let base_offset = LittleEndian::read_u64(&header[8..16]);
+                    if entries_count * 4 > segment_size.saturating_sub(data_offset + 24) {
+                        bail!("invalid number of table entries in EWF segment {}: {}", segment_idx + 1, entries_count);
+                    }
+                    let entries = read_bytes(segment, data_offset + 24, entries_count as usize * 4)?;
+                    let offsets: Vec<(u64, bool)> = entries.chunks(4)
+                        .map(LittleEndian::read_u32)
+                        .map(|e| (base_offset + (e & 0x7fff_ffff) as u64, e & 0x8000_0000 != 0))
+                        .collect();
+
+                    /* the last chunk ends where the sectors section ends */
+                    let last_chunk_end = sectors_end.unwrap_or(offset);
+                    for (idx, (chunk_offset, is_compressed)) in offsets.iter().enumerate() {
+                        let chunk_end = offsets.get(idx + 1).map(|(o, _)| *o).unwrap_or(last_chunk_end);
+                        if chunk_end < *chunk_offset {
+                            bail!("invalid chunk offset {:#x} in EWF segment {}", chunk_offset, segment_idx + 1);
+                        }
+                        let (offset, size) = (*chunk_offset, chunk_end - chunk_offset);
+                        self.chunks.push(if *is_compressed {
+                            ChunkData::Compressed { segment: segment_idx, offset, size }
+                        } else {
+                            ChunkData::Stored { segment: segment_idx, offset, size }
+                        });
+                    }
+                }
+                "next" => return Ok(false),
+                "done" => return Ok(true),
+                _ => (),
+            }
+
+            if next_offset <= offset {
+                break;
+            }
+            offset = next_offset;
+        }
+        log::warn!("EWF segment {} is not terminated by a 'next' or 'done' section", segment_idx + 1);
+        Ok(true)
+    }
+
+    /// reads all sections of an EWF2 segment file. In EWF2, the section
+    /// descriptor follows the section data, so we start reading at the end of the file.
+    /// Returns `true` if this is the last segment.
+    fn read_ewf2_segment(&mut self, segment: &mut File, segment_idx: usize, media_info: &mut MediaInfo) -> Result<bool> {
+        let header = read_bytes(segment, 0, EWF2_FILE_HEADER_SIZE as usize)?;
+        let compression_method = LittleEndian::read_u16(&header[10..12]);
+        if compression_method > 1 {
+            bail!("unsupported EWF2 compression method: {}", compression_method);
+        }
+
+        let segment_size = segment.seek(SeekFrom::End(0))?;
+        if segment_size < EWF2_FILE_HEADER_SIZE + EWF2_SECTION_DESCRIPTOR_SIZE as u64 {
+            bail!("EWF segment {} is too small", segment_idx + 1);
+        }
+
+        let mut sections = Vec::new();
+        let mut offset = segment_size - EWF2_SECTION_DESCRIPTOR_SIZE as u64;
+        loop {
+            let descriptor = read_bytes(segment, offset, EWF2_SECTION_DESCRIPTOR_SIZE)?;
+            if adler32(&descriptor[..60]) != LittleEndian::read_u32(&descriptor[60..64]) {
+                bail!("invalid checksum of EWF2 section descriptor at offset {:#x}", offset);
+            }
+            let section_type = LittleEndian::read_u32(&descriptor[0..4]);
+            let previous_offset = LittleEndian::read_u64(&descriptor[8..16]);
+            let data_size = LittleEndian::read_u64(&descriptor[16..24]);
+
+            /* the section data are stored between the previous descriptor and this one */
+            let data_offset = if previous_offset == 0 {
+                EWF2_FILE_HEADER_SIZE
+            } else {
+                previous_offset + EWF2_SECTION_DESCRIPTOR_SIZE as u64
+            };
+            sections.push((section_type, data_offset, std::cmp::min(data_size, offset - data_offset)));
+
+            if previous_offset == 0 || previous_offset >= offset {
+                break;
+            }
+            offset = previous_offset;
+        }
+        sections.reverse();
+
+        let mut is_last_segment = true;
+        for (section_type, data_offset, data_size) in sections {
+            match section_type {
+                EWF2_SECTION_DEVICE_INFORMATION => {
+                    for (key, value) in read_ewf2_properties(segment, data_offset, data_size)? {
+                        match &key[..] {
+                            "bp" => media_info.bytes_per_sector = value.parse()?,
+                            "ts" => media_info.number_of_sectors = value.parse()?,
+                            _ => (),
+                        }
+                    }
+                }
+                EWF2_SECTION_CASE_DATA => {
+                    for (key, value) in read_ewf2_properties(segment, data_offset, data_size)? {
+                        if key == "sb" {
+                            media_info.sectors_per_chunk = value.parse()?;
+                        }
+                    }
+                }
+                EWF2_SECTION_SECTOR_TABLE => {
+                    let header = read_bytes(segment, data_offset, 32)?;
+                    let entries_count = LittleEndian::read_u32(&header[8..12]) as u64;
+                    if entries_count * 16 > data_size.saturating_sub(32) {
+                        bail!("invalid number of table entries in EWF segment {}: {}", segment_idx + 1, entries_count);
+                    }
+                    let entries = read_bytes(segment, data_offset + 32, entries_count as usize * 16)?;
+                    for entry in entries.chunks(16) {
+                        let offset = LittleEndian::read_u64(&entry[0..8]);
+                        let size = LittleEndian::read_u32(&entry[8..12]) as u64;
+                        let flags = LittleEndian::read_u32(&entry[12..16]);
+                        self.chunks.push(if flags & EWF2_CHUNK_PATTERN_FILL != 0 {
+                            let mut pattern = [0; 8];
+                            pattern.copy_from_slice(&entry[0..8]);
+                            ChunkData::Pattern(pattern)
+                        } else if flags & EWF2_CHUNK_COMPRESSED != 0 {
+                            ChunkData::Compressed { segment: segment_idx, offset, size }
+                        } else {
+                            ChunkData::Stored { segment: segment_idx, offset, size }
+                        });
+                    }
+                }
+                EWF2_SECTION_NEXT => is_last_segment = false,
+                _ => (),
+            }
+        }
+        Ok(is_last_segment)
+    }
+
+    fn read_chunk(&mut self, chunk_idx: usize) -> io::Result<Vec<u8>> {
+        let chunk_size = self.chunk_size as usize;
+        let mut data = match &self.chunks[chunk_idx] {
+            ChunkData::Pattern(pattern) => pattern.iter().cycle().take(chunk_size).cloned().collect(),
+            ChunkData::Stored { segment, offset, size } => {
+                /* stored chunks are followed by a checksum, which we simply ignore */
+                let size = std::cmp::min(*size as usize, chunk_size);
+                read_bytes(&mut self.segments[*segment], *offset, size)
+                    .map_err(|why| io::Error::other(why.to_string()))?
+            }
+            ChunkData::Compressed { segment, offset, size } => {
+                let compressed = read_bytes(&mut self.segments[*segment], *offset, *size as usize)
+                    .map_err(|why| io::Error::other(why.to_string()))?;
+                let mut data = Vec::with_capacity(chunk_size);
+                ZlibDecoder::new(&compressed[..]).read_to_end(&mut data)?;
+                data
+            }
+        };
+        data.resize(chunk_size, 0);
+        Ok(data)
+    }
+}
+
+impl Read for EwfReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position >= self.size {
+            return Ok(0);
+        }
+        let chunk_idx = (self.position / self.chunk_size) as usize;
+        let chunk_offset = (self.position % self.chunk_size) as usize;
+
+        if !matches!(self.cached_chunk, Some((idx, _)) if idx == chunk_idx) {
+            let data = self.read_chunk(chunk_idx)?;
+            self.cached_chunk = Some((chunk_idx, data));
+        }
+        let chunk = &self.cached_chunk.as_ref().unwrap().1;
+
+        let count = std::cmp::min(
+            std::cmp::min(buf.len(), chunk.len() - chunk_offset),
+            (self.size - self.position) as usize);
+        buf[..count].copy_from_slice(&chunk[chunk_offset..chunk_offset + count]);
+        self.position += count as u64;
+        Ok(count)
+    }
+}
+
+impl Seek for EwfReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.position = match pos {
+            SeekFrom::Start(p) => p,
+            SeekFrom::Current(p) => super::offset_position(self.position, p)?,
+            SeekFrom::End(p) => super::offset_position(self.size, p)?,
+        };
+        Ok(self.position)
+    }
+}
+
+///
+/// iterates over the names of all segment files which belong to the
+/// same image, starting with the first segment file. The extensions are
+/// `E01` .. `E99`, `EAA` .. `EZZ`, `FAA` .. (or `Ex01` .. `Ex99`, `ExAA` ..).
+/// Iteration stops at the first segment file which does not exist.
+struct SegmentFiles {
+    first_segment: PathBuf,
+    prefix: String,
+    next_segment: usize,
+}
+
+impl SegmentFiles {
+    fn new(first_segment: &Path, version: EwfVersion) -> Result<Self> {
+        let extension = first_segment
+            .extension()
+            .map(|e| e.to_string_lossy().to_string())
+            .ok_or_else(|| anyhow!("missing file extension of {}", first_segment.display()))?;
+        let prefix_len = match version {
+            EwfVersion::V1 => 1,
+            EwfVersion::V2 => 2,
+        };
+        if extension.len() != prefix_len + 2 || !extension.is_ascii() {
+            /* we are not able to guess the names of other segments */
+            log::warn!("unexpected file extension of {}, reading only this segment", first_segment.display());
+        }
+        Ok(Self {
+            first_segment: first_segment.to_path_buf(),
+            prefix: extension.chars().take(prefix_len).collect(),
+            next_segment: 1,
+        })
+    }
+
+    fn extension(&self, segment: usize) -> Option<String> {
+        if segment < 100 {
+            return Some(format!("{}{:02}", self.prefix, segment));
+        }
+        let idx = segment - 100;
+        let letters = [(idx / 26 / 26) as u8, (idx / 26 % 26) as u8, (idx % 26) as u8];
+
+        /* the first letter of the prefix is increased after ZZ, keeping its case */
+        let mut prefix: Vec<char> = self.prefix.chars().collect();
+        let first = prefix[0];
+        let base = if first.is_ascii_lowercase() { b'a' } else { b'A' };
+        prefix[0] = (first.to_ascii_uppercase() as u8).checked_add(letters[0])? as char;
+        if !prefix[0].is_ascii_uppercase() {
+            return None;
+        }
+        prefix[0] = (prefix[0] as u8 - b'A' + base) as char;
+
+        /* the letters which replace the digits use the case of the last letter of the prefix */
+        let base = if prefix[prefix.len() - 1].is_ascii_lowercase() { b'a' } else { b'A' };
+        let prefix: String = prefix.into_iter().collect();
+        Some(format!("{}{}{}", prefix, (base + letters[1]) as char, (base + letters[2]) as char))
+    }
+}
+
+impl Iterator for SegmentFiles {
+    type Item = PathBuf;
+    fn next(&mut self) -> Option<Self::Item> {
+        let segment = if self.next_segment == 1 {
+            self.first_segment.clone()
+        } else {
+            let path = self.first_segment.with_extension(self.extension(self.next_segment)?);
+            if !path.exists() {
+                log::warn!("missing EWF segment file {}", path.display());
+                return None;
+            }
+            path
+        };
+        self.next_segment += 1;
+        Some(segment)
+    }
+}
+
+/// reads the key/value pairs of an EWF2 device information or case data section,
+/// which are stored as zlib compressed UTF-16 text
+fn read_ewf2_properties(segment: &mut File, offset: u64, size: u64) -> Result<Vec<(String, String)>> {
+    let compressed = read_bytes(segment, offset, size as usize)?;
+    let mut data = Vec::new();
+    if ZlibDecoder::new(&compressed[..]).read_to_end(&mut data).is_err() {
+        data = compressed;
+    }
+    let text: Vec<u16> = data.chunks_exact(2).map(LittleEndian::read_u16).collect();
+    let text = String::from_utf16_lossy(&text);
+    let lines: Vec<&str> = text.trim_start_matches('\u{feff}').lines().collect();
+
+    /* the first lines contain the number of categories and the category name */
+    if lines.len() < 4 {
+        return Ok(Vec::new());
+    }
+    Ok(lines[2].split('\t')
+        .zip(lines[3].split('\t'))
+        .map(|(k, v)| (k.trim().to_owned(), v.trim().to_owned()))
+        .collect())
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(5552) {
+        for byte in chunk {
+            a += *byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
@@ -0,0 +1,102 @@
+use super::{ReadSeek, SplitRawReader, VhdReader, VhdxReader};
+use super::vhd::VHD_FOOTER_SIGNATURE;
+use super::vhdx::VHDX_SIGNATURE;
+use anyhow::Result;
+use std::fmt;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+pub(crate) const EWF1_SIGNATURE: &[u8] = b"EVF\x09\x0d\x0a\xff\x00";
+pub(crate) const EWF2_SIGNATURE: &[u8] = b"EVF2\x0d\x0a\x81\x00";
+
+///
+/// the container format of a disk or volume image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Raw,
+    SplitRaw,
+    Ewf,
+    Vhd,
+    Vhdx,
+}
+
+impl ImageFormat {
+    ///
+    /// detects the format of an image by its signature. Because raw images
+    /// have no signature, split raw images are detected by the existence of a
+    /// second segment (e.g. `image.002` besides `image.001`) of the same size.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let mut file = File::open(path)?;
+        let mut signature = [0; 8];
+        let bytes = file.read(&mut signature)?;
+        let signature = &signature[..bytes];
+
+        if signature == EWF1_SIGNATURE || signature == EWF2_SIGNATURE {
+            return Ok(Self::Ewf);
+        }
+        if signature == VHDX_SIGNATURE {
+            return Ok(Self::Vhdx);
+        }
+
+        /* fixed VHD files have no header, only a footer */
+        let file_size = file.seek(SeekFrom::End(0))?;
+        if file_size >= 512 {
+            let mut footer = [0; 8];
+            file.seek(SeekFrom::Start(file_size - 512))?;
+            file.read_exact(&mut footer)?;
+            if footer == VHD_FOOTER_SIGNATURE {
+                return Ok(Self::Vhd);
+            }
+        }
+
+        if let Ok(segments) = SplitRawReader::segment_paths(path) {
+            if segments.len() > 1 && Self::have_split_raw_sizes(&segments, file_size)? {
+                return Ok(Self::SplitRaw);
+            }
+        }
+        Ok(Self::Raw)
+    }
+
+    /// checks if all segments but the last have the size of the first
+    /// segment, and the last segment is not larger than that
+    fn have_split_raw_sizes(segments: &[PathBuf], first_size: u64) -> Result<bool> {
+        for (idx, segment) in segments.iter().enumerate().skip(1) {
+            let size = segment.metadata()?.len();
+            let is_last = idx == segments.len() - 1;
+            if size > first_size || (!is_last && size != first_size) {
+                log::info!("not treating {} as split raw image, because {} has a size of {} bytes", segments[0].display(), segment.display(), size);
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// opens `path` as a virtual device of this format
+    pub fn open(&self, path: &Path) -> Result<Box<dyn ReadSeek + Send>> {
+        log::info!("opening {} as {} image", path.display(), self);
+        Ok(match self {
+            Self::Raw => Box::new(File::open(path)?),
+            Self::SplitRaw => Box::new(SplitRawReader::open(path)?),
+            #[cfg(feature = "ewf")]
+            Self::Ewf => Box::new(super::EwfReader::open(path)?),
+            #[cfg(not(feature = "ewf"))]
+            Self::Ewf => anyhow::bail!("EWF support has not been enabled (feature 'ewf')"),
+            Self::Vhd => Box::new(VhdReader::open(path)?),
+            Self::Vhdx => Box::new(VhdxReader::open(path)?),
+        })
+    }
+}
+
+impl fmt::Display for ImageFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Raw => "raw",
+            Self::SplitRaw => "split raw",
+            Self::Ewf => "EWF",
+            Self::Vhd => "VHD",
+            Self::Vhdx => "VHDX",
+        };
+        write!(f, "{}", name)
+    }
+}
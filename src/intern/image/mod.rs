@@ -0,0 +1,84 @@
+mod shared_reader;
+mod partition_reader;
+mod partition_table;
+mod image_format;
+mod split_raw;
+mod vhd;
+mod vhdx;
+#[cfg(feature = "ewf")]
+mod ewf;
+
+pub use shared_reader::SharedReader;
+pub use partition_reader::PartitionReader;
+pub use partition_table::{PartitionTable, PartitionTableType, Partition};
+pub use image_format::ImageFormat;
+pub use split_raw::SplitRawReader;
+pub use vhd::VhdReader;
+pub use vhdx::VhdxReader;
+#[cfg(feature = "ewf")]
+pub use ewf::EwfReader;
+
+use std::io::{Error, ErrorKind, Read, Result, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+pub trait ReadSeek: Read + Seek {}
+impl<T: Read + Seek> ReadSeek for T {}
+
+/// opens a disk or volume image, whose format is detected automatically
+pub fn open_image(image_file: &Path) -> anyhow::Result<Box<dyn ReadSeek + Send>> {
+    ImageFormat::from_path(image_file)?.open(image_file)
+}
+
+/// calculates the new position of a seek operation relative to `base`
+pub(crate) fn offset_position(base: u64, offset: i64) -> Result<u64> {
+    if offset >= 0 {
+        base.checked_add(offset as u64)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "invalid seek beyond the maximum position"))
+    } else {
+        base.checked_sub(offset.unsigned_abs())
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "invalid seek to a negative position"))
+    }
+}
+
+pub(crate) fn read_bytes<R: Read + Seek>(reader: &mut R, offset: u64, size: usize) -> anyhow::Result<Vec<u8>> {
+    let mut buffer = vec![0; size];
+    reader.seek(SeekFrom::Start(offset))?;
+    reader.read_exact(&mut buffer)?;
+    Ok(buffer)
+}
+
+/// reads data which are not stored in a differencing disk from its parent,
+/// or returns zeros if there is no parent
+pub(crate) fn read_parent(parent: &mut Option<Box<dyn ReadSeek + Send>>, position: u64, buf: &mut [u8]) -> Result<usize> {
+    match parent {
+        Some(parent) => {
+            parent.seek(SeekFrom::Start(position))?;
+            parent.read_exact(buf)?;
+        }
+        None => buf.fill(0),
+    }
+    Ok(buf.len())
+}
+
+/// searches the parent of the differencing disk `child`. `locators` contains
+/// relative or absolute Windows paths to the parent; if none of them exists,
+/// the parent is searched in the directory of `child`.
+pub(crate) fn find_parent_image(child: &Path, locators: &[String]) -> anyhow::Result<PathBuf> {
+    let directory = child.parent().unwrap_or_else(|| Path::new("."));
+    let mut candidates = Vec::new();
+    for locator in locators.iter().filter(|l| !l.is_empty()) {
+        let path = locator.replace('\\', "/");
+        let is_absolute = path.starts_with('/') || path.as_bytes().get(1) == Some(&b':');
+        if !is_absolute {
+            candidates.push(directory.join(path.trim_start_matches("./")));
+        } else {
+            candidates.push(PathBuf::from(&path));
+        }
+        if let Some(filename) = path.rsplit('/').next() {
+            candidates.push(directory.join(filename));
+        }
+    }
+    candidates.into_iter()
+        .find(|c| c.is_file())
+        .ok_or_else(|| anyhow::anyhow!("unable to find the parent of {} (tried {:?})", child.display(), locators))
+}
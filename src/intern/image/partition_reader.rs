@@ -0,0 +1,52 @@
+use std::io::{Read, Seek, SeekFrom, Result};
+
+///
+/// Provides access to a contiguous region (e.g. a partition) of some
+/// underlying [`Read`] + [`Seek`] object. Offset `0` of a [`PartitionReader`]
+/// is offset `start` of the underlying reader.
+#[derive(Clone)]
+pub struct PartitionReader<R> where R: Read + Seek {
+    inner: R,
+    start: u64,
+    size: u64,
+    position: u64,
+}
+
+impl<R> PartitionReader<R> where R: Read + Seek {
+    pub fn new(inner: R, start: u64, size: u64) -> Self {
+        Self {
+            inner,
+            start,
+            size,
+            position: 0
+        }
+    }
+
+    pub fn start(&self) -> u64 { self.start }
+    pub fn size(&self) -> u64 { self.size }
+}
+
+impl<R> Read for PartitionReader<R> where R: Read + Seek {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        if self.position >= self.size {
+            return Ok(0);
+        }
+        let remaining = (self.size - self.position) as usize;
+        let count = std::cmp::min(remaining, buf.len());
+        self.inner.seek(SeekFrom::Start(self.start + self.position))?;
+        let bytes = self.inner.read(&mut buf[..count])?;
+        self.position += bytes as u64;
+        Ok(bytes)
+    }
+}
+
+impl<R> Seek for PartitionReader<R> where R: Read + Seek {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        self.position = match pos {
+            SeekFrom::Start(p) => p,
+            SeekFrom::Current(p) => super::offset_position(self.position, p)?,
+            SeekFrom::End(p) => super::offset_position(self.size, p)?,
+        };
+        Ok(self.position)
+    }
+}
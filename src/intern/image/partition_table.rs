@@ -0,0 +1,205 @@
+use anyhow::{Result, bail};
+use byteorder::{ByteOrder, LittleEndian};
+use std::io::{Read, Seek, SeekFrom};
+use super::read_bytes;
+
+const MBR_SIGNATURE_OFFSET: usize = 0x1fe;
+const MBR_PARTITION_TABLE_OFFSET: usize = 0x1be;
+const GPT_SIGNATURE: &[u8] = b"EFI PART";
+const NTFS_OEM_ID: &[u8] = b"NTFS    ";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PartitionTableType {
+    /// the image does not contain a partition table, but starts with a volume boot record
+    None,
+    Mbr,
+    Gpt,
+}
+
+#[derive(Debug, Clone)]
+pub struct Partition {
+    index: usize,
+    start: u64,
+    size: u64,
+    description: String,
+}
+
+impl Partition {
+    /// number of the partition, starting with `1`
+    pub fn index(&self) -> usize { self.index }
+
+    /// offset of the partition (in bytes)
+    pub fn start(&self) -> u64 { self.start }
+
+    /// size of the partition (in bytes)
+    pub fn size(&self) -> u64 { self.size }
+    pub fn description(&self) -> &str { &self.description }
+}
+
+///
+/// Contains all partitions found in a disk image. Partitions can be
+/// described by an MBR (including extended partitions) or by a GPT. If the
+/// image starts with an NTFS boot sector, the whole image is considered to be
+/// one single partition.
+pub struct PartitionTable {
+    table_type: PartitionTableType,
+    partitions: Vec<Partition>,
+}
+
+impl PartitionTable {
+    pub fn from_reader<R: Read + Seek>(reader: &mut R) -> Result<Self> {
+        let image_size = reader.seek(SeekFrom::End(0))?;
+        let sector0 = read_bytes(reader, 0, 512)?;
+
+        if &sector0[3..11] == NTFS_OEM_ID {
+            return Ok(Self {
+                table_type: PartitionTableType::None,
+                partitions: vec![Partition {
+                    index: 1,
+                    start: 0,
+                    size: image_size,
+                    description: "NTFS volume".to_owned(),
+                }]
+            });
+        }
+
+        if sector0[MBR_SIGNATURE_OFFSET..MBR_SIGNATURE_OFFSET+2] != [0x55, 0xaa] {
+            bail!("the image neither contains a partition table nor an NTFS volume");
+        }
+
+        let mbr_partitions = Self::read_mbr_entries(&sector0);
+        if mbr_partitions.iter().any(|(partition_type, _, _)| *partition_type == 0xee) {
+            for sector_size in &[512, 4096] {
+                let header = read_bytes(reader, *sector_size, 512)?;
+                if &header[0..8] == GPT_SIGNATURE {
+                    return Ok(Self {
+                        table_type: PartitionTableType::Gpt,
+                        partitions: Self::read_gpt(reader, &header, *sector_size)?
+                    });
+                }
+            }
+            bail!("found a protective MBR, but no GPT header");
+        }
+
+        let mut partitions = Vec::new();
+        for (partition_type, lba, count) in mbr_partitions.into_iter() {
+            match partition_type {
+                0x00 => (),
+                0x05 | 0x0f | 0x85 => {
+                    Self::read_extended_partitions(reader, lba, &mut partitions)?
+                }
+                _ => partitions.push(Partition {
+                    index: partitions.len() + 1,
+                    start: lba * 512,
+                    size: count * 512,
+                    description: format!("MBR partition of type {:#04x}", partition_type),
+                }),
+            }
+        }
+
+        Ok(Self {
+            table_type: PartitionTableType::Mbr,
+            partitions
+        })
+    }
+
+    pub fn table_type(&self) -> PartitionTableType {
+        self.table_type
+    }
+
+    pub fn partitions(&self) -> &Vec<Partition> {
+        &self.partitions
+    }
+
+    /// returns `(type, first lba, number of sectors)` of all four MBR entries
+    fn read_mbr_entries(sector: &[u8]) -> Vec<(u8, u64, u64)> {
+        (0..4).map(|idx| {
+            let entry = &sector[MBR_PARTITION_TABLE_OFFSET + idx*16 .. MBR_PARTITION_TABLE_OFFSET + (idx+1)*16];
+            (entry[4],
+            LittleEndian::read_u32(&entry[8..12]) as u64,
+            LittleEndian::read_u32(&entry[12..16]) as u64)
+        }).collect()
+    }
+
+    /// follows the chain of extended boot records. Logical partitions
+    /// are relative to the current EBR, next EBRs are relative to the first EBR.
+    fn read_extended_partitions<R: Read + Seek>(reader: &mut R, first_ebr: u64, partitions: &mut Vec<Partition>) -> Result<()> {
+        let mut current_ebr = first_ebr;
+
+        /* limit the number of logical partitions to prevent endless loops */
+        for _ in 0..128 {
+            let sector = read_bytes(reader, current_ebr * 512, 512)?;
+            if sector[MBR_SIGNATURE_OFFSET..MBR_SIGNATURE_OFFSET+2] != [0x55, 0xaa] {
+                log::warn!("invalid extended boot record at sector {}", current_ebr);
+                break;
+            }
+            let entries = Self::read_mbr_entries(&sector);
+            let (logical_type, logical_lba, logical_count) = entries[0];
+            if logical_type != 0 {
+                partitions.push(Partition {
+                    index: partitions.len() + 1,
+                    start: (current_ebr + logical_lba) * 512,
+                    size: logical_count * 512,
+                    description: format!("logical partition of type {:#04x}", logical_type),
+                });
+            }
+
+            let (next_type, next_lba, _) = entries[1];
+            if next_type == 0 || next_lba == 0 {
+                break;
+            }
+            current_ebr = first_ebr + next_lba;
+        }
+        Ok(())
+    }
+
+    fn read_gpt<R: Read + Seek>(reader: &mut R, header: &[u8], sector_size: u64) -> Result<Vec<Partition>> {
+        let entries_lba = LittleEndian::read_u64(&header[72..80]);
+        let entries_count = LittleEndian::read_u32(&header[80..84]) as u64;
+        let entry_size = LittleEndian::read_u32(&header[84..88]) as u64;
+
+        if !(128..=4096).contains(&entry_size) || entries_count > 1024 {
+            bail!("invalid GPT header: {} entries of size {}", entries_count, entry_size);
+        }
+
+        let entries_offset = match entries_lba.checked_mul(sector_size) {
+            Some(offset) => offset,
+            None => bail!("invalid GPT header: partition entries at LBA {}", entries_lba),
+        };
+        let entries = read_bytes(reader, entries_offset, (entries_count * entry_size) as usize)?;
+        let mut partitions = Vec::new();
+        for entry in entries.chunks(entry_size as usize) {
+            if entry[0..16].iter().all(|b| *b == 0) {
+                continue;
+            }
+            let first_lba = LittleEndian::read_u64(&entry[32..40]);
+            let last_lba = LittleEndian::read_u64(&entry[40..48]);
+            let name: Vec<u16> = entry[56..128].chunks(2)
+                .map(LittleEndian::read_u16)
+                .take_while(|c| *c != 0)
+                .collect();
+            let name = String::from_utf16_lossy(&name);
+
+            /* a corrupt entry must not abort the search for other partitions */
+            let bounds = last_lba
+                .checked_sub(first_lba)
+                .and_then(|count| count.checked_add(1))
+                .and_then(|count| count.checked_mul(sector_size))
+                .and_then(|size| first_lba.checked_mul(sector_size).map(|start| (start, size)));
+            let (start, size) = match bounds {
+                Some(bounds) => bounds,
+                None => {
+                    log::warn!("ignoring GPT partition '{}' with invalid bounds {}..{}", name, first_lba, last_lba);
+                    continue;
+                }
+            };
+            partitions.push(Partition {
+                index: partitions.len() + 1,
+                start,
+                size,
+                description: format!("GPT partition '{}'", name),
+            });
+        }
+        Ok(partitions)
+    }
+}
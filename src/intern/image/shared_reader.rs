@@ -0,0 +1,53 @@
+use std::io::{Read, Seek, SeekFrom, Result};
+use std::sync::{Arc, Mutex};
+
+///
+/// Wraps a [`Read`] + [`Seek`] object so that it can be used from different
+/// places (and threads) at the same time. Every clone has its own
+/// position, the underlying reader is only locked during a single read or seek.
+pub struct SharedReader<R> where R: Read + Seek {
+    inner: Arc<Mutex<R>>,
+    position: u64,
+}
+
+impl<R> SharedReader<R> where R: Read + Seek {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(inner)),
+            position: 0
+        }
+    }
+}
+
+impl<R> Clone for SharedReader<R> where R: Read + Seek {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+            position: self.position
+        }
+    }
+}
+
+impl<R> Read for SharedReader<R> where R: Read + Seek {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.seek(SeekFrom::Start(self.position))?;
+        let bytes = inner.read(buf)?;
+        self.position += bytes as u64;
+        Ok(bytes)
+    }
+}
+
+impl<R> Seek for SharedReader<R> where R: Read + Seek {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        self.position = match pos {
+            SeekFrom::Start(p) => p,
+            SeekFrom::Current(p) => super::offset_position(self.position, p)?,
+            SeekFrom::End(p) => {
+                let end = self.inner.lock().unwrap().seek(SeekFrom::End(0))?;
+                super::offset_position(end, p)?
+            }
+        };
+        Ok(self.position)
+    }
+}
@@ -0,0 +1,89 @@
+use anyhow::{Result, bail};
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+///
+/// Concatenates the segments of a split raw image (`image.001`, `image.002`, ...)
+/// to a single virtual device.
+pub struct SplitRawReader {
+    segments: Vec<(File, u64)>,
+    size: u64,
+    position: u64,
+}
+
+impl SplitRawReader {
+    pub fn open(first_segment: &Path) -> Result<Self> {
+        let paths = Self::segment_paths(first_segment)?;
+        let mut segments = Vec::new();
+        let mut size = 0;
+        for path in paths {
+            let mut file = File::open(&path)?;
+            let segment_size = file.seek(SeekFrom::End(0))?;
+            size += segment_size;
+            segments.push((file, segment_size));
+        }
+        log::info!("found {} segments of {}", segments.len(), first_segment.display());
+        Ok(Self {
+            segments,
+            size,
+            position: 0,
+        })
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// returns the names of all segments which belong to the same image as `first_segment`
+    pub fn segment_paths(first_segment: &Path) -> Result<Vec<PathBuf>> {
+        let extension = match first_segment.extension() {
+            Some(extension) => extension.to_string_lossy().to_string(),
+            None => bail!("missing file extension of {}", first_segment.display()),
+        };
+        if extension.is_empty() || !extension.chars().all(|c| c.is_ascii_digit()) {
+            bail!("{} is not the first segment of a split raw image", first_segment.display());
+        }
+        let first_number: usize = extension.parse()?;
+        let width = extension.len();
+
+        let mut paths = vec![first_segment.to_path_buf()];
+        for number in first_number + 1.. {
+            let path = first_segment.with_extension(format!("{:0width$}", number, width = width));
+            if !path.exists() {
+                break;
+            }
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+}
+
+impl Read for SplitRawReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut segment_start = 0;
+        for (file, segment_size) in self.segments.iter_mut() {
+            if self.position < segment_start + *segment_size {
+                let offset = self.position - segment_start;
+                let count = std::cmp::min(buf.len() as u64, *segment_size - offset) as usize;
+                file.seek(SeekFrom::Start(offset))?;
+                let bytes = file.read(&mut buf[..count])?;
+                self.position += bytes as u64;
+                return Ok(bytes);
+            }
+            segment_start += *segment_size;
+        }
+        Ok(0)
+    }
+}
+
+impl Seek for SplitRawReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.position = match pos {
+            SeekFrom::Start(p) => p,
+            SeekFrom::Current(p) => super::offset_position(self.position, p)?,
+            SeekFrom::End(p) => super::offset_position(self.size, p)?,
+        };
+        Ok(self.position)
+    }
+}
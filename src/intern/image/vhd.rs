@@ -0,0 +1,206 @@
+use super::{ReadSeek, read_bytes, read_parent};
+use anyhow::{Result, bail};
+use byteorder::{BigEndian, ByteOrder};
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+pub const VHD_FOOTER_SIGNATURE: &[u8] = b"conectix";
+const VHD_DYNAMIC_HEADER_SIGNATURE: &[u8] = b"cxsparse";
+const VHD_FOOTER_SIZE: u64 = 512;
+const VHD_SECTOR_SIZE: u64 = 512;
+
+const VHD_DISK_TYPE_FIXED: u32 = 2;
+const VHD_DISK_TYPE_DYNAMIC: u32 = 3;
+const VHD_DISK_TYPE_DIFFERENCING: u32 = 4;
+
+const VHD_UNUSED_BLOCK: u32 = 0xffff_ffff;
+
+const VHD_PLATFORM_CODE_W2RU: u32 = 0x5732_7275;
+const VHD_PLATFORM_CODE_W2KU: u32 = 0x5732_6b75;
+
+enum VhdLayout {
+    Fixed,
+    Dynamic {
+        bat: Vec<u32>,
+        block_size: u64,
+        bitmap_size: u64,
+        parent: Option<Box<dyn ReadSeek + Send>>,
+    },
+}
+
+///
+/// Provides access to the virtual disk stored in a VHD file. Fixed, dynamic
+/// and differencing disks are supported; the parent of a differencing disk is
+/// opened automatically.
+pub struct VhdReader {
+    file: File,
+    layout: VhdLayout,
+    size: u64,
+    position: u64,
+    cached_bitmap: Option<(usize, Vec<u8>)>,
+}
+
+impl VhdReader {
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut file = File::open(path)?;
+        let file_size = file.seek(SeekFrom::End(0))?;
+        if file_size < VHD_FOOTER_SIZE {
+            bail!("{} is too small to be a VHD file", path.display());
+        }
+        let footer = read_bytes(&mut file, file_size - VHD_FOOTER_SIZE, VHD_FOOTER_SIZE as usize)?;
+        if &footer[0..8] != VHD_FOOTER_SIGNATURE {
+            bail!("{} has no valid VHD footer", path.display());
+        }
+
+        let size = BigEndian::read_u64(&footer[48..56]);
+        let disk_type = BigEndian::read_u32(&footer[60..64]);
+        let layout = match disk_type {
+            VHD_DISK_TYPE_FIXED => {
+                if file_size - VHD_FOOTER_SIZE < size {
+                    bail!("{} is truncated", path.display());
+                }
+                VhdLayout::Fixed
+            }
+            VHD_DISK_TYPE_DYNAMIC | VHD_DISK_TYPE_DIFFERENCING => {
+                let header_offset = BigEndian::read_u64(&footer[16..24]);
+                let header = read_bytes(&mut file, header_offset, 1024)?;
+                if &header[0..8] != VHD_DYNAMIC_HEADER_SIGNATURE {
+                    bail!("{} has no valid dynamic disk header", path.display());
+                }
+                let bat_offset = BigEndian::read_u64(&header[16..24]);
+                let bat_entries = BigEndian::read_u32(&header[28..32]) as usize;
+                let block_size = BigEndian::read_u32(&header[32..36]) as u64;
+                if block_size == 0 || !block_size.is_multiple_of(VHD_SECTOR_SIZE) {
+                    bail!("invalid VHD block size: {}", block_size);
+                }
+                let bat = read_bytes(&mut file, bat_offset, bat_entries * 4)?
+                    .chunks(4)
+                    .map(BigEndian::read_u32)
+                    .collect();
+
+                /* the sector bitmap is padded to a full sector */
+                let bitmap_size = (block_size / VHD_SECTOR_SIZE).div_ceil(8).div_ceil(VHD_SECTOR_SIZE) * VHD_SECTOR_SIZE;
+
+                let parent = if disk_type == VHD_DISK_TYPE_DIFFERENCING {
+                    let parent_path = super::find_parent_image(path, &Self::parent_locators(&mut file, &header)?)?;
+                    log::info!("{} is a differencing disk with parent {}", path.display(), parent_path.display());
+                    Some(super::open_image(&parent_path)?)
+                } else {
+                    None
+                };
+                VhdLayout::Dynamic { bat, block_size, bitmap_size, parent }
+            }
+            _ => bail!("unsupported VHD disk type: {}", disk_type),
+        };
+
+        Ok(Self {
+            file,
+            layout,
+            size,
+            position: 0,
+            cached_bitmap: None,
+        })
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// returns all possible names of the parent disk, in the order of preference
+    fn parent_locators(file: &mut File, header: &[u8]) -> Result<Vec<String>> {
+        let mut locators = Vec::new();
+        for entry in header[576..768].chunks(24) {
+            let platform_code = BigEndian::read_u32(&entry[0..4]);
+            let data_length = BigEndian::read_u32(&entry[8..12]) as usize;
+            let data_offset = BigEndian::read_u64(&entry[16..24]);
+            if platform_code == VHD_PLATFORM_CODE_W2RU || platform_code == VHD_PLATFORM_CODE_W2KU {
+                let data = read_bytes(file, data_offset, data_length)?;
+                let name: Vec<u16> = data.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+                locators.push(String::from_utf16_lossy(&name).trim_end_matches('\0').to_owned());
+            }
+        }
+
+        let name: Vec<u16> = header[64..576].chunks_exact(2).map(BigEndian::read_u16).collect();
+        locators.push(String::from_utf16_lossy(&name).trim_end_matches('\0').to_owned());
+        Ok(locators)
+    }
+}
+
+impl Read for VhdReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position >= self.size || buf.is_empty() {
+            return Ok(0);
+        }
+        let remaining = std::cmp::min(buf.len() as u64, self.size - self.position);
+
+        let count = match &mut self.layout {
+            VhdLayout::Fixed => {
+                self.file.seek(SeekFrom::Start(self.position))?;
+                self.file.read(&mut buf[..remaining as usize])?
+            }
+            VhdLayout::Dynamic { bat, block_size, bitmap_size, parent } => {
+                let block = (self.position / *block_size) as usize;
+                let offset_in_block = self.position % *block_size;
+                let count = std::cmp::min(remaining, *block_size - offset_in_block) as usize;
+                let buf = &mut buf[..count];
+
+                match bat.get(block).cloned().unwrap_or(VHD_UNUSED_BLOCK) {
+                    VHD_UNUSED_BLOCK => read_parent(parent, self.position, buf)?,
+                    sector => {
+                        let block_offset = sector as u64 * VHD_SECTOR_SIZE;
+                        let data_offset = block_offset + *bitmap_size + offset_in_block;
+                        match parent {
+                            None => {
+                                self.file.seek(SeekFrom::Start(data_offset))?;
+                                self.file.read_exact(buf)?;
+                                count
+                            }
+                            Some(parent) => {
+                                if !matches!(self.cached_bitmap, Some((idx, _)) if idx == block) {
+                                    let mut bitmap = vec![0; *bitmap_size as usize];
+                                    self.file.seek(SeekFrom::Start(block_offset))?;
+                                    self.file.read_exact(&mut bitmap)?;
+                                    self.cached_bitmap = Some((block, bitmap));
+                                }
+                                let bitmap = &self.cached_bitmap.as_ref().unwrap().1;
+                                let is_present = |sector: u64| bitmap[(sector / 8) as usize] & (0x80 >> (sector % 8)) != 0;
+
+                                /* read all sectors which are stored in the same place */
+                                let first_sector = offset_in_block / VHD_SECTOR_SIZE;
+                                let present = is_present(first_sector);
+                                let mut end = (first_sector + 1) * VHD_SECTOR_SIZE;
+                                while end < offset_in_block + count as u64 && is_present(end / VHD_SECTOR_SIZE) == present {
+                                    end += VHD_SECTOR_SIZE;
+                                }
+                                let buf = &mut buf[..std::cmp::min(count as u64, end - offset_in_block) as usize];
+                                if present {
+                                    self.file.seek(SeekFrom::Start(data_offset))?;
+                                    self.file.read_exact(buf)?;
+                                    buf.len()
+                                } else {
+                                    parent.seek(SeekFrom::Start(self.position))?;
+                                    parent.read_exact(buf)?;
+                                    buf.len()
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        };
+        self.position += count as u64;
+        Ok(count)
+    }
+}
+
+impl Seek for VhdReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.position = match pos {
+            SeekFrom::Start(p) => p,
+            SeekFrom::Current(p) => super::offset_position(self.position, p)?,
+            SeekFrom::End(p) => super::offset_position(self.size, p)?,
+        };
+        Ok(self.position)
+    }
+}
@@ -0,0 +1,322 @@
+use super::{ReadSeek, read_bytes, read_parent};
+use anyhow::{Result, anyhow, bail};
+use byteorder::{ByteOrder, LittleEndian};
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+pub const VHDX_SIGNATURE: &[u8] = b"vhdxfile";
+
+const VHDX_HEADER_OFFSETS: [u64; 2] = [0x10000, 0x20000];
+const VHDX_REGION_TABLE_OFFSETS: [u64; 2] = [0x30000, 0x40000];
+const VHDX_HEADER_SIZE: usize = 4096;
+const VHDX_REGION_TABLE_SIZE: usize = 65536;
+
+const REGION_BAT: &str = "2dc27766-f623-4200-9d64-115e9bfd4a08";
+const REGION_METADATA: &str = "8b7ca206-4790-4b9a-b8fe-575f050f886e";
+
+const METADATA_FILE_PARAMETERS: &str = "caa16737-fa36-4d43-b3b6-33f0aa44e76b";
+const METADATA_VIRTUAL_DISK_SIZE: &str = "2fa54224-cd1b-4876-b211-5dbed83bf4b8";
+const METADATA_LOGICAL_SECTOR_SIZE: &str = "8141bf1d-a96f-4709-ba47-f233a8faab5f";
+const METADATA_PARENT_LOCATOR: &str = "a8d35f2b-b30b-454d-abf7-d3d84834ab0c";
+
+const PAYLOAD_BLOCK_NOT_PRESENT: u64 = 0;
+const PAYLOAD_BLOCK_UNDEFINED: u64 = 1;
+const PAYLOAD_BLOCK_ZERO: u64 = 2;
+const PAYLOAD_BLOCK_UNMAPPED: u64 = 3;
+const PAYLOAD_BLOCK_FULLY_PRESENT: u64 = 6;
+const PAYLOAD_BLOCK_PARTIALLY_PRESENT: u64 = 7;
+
+const SECTOR_BITMAP_BLOCK_SIZE: u64 = 1 << 20;
+
+/// metadata items must not be larger than 1 MiB
+const MAX_METADATA_ITEM_SIZE: usize = 1 << 20;
+
+///
+/// Provides access to the virtual disk stored in a VHDX file. Dynamic and
+/// differencing disks are supported; the parent of a differencing disk is
+/// opened automatically. The log is not replayed.
+pub struct VhdxReader {
+    file: File,
+    bat: Vec<u64>,
+    block_size: u64,
+    sector_size: u64,
+    chunk_ratio: u64,
+    parent: Option<Box<dyn ReadSeek + Send>>,
+    size: u64,
+    position: u64,
+    cached_bitmap: Option<(usize, Vec<u8>)>,
+}
+
+impl VhdxReader {
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut file = File::open(path)?;
+        if read_bytes(&mut file, 0, 8)? != VHDX_SIGNATURE {
+            bail!("{} is not a VHDX file", path.display());
+        }
+
+        let header = Self::current_header(&mut file)?;
+        if header[48..64].iter().any(|b| *b != 0) {
+            log::warn!("the log of {} has not been replayed, the virtual disk might be inconsistent", path.display());
+        }
+
+        let regions = Self::region_table(&mut file)?;
+        let metadata_offset = regions.iter()
+            .find(|(guid, _, _)| guid == REGION_METADATA)
+            .map(|(_, offset, _)| *offset)
+            .ok_or_else(|| anyhow!("{} has no metadata region", path.display()))?;
+        let (bat_offset, bat_length) = regions.iter()
+            .find(|(guid, _, _)| guid == REGION_BAT)
+            .map(|(_, offset, length)| (*offset, *length))
+            .ok_or_else(|| anyhow!("{} has no BAT region", path.display()))?;
+
+        let metadata = Self::metadata(&mut file, metadata_offset)?;
+        let item = |id: &str, min_length: usize| -> Result<&[u8]> {
+            let data = metadata.iter()
+                .find(|(guid, _)| guid == id)
+                .map(|(_, data)| data)
+                .ok_or_else(|| anyhow!("missing metadata item {} in {}", id, path.display()))?;
+            if data.len() < min_length {
+                bail!("metadata item {} in {} is too short ({} bytes)", id, path.display(), data.len());
+            }
+            Ok(data)
+        };
+
+        let file_parameters = item(METADATA_FILE_PARAMETERS, 8)?;
+        let block_size = LittleEndian::read_u32(&file_parameters[0..4]) as u64;
+        let has_parent = LittleEndian::read_u32(&file_parameters[4..8]) & 0x2 != 0;
+        let size = LittleEndian::read_u64(item(METADATA_VIRTUAL_DISK_SIZE, 8)?);
+        let sector_size = LittleEndian::read_u32(item(METADATA_LOGICAL_SECTOR_SIZE, 4)?) as u64;
+        if block_size == 0 || sector_size == 0 || !block_size.is_multiple_of(sector_size) {
+            bail!("invalid block size ({}) or sector size ({}) in {}", block_size, sector_size, path.display());
+        }
+
+        /* every sector bitmap block covers `chunk_ratio` payload blocks */
+        let chunk_ratio = (1u64 << 23) * sector_size / block_size;
+        let file_size = file.seek(SeekFrom::End(0))?;
+        if bat_offset.checked_add(bat_length as u64).is_none_or(|end| end > file_size) {
+            bail!("the BAT region of {} exceeds the file size", path.display());
+        }
+        let bat = read_bytes(&mut file, bat_offset, bat_length as usize)?
+            .chunks_exact(8)
+            .map(LittleEndian::read_u64)
+            .collect();
+
+        let parent = if has_parent {
+            let locators = Self::parent_locators(item(METADATA_PARENT_LOCATOR, 0)?);
+            let parent_path = super::find_parent_image(path, &locators)?;
+            log::info!("{} is a differencing disk with parent {}", path.display(), parent_path.display());
+            Some(super::open_image(&parent_path)?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            file,
+            bat,
+            block_size,
+            sector_size,
+            chunk_ratio,
+            parent,
+            size,
+            position: 0,
+            cached_bitmap: None,
+        })
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// returns the valid header with the highest sequence number
+    fn current_header(file: &mut File) -> Result<Vec<u8>> {
+        let mut current: Option<(u64, Vec<u8>)> = None;
+        for offset in VHDX_HEADER_OFFSETS.iter() {
+            let header = read_bytes(file, *offset, VHDX_HEADER_SIZE)?;
+            if &header[0..4] != b"head" || !has_valid_checksum(&header) {
+                continue;
+            }
+            let sequence_number = LittleEndian::read_u64(&header[8..16]);
+            if current.as_ref().is_none_or(|(seq, _)| *seq < sequence_number) {
+                current = Some((sequence_number, header));
+            }
+        }
+        current.map(|(_, header)| header).ok_or_else(|| anyhow!("no valid VHDX header found"))
+    }
+
+    /// returns the entries of the first valid region table as `(guid, offset, length)`
+    fn region_table(file: &mut File) -> Result<Vec<(String, u64, u32)>> {
+        for offset in VHDX_REGION_TABLE_OFFSETS.iter() {
+            let table = read_bytes(file, *offset, VHDX_REGION_TABLE_SIZE)?;
+            if &table[0..4] != b"regi" || !has_valid_checksum(&table) {
+                continue;
+            }
+            let entry_count = std::cmp::min(LittleEndian::read_u32(&table[8..12]) as usize, 2047);
+            return Ok(table[16..16 + entry_count * 32].chunks(32)
+                .map(|e| (guid(&e[0..16]), LittleEndian::read_u64(&e[16..24]), LittleEndian::read_u32(&e[24..28])))
+                .collect());
+        }
+        bail!("no valid VHDX region table found")
+    }
+
+    /// returns all metadata items as `(guid, data)`
+    fn metadata(file: &mut File, offset: u64) -> Result<Vec<(String, Vec<u8>)>> {
+        let table = read_bytes(file, offset, VHDX_REGION_TABLE_SIZE)?;
+        if &table[0..8] != b"metadata" {
+            bail!("invalid VHDX metadata table");
+        }
+        let entry_count = std::cmp::min(LittleEndian::read_u16(&table[10..12]) as usize, 2047);
+        let mut items = Vec::new();
+        for entry in table[32..32 + entry_count * 32].chunks(32) {
+            let item_offset = LittleEndian::read_u32(&entry[16..20]) as u64;
+            let item_length = LittleEndian::read_u32(&entry[20..24]) as usize;
+            if item_length > MAX_METADATA_ITEM_SIZE {
+                bail!("invalid length of VHDX metadata item: {}", item_length);
+            }
+            items.push((guid(&entry[0..16]), read_bytes(file, offset + item_offset, item_length)?));
+        }
+        Ok(items)
+    }
+
+    /// returns all possible names of the parent disk, in the order of preference
+    fn parent_locators(locator: &[u8]) -> Vec<String> {
+        if locator.len() < 20 {
+            return Vec::new();
+        }
+        let utf16 = |offset: usize, length: usize| -> Option<String> {
+            let data = locator.get(offset..offset + length)?;
+            let name: Vec<u16> = data.chunks_exact(2).map(LittleEndian::read_u16).collect();
+            Some(String::from_utf16_lossy(&name))
+        };
+
+        let count = LittleEndian::read_u16(&locator[18..20]) as usize;
+        let entries: Vec<(String, String)> = locator[20..].chunks_exact(12)
+            .take(count)
+            .filter_map(|e| {
+                let key = utf16(LittleEndian::read_u32(&e[0..4]) as usize, LittleEndian::read_u16(&e[8..10]) as usize)?;
+                let value = utf16(LittleEndian::read_u32(&e[4..8]) as usize, LittleEndian::read_u16(&e[10..12]) as usize)?;
+                Some((key, value))
+            })
+            .collect();
+
+        ["relative_path", "absolute_win32_path", "volume_path"].iter()
+            .filter_map(|k| entries.iter().find(|(key, _)| key == k).map(|(_, value)| value.clone()))
+            .collect()
+    }
+
+    fn read_bitmap(&mut self, chunk: usize) -> io::Result<bool> {
+        if matches!(self.cached_bitmap, Some((idx, _)) if idx == chunk) {
+            return Ok(true);
+        }
+        let entry_idx = chunk * (self.chunk_ratio as usize + 1) + self.chunk_ratio as usize;
+        let entry = self.bat.get(entry_idx).cloned().unwrap_or(0);
+        if entry & 0x7 != PAYLOAD_BLOCK_FULLY_PRESENT {
+            return Ok(false);
+        }
+        let mut bitmap = vec![0; SECTOR_BITMAP_BLOCK_SIZE as usize];
+        self.file.seek(SeekFrom::Start((entry >> 20) << 20))?;
+        self.file.read_exact(&mut bitmap)?;
+        self.cached_bitmap = Some((chunk, bitmap));
+        Ok(true)
+    }
+}
+
+impl Read for VhdxReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position >= self.size || buf.is_empty() {
+            return Ok(0);
+        }
+        let remaining = std::cmp::min(buf.len() as u64, self.size - self.position);
+        let block = self.position / self.block_size;
+        let offset_in_block = self.position % self.block_size;
+        let count = std::cmp::min(remaining, self.block_size - offset_in_block) as usize;
+        let buf = &mut buf[..count];
+
+        /* the sector bitmap entries are interleaved with the payload block entries */
+        let entry_idx = (block + block / self.chunk_ratio) as usize;
+        let entry = self.bat.get(entry_idx).cloned().unwrap_or(0);
+        let data_offset = ((entry >> 20) << 20) + offset_in_block;
+
+        let count = match entry & 0x7 {
+            PAYLOAD_BLOCK_FULLY_PRESENT => {
+                self.file.seek(SeekFrom::Start(data_offset))?;
+                self.file.read_exact(buf)?;
+                count
+            }
+            PAYLOAD_BLOCK_PARTIALLY_PRESENT if self.parent.is_some() => {
+                let chunk = (block / self.chunk_ratio) as usize;
+                if !self.read_bitmap(chunk)? {
+                    return Err(io::Error::other(format!("missing sector bitmap for block {}", block)));
+                }
+                let bitmap = &self.cached_bitmap.as_ref().unwrap().1;
+                let chunk_sector = |position: u64| position / self.sector_size - chunk as u64 * (1 << 23);
+                let is_present = |sector: u64| bitmap[(sector / 8) as usize] & (1 << (sector % 8)) != 0;
+
+                /* read all sectors which are stored in the same place */
+                let first_sector = self.position / self.sector_size;
+                let present = is_present(chunk_sector(self.position));
+                let mut end = (first_sector + 1) * self.sector_size;
+                while end < self.position + count as u64 && is_present(chunk_sector(end)) == present {
+                    end += self.sector_size;
+                }
+                let buf = &mut buf[..std::cmp::min(count as u64, end - self.position) as usize];
+                if present {
+                    self.file.seek(SeekFrom::Start(data_offset))?;
+                    self.file.read_exact(buf)?;
+                    buf.len()
+                } else {
+                    read_parent(&mut self.parent, self.position, buf)?
+                }
+            }
+            PAYLOAD_BLOCK_NOT_PRESENT | PAYLOAD_BLOCK_UNDEFINED | PAYLOAD_BLOCK_UNMAPPED | PAYLOAD_BLOCK_PARTIALLY_PRESENT => {
+                read_parent(&mut self.parent, self.position, buf)?
+            }
+            PAYLOAD_BLOCK_ZERO => {
+                buf.fill(0);
+                count
+            }
+            state => return Err(io::Error::other(format!("invalid VHDX block state {}", state))),
+        };
+        self.position += count as u64;
+        Ok(count)
+    }
+}
+
+impl Seek for VhdxReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.position = match pos {
+            SeekFrom::Start(p) => p,
+            SeekFrom::Current(p) => super::offset_position(self.position, p)?,
+            SeekFrom::End(p) => super::offset_position(self.size, p)?,
+        };
+        Ok(self.position)
+    }
+}
+
+/// formats a GUID as it is stored on disk (the first three fields are little endian)
+fn guid(data: &[u8]) -> String {
+    format!("{:08x}-{:04x}-{:04x}-{:02x}{:02x}-{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}",
+        LittleEndian::read_u32(&data[0..4]),
+        LittleEndian::read_u16(&data[4..6]),
+        LittleEndian::read_u16(&data[6..8]),
+        data[8], data[9], data[10], data[11], data[12], data[13], data[14], data[15])
+}
+
+/// verifies the CRC-32C checksum, which is stored at offset 4
+fn has_valid_checksum(data: &[u8]) -> bool {
+    let expected = LittleEndian::read_u32(&data[4..8]);
+    let mut data = data.to_vec();
+    data[4..8].fill(0);
+    crc32c(&data) == expected
+}
+
+fn crc32c(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0x82f6_3b78 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
@@ -0,0 +1,209 @@
+use crate::intern::carving_reader::CarvingReader;
+use crate::intern::fixup::apply_fixups;
+use crate::intern::mft_slack::FILE_NAME_HEADER_SIZE;
+use crate::intern::{BodyfileLine, PreprocessedMft, TimestampTuple, INDEX_ALLOCATION};
+use byteorder::{ByteOrder, LittleEndian};
+use mft::attribute::x30::{FileNameAttr, FileNamespace};
+use std::io::{Cursor, Read};
+use winstructs::ntfs::mft_reference::MftReference;
+
+/// default size of an INDX record, which is used if the size is unknown
+pub const DEFAULT_INDEX_RECORD_SIZE: usize = 0x1000;
+
+/// offset of the index node header in an INDX record
+const NODE_HEADER_OFFSET: usize = 0x18;
+
+/// size of an index entry header, which is followed by the `$FILE_NAME` attribute
+const INDEX_ENTRY_HEADER_SIZE: usize = 0x10;
+
+/// index entries are always aligned to 8 bytes
+const ENTRY_ALIGNMENT: usize = 8;
+
+const INDEX_ENTRY_LAST: u32 = 0x02;
+
+///
+/// an entry of a `$I30` directory index, which contains a copy of the
+/// `$FILE_NAME` attribute of a file in that directory. Entries which are found
+/// in the slack of an INDX record (i.e. after the last entry of that record)
+/// are remainders of files which have been deleted or moved to another directory.
+pub struct IndexEntry {
+    file_reference: MftReference,
+    parent: MftReference,
+    filename: String,
+    timestamps: TimestampTuple,
+    logical_size: u64,
+    offset: u64,
+    is_slack: bool,
+    is_dos_name: bool,
+}
+
+impl IndexEntry {
+    pub fn file_reference(&self) -> &MftReference { &self.file_reference }
+    pub fn parent(&self) -> &MftReference { &self.parent }
+    pub fn filename(&self) -> &String { &self.filename }
+    pub fn timestamps(&self) -> &TimestampTuple { &self.timestamps }
+    pub fn logical_size(&self) -> u64 { self.logical_size }
+
+    /// returns the offset of the entry in the `$I30` stream
+    pub fn offset(&self) -> u64 { self.offset }
+    pub fn is_slack(&self) -> bool { self.is_slack }
+    pub fn is_dos_name(&self) -> bool { self.is_dos_name }
+
+    /// checks if both entries describe the same file with the same name and timestamps
+    pub fn is_same_entry(&self, other: &Self) -> bool {
+        self.file_reference == other.file_reference
+            && self.parent == other.parent
+            && self.filename == other.filename
+            && self.timestamps == other.timestamps
+    }
+
+    pub fn bodyfile_line(&self, mft: &PreprocessedMft) -> String {
+        let mut path = mft.get_full_path(&self.parent).full_path;
+        if !path.ends_with('/') {
+            path.push('/');
+        }
+        path.push_str(&self.filename);
+        let status = if self.is_slack { "$I30, slack" } else { "$I30" };
+
+        BodyfileLine::new()
+            .with_owned_name(mft.tagged_path(format!("{} ({})", path, status)))
+            .with_owned_inode(format!(
+                "{}-{}-{}",
+                self.file_reference.entry,
+                INDEX_ALLOCATION,
+                self.file_reference.sequence
+            ))
+            .with_size(self.logical_size)
+            .with_atime(self.timestamps.accessed())
+            .with_mtime(self.timestamps.mft_modified())
+            .with_ctime(self.timestamps.modified())
+            .with_crtime(self.timestamps.created())
+            .to_string()
+    }
+
+    /// tries to parse an index entry at the beginning of `data`. Returns the
+    /// entry and its length if it is plausible.
+    pub(crate) fn from_slice(data: &[u8], offset: u64, is_slack: bool) -> Option<(Self, usize)> {
+        if data.len() < INDEX_ENTRY_HEADER_SIZE + FILE_NAME_HEADER_SIZE {
+            return None;
+        }
+        let entry_length = LittleEndian::read_u16(&data[0x08..]) as usize;
+        let content_length = LittleEndian::read_u16(&data[0x0a..]) as usize;
+        let flags = LittleEndian::read_u32(&data[0x0c..]);
+        let name_length = data[INDEX_ENTRY_HEADER_SIZE + 0x40] as usize;
+        let namespace = data[INDEX_ENTRY_HEADER_SIZE + 0x41];
+
+        if content_length != FILE_NAME_HEADER_SIZE + 2 * name_length
+            || name_length == 0
+            || namespace > 3
+            || flags & !0x03 != 0
+            || !entry_length.is_multiple_of(ENTRY_ALIGNMENT)
+            || entry_length < INDEX_ENTRY_HEADER_SIZE + content_length
+            || entry_length > data.len() {
+            return None;
+        }
+
+        /* a zeroed creation time is a strong hint that this is not a `$FILE_NAME` attribute */
+        if LittleEndian::read_u64(&data[INDEX_ENTRY_HEADER_SIZE + 0x08..]) == 0 {
+            return None;
+        }
+        let content = &data[INDEX_ENTRY_HEADER_SIZE..INDEX_ENTRY_HEADER_SIZE + content_length];
+        let attr = FileNameAttr::from_stream(&mut Cursor::new(content)).ok()?;
+
+        let entry = Self {
+            file_reference: MftReference::from(LittleEndian::read_u64(&data[0x00..])),
+            parent: attr.parent,
+            timestamps: TimestampTuple::from_file_name_value(content)?,
+            logical_size: attr.logical_size,
+            is_dos_name: attr.namespace == FileNamespace::DOS,
+            filename: attr.name,
+            offset,
+            is_slack,
+        };
+        Some((entry, entry_length))
+    }
+}
+
+///
+/// Parses INDX records of a `$I30` index (the content of
+/// `$INDEX_ALLOCATION:$I30`), which can be read from any [`Read`] object. In
+/// addition to the current entries of every record, its slack is searched
+/// for remainders of former entries.
+pub struct IndexRecordIterator<R> where R: Read {
+    reader: CarvingReader<R>,
+    record_size: usize,
+    entries: std::vec::IntoIter<IndexEntry>,
+}
+
+impl<R> IndexRecordIterator<R> where R: Read {
+    pub fn new(reader: R, record_size: usize) -> Self {
+        Self {
+            reader: CarvingReader::new(reader),
+            record_size,
+            entries: Vec::new().into_iter(),
+        }
+    }
+
+    /// parses the INDX record at the current position, or returns `None` if there is none
+    fn parse_record(&mut self) -> Option<Vec<IndexEntry>> {
+        let record_offset = self.reader.offset();
+        let mut record = self.reader.data()[..self.record_size].to_vec();
+        if &record[0..4] != b"INDX" || !apply_fixups(&mut record) {
+            return None;
+        }
+
+        let entries_offset = NODE_HEADER_OFFSET + LittleEndian::read_u32(&record[NODE_HEADER_OFFSET..]) as usize;
+        let index_length = NODE_HEADER_OFFSET + LittleEndian::read_u32(&record[NODE_HEADER_OFFSET + 4..]) as usize;
+        let allocated_size = NODE_HEADER_OFFSET + LittleEndian::read_u32(&record[NODE_HEADER_OFFSET + 8..]) as usize;
+        if allocated_size > record.len() || index_length > allocated_size || entries_offset > index_length {
+            log::warn!("invalid INDX record header at offset {:#x}", record_offset);
+            return None;
+        }
+
+        let mut entries = Vec::new();
+        let mut offset = entries_offset;
+        while offset + INDEX_ENTRY_HEADER_SIZE <= index_length {
+            let entry_length = LittleEndian::read_u16(&record[offset + 0x08..]) as usize;
+            let flags = LittleEndian::read_u32(&record[offset + 0x0c..]);
+            if entry_length == 0 || flags & INDEX_ENTRY_LAST != 0 {
+                break;
+            }
+            if let Some((entry, _)) = IndexEntry::from_slice(&record[offset..index_length], record_offset + offset as u64, false) {
+                entries.push(entry);
+            }
+            offset += entry_length;
+        }
+
+        let mut offset = index_length.next_multiple_of(ENTRY_ALIGNMENT);
+        while offset + INDEX_ENTRY_HEADER_SIZE < allocated_size {
+            match IndexEntry::from_slice(&record[offset..allocated_size], record_offset + offset as u64, true) {
+                Some((entry, length)) => {
+                    entries.push(entry);
+                    offset += length;
+                }
+                None => offset += ENTRY_ALIGNMENT,
+            }
+        }
+        /* DOS names are only an alias of the long name of the same file */
+        entries.retain(|e| !e.is_dos_name);
+        Some(entries)
+    }
+}
+
+impl<R> Iterator for IndexRecordIterator<R> where R: Read {
+    type Item = IndexEntry;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(entry) = self.entries.next() {
+                return Some(entry);
+            }
+            if !self.reader.fill(self.record_size) {
+                return None;
+            }
+            if let Some(entries) = self.parse_record() {
+                self.entries = entries.into_iter();
+            }
+            self.reader.advance(self.record_size);
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use crate::intern::Compression;
+use anyhow::Result;
+use std::fmt;
+use std::io::Read;
+use std::path::PathBuf;
+
+///
+/// the origin of `$MFT` or `$UsnJrnl:$J` data, which is either a file or
+/// some arbitrary [`Read`] object (like stdin)
+pub enum InputSource {
+    File(PathBuf),
+    Reader(Box<dyn Read + Send>),
+}
+
+impl InputSource {
+    /// opens the source and returns a reader for the decompressed data
+    pub fn open(self) -> Result<(Compression, Box<dyn Read + Send>)> {
+        match self {
+            Self::File(path) => Compression::open(&path),
+            Self::Reader(reader) => Compression::open_reader(reader),
+        }
+    }
+
+    /// files can be opened multiple times, but readers can be consumed only once
+    pub fn try_clone(&self) -> Option<Self> {
+        match self {
+            Self::File(path) => Some(Self::File(path.clone())),
+            Self::Reader(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for InputSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::File(path) => write!(f, "{}", path.display()),
+            Self::Reader(_) => write!(f, "<stream>"),
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use crate::intern::report::{csv_line, format_timestamp};
+use crate::intern::usn_record::USN_RECORD_MAX_SIZE;
+use crate::intern::usnjrnl_max::DEFAULT_ALLOCATION_DELTA;
+use crate::intern::{Filetime, UsnJrnlMax, UsnReason, UsnRecord};
+use anyhow::Result;
+
+/// a range of USNs between two records, which contains no records
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UsnGap {
+    from: i64,
+    to: i64,
+}
+
+impl UsnGap {
+    /// the USN of the last record before the gap
+    pub fn from(&self) -> i64 { self.from }
+
+    /// the USN of the first record after the gap
+    pub fn to(&self) -> i64 { self.to }
+}
+
+///
+/// a summary of a single `$UsnJrnl:$J` file, which shows which period of
+/// time it covers. USNs are offsets in `$J`, so they increase by the length
+/// of each record, plus the unused space at the end of every page. If the
+/// USN jumps further than the allocation delta of the journal, records have
+/// been lost (e.g. because `$J` has been wiped or partially overwritten).
+pub struct JournalCoverage {
+    name: String,
+    records: usize,
+    errors: usize,
+    first_usn: Option<i64>,
+    last_usn: Option<i64>,
+    first_timestamp: Option<Filetime>,
+    last_timestamp: Option<Filetime>,
+    reasons: [usize; 32],
+    jumps: Vec<UsnGap>,
+    usnjrnl_max: Option<UsnJrnlMax>,
+}
+
+impl JournalCoverage {
+    pub const CSV_HEADER: &'static str = "journal,records,errors,first_usn,last_usn,first_timestamp,last_timestamp,maximum_size,allocation_delta,gaps,reasons";
+
+    pub fn new(name: String, usnjrnl_max: Option<UsnJrnlMax>) -> Self {
+        Self {
+            name,
+            records: 0,
+            errors: 0,
+            first_usn: None,
+            last_usn: None,
+            first_timestamp: None,
+            last_timestamp: None,
+            reasons: [0; 32],
+            jumps: Vec::new(),
+            usnjrnl_max,
+        }
+    }
+
+    pub fn name(&self) -> &str { &self.name }
+    pub fn records(&self) -> usize { self.records }
+
+    /// returns the number of records which could not be parsed
+    pub fn errors(&self) -> usize { self.errors }
+    pub fn first_usn(&self) -> Option<i64> { self.first_usn }
+    pub fn last_usn(&self) -> Option<i64> { self.last_usn }
+    pub fn first_timestamp(&self) -> Option<Filetime> { self.first_timestamp }
+    pub fn last_timestamp(&self) -> Option<Filetime> { self.last_timestamp }
+    pub fn usnjrnl_max(&self) -> Option<&UsnJrnlMax> { self.usnjrnl_max.as_ref() }
+
+    /// returns the number of records which have the reason `flag`
+    pub fn reason_count(&self, flag: u32) -> usize {
+        match flag.trailing_zeros() {
+            32 => 0,
+            bit => self.reasons[bit as usize],
+        }
+    }
+
+    /// returns the allocation delta from `$UsnJrnl:$Max`, or the default value if it is unknown
+    pub fn allocation_delta(&self) -> u64 {
+        self.usnjrnl_max.map(|m| m.allocation_delta()).unwrap_or(DEFAULT_ALLOCATION_DELTA)
+    }
+
+    /// returns all jumps of the USN which are larger than the allocation delta
+    pub fn gaps(&self) -> impl Iterator<Item=&UsnGap> + '_ {
+        let allocation_delta = self.allocation_delta() as i64;
+        self.jumps.iter().filter(move |g| g.to - g.from > allocation_delta)
+    }
+
+    /// adds a record (or an error) of the `$J` file, in the order they are stored in the file
+    pub fn add(&mut self, record: &Result<UsnRecord>) {
+        let record = match record {
+            Ok(record) => record,
+            Err(_) => {
+                self.errors += 1;
+                return;
+            }
+        };
+        self.records += 1;
+
+        /* records are never larger than a page, so every larger jump is suspicious */
+        let usn = record.usn();
+        if let Some(last_usn) = self.last_usn {
+            if usn - last_usn > USN_RECORD_MAX_SIZE as i64 {
+                self.jumps.push(UsnGap { from: last_usn, to: usn });
+            }
+        }
+        self.first_usn = Some(self.first_usn.map_or(usn, |u| u.min(usn)));
+        self.last_usn = Some(self.last_usn.map_or(usn, |u| u.max(usn)));
+
+        if !record.is_range_record() {
+            let timestamp = Filetime::from(record.filetime());
+            self.first_timestamp = Some(self.first_timestamp.map_or(timestamp, |t| t.min(timestamp)));
+            self.last_timestamp = Some(self.last_timestamp.map_or(timestamp, |t| t.max(timestamp)));
+        }
+
+        let reason = record.reason().bits();
+        for (bit, count) in self.reasons.iter_mut().enumerate() {
+            if reason & (1 << bit) != 0 {
+                *count += 1;
+            }
+        }
+    }
+
+    pub fn to_csv_line(&self) -> String {
+        let format_usn = |usn: Option<i64>| usn.map(|u| format!("{:#x}", u)).unwrap_or_default();
+        let gaps: Vec<String> = self.gaps().map(|g| format!("{:#x}-{:#x}", g.from, g.to)).collect();
+        let reasons: Vec<String> = self.reasons
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .map(|(bit, count)| format!("{}={}", UsnReason::from(1 << bit), count))
+            .collect();
+        csv_line(&[
+            self.name.clone(),
+            self.records.to_string(),
+            self.errors.to_string(),
+            format_usn(self.first_usn),
+            format_usn(self.last_usn),
+            format_timestamp(self.first_timestamp),
+            format_timestamp(self.last_timestamp),
+            self.usnjrnl_max.map(|m| m.maximum_size().to_string()).unwrap_or_default(),
+            self.usnjrnl_max.map(|m| m.allocation_delta().to_string()).unwrap_or_default(),
+            gaps.join("; "),
+            reasons.join("; "),
+        ])
+    }
+}
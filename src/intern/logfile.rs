@@ -0,0 +1,250 @@
+use crate::intern::fixup::apply_fixups;
+use crate::intern::read_exact::read_exact_or_eof;
+use anyhow::{Result, bail};
+use byteorder::{ByteOrder, LittleEndian};
+use std::collections::HashSet;
+use std::io::Read;
+
+/// size of the restart pages and of the log record pages, if the restart page is missing
+const DEFAULT_PAGE_SIZE: usize = 0x1000;
+
+/// offset of the first log record in a log record page, if the restart page is missing
+const DEFAULT_PAGE_DATA_OFFSET: usize = 0x40;
+
+/// size of the header of every log record
+pub const LOG_RECORD_HEADER_SIZE: usize = 0x30;
+
+/// size of the header of the client data of an NTFS log record, without the list of LCNs
+const CLIENT_HEADER_SIZE: usize = 0x20;
+
+/// log records are always aligned to 8 bytes
+const RECORD_ALIGNMENT: usize = 8;
+
+/// larger records are considered to be garbage
+const MAX_CLIENT_DATA_LENGTH: usize = 0x10000;
+
+/// log records which have been written by NTFS (as opposed to restart records)
+const LFS_CLIENT_RECORD: u32 = 1;
+const LFS_CLIENT_RESTART: u32 = 2;
+
+///
+/// a log record of `$LogFile`, which describes an operation on the volume
+/// metadata. Every operation can be repeated using the redo data, and
+/// reverted using the undo data.
+pub struct LogRecord {
+    lsn: u64,
+    transaction_id: u32,
+    redo_operation: u16,
+    undo_operation: u16,
+    target_attribute: u16,
+    record_offset: u16,
+    attribute_offset: u16,
+    cluster_block_offset: u16,
+    target_vcn: u64,
+    redo_data: Vec<u8>,
+    undo_data: Vec<u8>,
+}
+
+impl LogRecord {
+    pub fn lsn(&self) -> u64 { self.lsn }
+    pub fn transaction_id(&self) -> u32 { self.transaction_id }
+    pub fn redo_operation(&self) -> u16 { self.redo_operation }
+    pub fn undo_operation(&self) -> u16 { self.undo_operation }
+    pub fn target_attribute(&self) -> u16 { self.target_attribute }
+
+    /// offset of the modified bytes, relative to the start of the attribute
+    pub fn record_offset(&self) -> u16 { self.record_offset }
+
+    /// offset of the modified attribute, relative to the start of the `$MFT` entry
+    pub fn attribute_offset(&self) -> u16 { self.attribute_offset }
+
+    /// offset of the modified data in the target cluster, in units of 512 bytes
+    pub fn cluster_block_offset(&self) -> u16 { self.cluster_block_offset }
+    pub fn target_vcn(&self) -> u64 { self.target_vcn }
+    pub fn redo_data(&self) -> &[u8] { &self.redo_data }
+    pub fn undo_data(&self) -> &[u8] { &self.undo_data }
+
+    /// parses a complete log record, including its header
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        if data.len() < LOG_RECORD_HEADER_SIZE + CLIENT_HEADER_SIZE {
+            bail!("log record is too short: {} bytes", data.len());
+        }
+        let lsn = LittleEndian::read_u64(&data[0x00..]);
+        let client = &data[LOG_RECORD_HEADER_SIZE..];
+        let lcns_to_follow = LittleEndian::read_u16(&client[0x0e..]) as usize;
+        if client.len() < CLIENT_HEADER_SIZE + 8 * lcns_to_follow {
+            bail!("log record {:#x} is too short for {} LCNs", lsn, lcns_to_follow);
+        }
+
+        let slice = |offset_pos: usize, length_pos: usize| -> Result<Vec<u8>> {
+            let offset = LittleEndian::read_u16(&client[offset_pos..]) as usize;
+            let length = LittleEndian::read_u16(&client[length_pos..]) as usize;
+            match client.get(offset..offset + length) {
+                Some(data) => Ok(data.to_vec()),
+                None => bail!("redo or undo data of log record {:#x} exceed the record", lsn),
+            }
+        };
+
+        Ok(Self {
+            lsn,
+            transaction_id: LittleEndian::read_u32(&data[0x24..]),
+            redo_operation: LittleEndian::read_u16(&client[0x00..]),
+            undo_operation: LittleEndian::read_u16(&client[0x02..]),
+            redo_data: slice(0x04, 0x06)?,
+            undo_data: slice(0x08, 0x0a)?,
+            target_attribute: LittleEndian::read_u16(&client[0x0c..]),
+            record_offset: LittleEndian::read_u16(&client[0x10..]),
+            attribute_offset: LittleEndian::read_u16(&client[0x12..]),
+            cluster_block_offset: LittleEndian::read_u16(&client[0x14..]),
+            target_vcn: LittleEndian::read_u64(&client[0x18..]),
+        })
+    }
+}
+
+///
+/// iterates over the log records of a `$LogFile`, which can be read from
+/// any [`Read`] object. The log record pages are read in the order they are
+/// stored in the file, which is not the order of their LSNs, because the log
+/// is circular. Records which are found more than once (e.g. in the buffer
+/// pages at the beginning of the log) are returned only once.
+pub struct LogRecordIterator<R> where R: Read {
+    data: R,
+    page_size: usize,
+    data_offset: usize,
+    page: Vec<u8>,
+    last_lsn: u64,
+    position: usize,
+    is_initialized: bool,
+    known_lsns: HashSet<u64>,
+}
+
+impl<R> LogRecordIterator<R> where R: Read {
+    pub fn new(data: R) -> Self {
+        Self {
+            data,
+            page_size: DEFAULT_PAGE_SIZE,
+            data_offset: DEFAULT_PAGE_DATA_OFFSET,
+            page: Vec::new(),
+            last_lsn: 0,
+            position: 0,
+            is_initialized: false,
+            known_lsns: HashSet::new(),
+        }
+    }
+
+    /// reads the page sizes from the first restart page, and skips both restart pages
+    fn read_restart_pages(&mut self) -> Result<bool> {
+        let mut page = vec![0; DEFAULT_PAGE_SIZE];
+        if !read_exact_or_eof(&mut self.data, &mut page)? {
+            return Ok(false);
+        }
+
+        let mut system_page_size = DEFAULT_PAGE_SIZE;
+        if &page[0..4] == b"RSTR" || &page[0..4] == b"CHKD" {
+            let is_valid_size = |size: usize| size.is_power_of_two() && (DEFAULT_PAGE_SIZE..=0x10000).contains(&size);
+            let restart_system_page_size = LittleEndian::read_u32(&page[0x10..]) as usize;
+            let log_page_size = LittleEndian::read_u32(&page[0x14..]) as usize;
+            let restart_area = LittleEndian::read_u16(&page[0x18..]) as usize;
+
+            if is_valid_size(restart_system_page_size) && is_valid_size(log_page_size) && restart_area + 0x30 <= page.len() {
+                system_page_size = restart_system_page_size;
+                self.page_size = log_page_size;
+                let data_offset = LittleEndian::read_u16(&page[restart_area + 0x26..]) as usize;
+                if (LOG_RECORD_HEADER_SIZE..log_page_size).contains(&data_offset) {
+                    self.data_offset = data_offset;
+                }
+            }
+        } else {
+            log::warn!("$LogFile has no restart page, using default page sizes");
+        }
+
+        let mut restart_pages = vec![0; 2 * system_page_size - DEFAULT_PAGE_SIZE];
+        read_exact_or_eof(&mut self.data, &mut restart_pages)
+    }
+
+    /// reads the next page. If it is no valid log record page, `self.page` is empty
+    fn next_page(&mut self) -> Result<bool> {
+        self.page.resize(self.page_size, 0);
+        let mut page = std::mem::take(&mut self.page);
+        if !read_exact_or_eof(&mut self.data, &mut page)? {
+            return Ok(false);
+        }
+        if &page[0..4] == b"RCRD" && apply_fixups(&mut page) {
+            self.last_lsn = LittleEndian::read_u64(&page[0x08..]);
+            self.page = page;
+        }
+        self.position = self.data_offset;
+        Ok(true)
+    }
+
+    fn next_record(&mut self) -> Result<Option<LogRecord>> {
+        if !self.is_initialized {
+            self.is_initialized = true;
+            if !self.read_restart_pages()? {
+                return Ok(None);
+            }
+        }
+
+        loop {
+            if self.page.is_empty() || self.position + LOG_RECORD_HEADER_SIZE > self.page_size {
+                if !self.next_page()? {
+                    return Ok(None);
+                }
+                continue;
+            }
+
+            let header = &self.page[self.position..self.position + LOG_RECORD_HEADER_SIZE];
+            let lsn = LittleEndian::read_u64(&header[0x00..]);
+            let client_data_length = LittleEndian::read_u32(&header[0x18..]) as usize;
+            let record_type = LittleEndian::read_u32(&header[0x20..]);
+
+            /* the rest of the page is unused or contains remainders of older records */
+            if lsn == 0
+                || lsn > self.last_lsn
+                || !(LFS_CLIENT_RECORD..=LFS_CLIENT_RESTART).contains(&record_type)
+                || client_data_length > MAX_CLIENT_DATA_LENGTH {
+                self.page.clear();
+                continue;
+            }
+
+            let length = LOG_RECORD_HEADER_SIZE + client_data_length;
+            let end = std::cmp::min(self.position + length, self.page_size);
+            let mut record = self.page[self.position..end].to_vec();
+            let is_last_record = lsn == self.last_lsn && end < self.page_size;
+            self.position = end;
+
+            /* the record is continued in the following pages */
+            while record.len() < length {
+                if !self.next_page()? {
+                    return Ok(None);
+                }
+                if self.page.is_empty() {
+                    break;
+                }
+                let remaining = std::cmp::min(length - record.len(), self.page_size - self.data_offset);
+                record.extend_from_slice(&self.page[self.data_offset..self.data_offset + remaining]);
+                self.position = self.data_offset + remaining;
+            }
+            if record.len() < length {
+                continue;
+            }
+
+            self.position = self.position.next_multiple_of(RECORD_ALIGNMENT);
+            if is_last_record {
+                self.page.clear();
+            }
+
+            if record_type != LFS_CLIENT_RECORD || !self.known_lsns.insert(lsn) {
+                continue;
+            }
+            return LogRecord::from_bytes(&record).map(Some);
+        }
+    }
+}
+
+impl<R> Iterator for LogRecordIterator<R> where R: Read {
+    type Item = Result<LogRecord>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record().transpose()
+    }
+}
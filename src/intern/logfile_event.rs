@@ -0,0 +1,299 @@
+use crate::intern::{AttributeContent, AttributeRecords, Filetime, IndexEntry, LogRecord, SlackAttribute, SlackAttributeContent, TimestampTuple, INDEX_ALLOCATION};
+use crate::intern::mft_slack::RESIDENT_HEADER_SIZE;
+use byteorder::{ByteOrder, LittleEndian};
+use mft::attribute::{MftAttributeContent, MftAttributeType};
+use mft::MftEntry;
+use std::fmt;
+use winstructs::ntfs::mft_reference::MftReference;
+
+/// cluster size of almost every NTFS volume, which is used if the real cluster size is unknown
+pub const DEFAULT_CLUSTER_SIZE: u64 = 0x1000;
+
+/// size of an `$MFT` entry, which is used if the real size is unknown
+pub const DEFAULT_MFT_RECORD_SIZE: u64 = 0x400;
+
+const INITIALIZE_FILE_RECORD_SEGMENT: u16 = 0x02;
+const CREATE_ATTRIBUTE: u16 = 0x05;
+const DELETE_ATTRIBUTE: u16 = 0x06;
+const UPDATE_RESIDENT_VALUE: u16 = 0x07;
+const ADD_INDEX_ENTRY_ROOT: u16 = 0x0c;
+const DELETE_INDEX_ENTRY_ROOT: u16 = 0x0d;
+const ADD_INDEX_ENTRY_ALLOCATION: u16 = 0x0e;
+const DELETE_INDEX_ENTRY_ALLOCATION: u16 = 0x0f;
+
+/// names of the operations of NTFS log records, as used by Microsoft
+const OPERATION_NAMES: &[&str] = &[
+    "Noop",
+    "CompensationLogRecord",
+    "InitializeFileRecordSegment",
+    "DeallocateFileRecordSegment",
+    "WriteEndOfFileRecordSegment",
+    "CreateAttribute",
+    "DeleteAttribute",
+    "UpdateResidentValue",
+    "UpdateNonresidentValue",
+    "UpdateMappingPairs",
+    "DeleteDirtyClusters",
+    "SetNewAttributeSizes",
+    "AddIndexEntryRoot",
+    "DeleteIndexEntryRoot",
+    "AddIndexEntryAllocation",
+    "DeleteIndexEntryAllocation",
+    "WriteEndOfIndexBuffer",
+    "SetIndexEntryVcnRoot",
+    "SetIndexEntryVcnAllocation",
+    "UpdateFileNameRoot",
+    "UpdateFileNameAllocation",
+    "SetBitsInNonresidentBitMap",
+    "ClearBitsInNonresidentBitMap",
+    "HotFix",
+    "EndTopLevelAction",
+    "PrepareTransaction",
+    "CommitTransaction",
+    "ForgetTransaction",
+    "OpenNonresidentAttribute",
+    "OpenAttributeTableDump",
+    "AttributeNamesDump",
+    "DirtyPageTableDump",
+    "TransactionTableDump",
+    "UpdateRecordDataRoot",
+    "UpdateRecordDataAllocation",
+];
+
+const STANDARD_INFORMATION: u32 = 0x10;
+const FILE_NAME: u32 = 0x30;
+
+/// size of the four timestamps at the beginning of `$STANDARD_INFORMATION`
+const STANDARD_INFORMATION_TIMESTAMPS_SIZE: usize = 0x20;
+
+/// the operation code of an NTFS log record
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LogOperation(u16);
+
+impl From<u16> for LogOperation {
+    fn from(code: u16) -> Self {
+        Self(code)
+    }
+}
+
+impl LogOperation {
+    pub fn code(&self) -> u16 { self.0 }
+}
+
+impl fmt::Display for LogOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match OPERATION_NAMES.get(self.0 as usize) {
+            Some(name) => write!(f, "{}", name),
+            None => write!(f, "{:#x}", self.0),
+        }
+    }
+}
+
+///
+/// the timestamps which have been written by a log record. Partial updates
+/// of `$STANDARD_INFORMATION` do not contain all four timestamps.
+#[derive(Default)]
+pub struct LogFileTimestamps {
+    created: Option<Filetime>,
+    modified: Option<Filetime>,
+    mft_modified: Option<Filetime>,
+    accessed: Option<Filetime>,
+}
+
+impl LogFileTimestamps {
+    pub fn created(&self) -> Option<Filetime> { self.created }
+    pub fn modified(&self) -> Option<Filetime> { self.modified }
+    pub fn mft_modified(&self) -> Option<Filetime> { self.mft_modified }
+    pub fn accessed(&self) -> Option<Filetime> { self.accessed }
+
+    pub fn is_empty(&self) -> bool {
+        self.created.is_none() && self.modified.is_none() && self.mft_modified.is_none() && self.accessed.is_none()
+    }
+
+    fn set(&mut self, index: usize, filetime: u64) {
+        let timestamp = Filetime::from(filetime);
+        match index {
+            0 => self.created = Some(timestamp),
+            1 => self.modified = Some(timestamp),
+            2 => self.mft_modified = Some(timestamp),
+            _ => self.accessed = Some(timestamp),
+        }
+    }
+}
+
+impl From<&TimestampTuple> for LogFileTimestamps {
+    fn from(timestamps: &TimestampTuple) -> Self {
+        Self {
+            created: Some(timestamps.created()),
+            modified: Some(timestamps.modified()),
+            mft_modified: Some(timestamps.mft_modified()),
+            accessed: Some(timestamps.accessed()),
+        }
+    }
+}
+
+///
+/// an event which has been decoded from a `$LogFile` record. Only operations
+/// which modify `$STANDARD_INFORMATION`, `$FILE_NAME` or `$I30` index entries
+/// are used.
+pub struct LogFileEvent {
+    lsn: u64,
+    operation: LogOperation,
+    record_number: u64,
+    file_reference: Option<MftReference>,
+    attribute_type: u32,
+    filename: Option<String>,
+    parent: Option<MftReference>,
+    timestamps: LogFileTimestamps,
+}
+
+impl LogFileEvent {
+    pub fn lsn(&self) -> u64 { self.lsn }
+    pub fn operation(&self) -> LogOperation { self.operation }
+
+    /// the number of the `$MFT` entry of the file
+    pub fn record_number(&self) -> u64 { self.record_number }
+
+    /// the reference of the file, if the sequence number of its `$MFT` entry is known
+    pub fn file_reference(&self) -> Option<&MftReference> { self.file_reference.as_ref() }
+    pub fn attribute_type(&self) -> u32 { self.attribute_type }
+    pub fn filename(&self) -> Option<&String> { self.filename.as_ref() }
+    pub fn parent(&self) -> Option<&MftReference> { self.parent.as_ref() }
+    pub fn timestamps(&self) -> &LogFileTimestamps { &self.timestamps }
+
+    /// decodes `record`. `cluster_size` and `mft_record_size` are needed to
+    /// calculate the `$MFT` entry which is modified by the record
+    pub fn from_record(record: &LogRecord, cluster_size: u64, mft_record_size: u64) -> Option<Self> {
+        let record_offset = record.target_vcn()
+            .checked_mul(cluster_size)?
+            .checked_add(record.cluster_block_offset() as u64 * 512)?;
+        let record_number = record_offset.checked_div(mft_record_size)?;
+        let mut event = Self {
+            lsn: record.lsn(),
+            operation: LogOperation::from(record.redo_operation()),
+            record_number,
+            file_reference: None,
+            attribute_type: STANDARD_INFORMATION,
+            filename: None,
+            parent: None,
+            timestamps: LogFileTimestamps::default(),
+        };
+
+        let is_valid = match (record.redo_operation(), record.undo_operation()) {
+            (INITIALIZE_FILE_RECORD_SEGMENT, _) => event.set_file_record(record.redo_data(), mft_record_size),
+            (CREATE_ATTRIBUTE, _) => event.set_attribute(record.redo_data()),
+            (DELETE_ATTRIBUTE, CREATE_ATTRIBUTE) => event.set_attribute(record.undo_data()),
+            (ADD_INDEX_ENTRY_ROOT, _) | (ADD_INDEX_ENTRY_ALLOCATION, _) => event.set_index_entry(record.redo_data()),
+            (DELETE_INDEX_ENTRY_ROOT, ADD_INDEX_ENTRY_ROOT)
+            | (DELETE_INDEX_ENTRY_ALLOCATION, ADD_INDEX_ENTRY_ALLOCATION) => event.set_index_entry(record.undo_data()),
+            (UPDATE_RESIDENT_VALUE, UPDATE_RESIDENT_VALUE) => event.set_standard_information_update(record, mft_record_size),
+            _ => false,
+        };
+
+        if is_valid && !event.timestamps.is_empty() {
+            Some(event)
+        } else {
+            None
+        }
+    }
+
+    /// uses the `$STANDARD_INFORMATION` and `$FILE_NAME` attributes of a newly initialized `$MFT` entry
+    fn set_file_record(&mut self, data: &[u8], mft_record_size: u64) -> bool {
+        let mut buffer = data.to_vec();
+        buffer.resize(std::cmp::max(data.len(), mft_record_size as usize), 0);
+        let entry = match MftEntry::from_buffer_skip_fixup(buffer, self.record_number) {
+            Ok(entry) => entry,
+            Err(_) => return false,
+        };
+        /* the entry header contains its own record number since NTFS 3.1 */
+        if data.len() >= 0x30 {
+            self.record_number = LittleEndian::read_u32(&data[0x2c..]) as u64;
+        }
+        self.file_reference = Some(MftReference::new(self.record_number, entry.header.sequence));
+
+        for attribute in entry
+            .iter_attributes_matching(Some(vec![MftAttributeType::StandardInformation, MftAttributeType::FileName]))
+            .filter_map(Result::ok) {
+            let instance = attribute.header.instance;
+            match attribute.data {
+                MftAttributeContent::AttrX10(standard_info) => {
+                    /* the `mft` crate rounds timestamps to microseconds */
+                    let timestamps = AttributeRecords::from(&entry)
+                        .find(|r| r.instance() == instance)
+                        .and_then(|r| match r.content() {
+                            AttributeContent::Resident(value) => TimestampTuple::from_standard_info_value(value),
+                            _ => None,
+                        })
+                        .unwrap_or_else(|| TimestampTuple::from(&standard_info));
+                    self.timestamps = LogFileTimestamps::from(&timestamps);
+                }
+                MftAttributeContent::AttrX30(file_name) => {
+                    self.filename = Some(file_name.name);
+                    self.parent = Some(file_name.parent);
+                }
+                _ => (),
+            }
+        }
+        true
+    }
+
+    /// uses a resident `$STANDARD_INFORMATION` or `$FILE_NAME` attribute, which has been created or deleted
+    fn set_attribute(&mut self, data: &[u8]) -> bool {
+        match SlackAttribute::from_slice(data, 0) {
+            Some((attribute, _)) => {
+                self.timestamps = LogFileTimestamps::from(attribute.timestamps());
+                if let SlackAttributeContent::FileName(fn_attr) = attribute.content() {
+                    self.attribute_type = FILE_NAME;
+                    self.filename = Some(fn_attr.filename().clone());
+                    self.parent = Some(*fn_attr.parent());
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// uses an `$I30` index entry, which has been added or deleted
+    fn set_index_entry(&mut self, data: &[u8]) -> bool {
+        match IndexEntry::from_slice(data, 0, false) {
+            Some((entry, _)) if !entry.is_dos_name() => {
+                self.attribute_type = INDEX_ALLOCATION;
+                self.record_number = entry.file_reference().entry;
+                self.file_reference = Some(*entry.file_reference());
+                self.filename = Some(entry.filename().clone());
+                self.parent = Some(*entry.parent());
+                self.timestamps = LogFileTimestamps::from(entry.timestamps());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// uses the timestamps written to `$STANDARD_INFORMATION`. The type of the
+    /// modified attribute is not stored in the log record, but
+    /// `$STANDARD_INFORMATION` is always the first attribute of an `$MFT` entry.
+    fn set_standard_information_update(&mut self, record: &LogRecord, mft_record_size: u64) -> bool {
+        let first_attribute_offset = (0x30 + 2 * (mft_record_size as usize / 512 + 1)).next_multiple_of(8);
+        let data = record.redo_data();
+        let record_offset = record.record_offset() as usize;
+
+        if record.attribute_offset() as usize != first_attribute_offset
+            || record_offset < RESIDENT_HEADER_SIZE
+            || data.is_empty()
+            || data == record.undo_data() {
+            return false;
+        }
+
+        let value_offset = record_offset - RESIDENT_HEADER_SIZE;
+        if !value_offset.is_multiple_of(8)
+            || !data.len().is_multiple_of(8)
+            || value_offset + data.len() > STANDARD_INFORMATION_TIMESTAMPS_SIZE {
+            return false;
+        }
+
+        for (index, filetime) in data.chunks(8).enumerate() {
+            self.timestamps.set(value_offset / 8 + index, LittleEndian::read_u64(filetime));
+        }
+        true
+    }
+}
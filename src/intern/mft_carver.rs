@@ -0,0 +1,97 @@
+use byteorder::{ByteOrder, LittleEndian};
+use mft::MftEntry;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use std::io::Read;
+use crate::intern::carving_reader::CarvingReader;
+
+/// `$MFT` entries are always aligned to sector boundaries
+const SECTOR_SIZE: usize = 512;
+
+///
+/// Scans arbitrary binary data (unallocated space, `pagefile.sys`,
+/// `hiberfil.sys`, memory dumps, ...) for `$MFT` entries. At every sector
+/// boundary, the carver checks for a `FILE` signature and validates the entry
+/// header and its fixups. The data are read only once, so any forward-only
+/// stream can be used.
+///
+/// Only base entries are returned, because nonbase entries cannot be assigned
+/// to their base entry reliably. Entries which occur more than once (e.g. in
+/// multiple memory pages) are returned only once.
+pub struct MftCarver<R> where R: Read {
+    reader: CarvingReader<R>,
+    known_entries: HashSet<u64>,
+}
+
+impl<R> MftCarver<R> where R: Read {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader: CarvingReader::new(reader),
+            known_entries: HashSet::new(),
+        }
+    }
+
+    /// returns the size of the entry which starts at the current position,
+    /// if its header looks valid
+    fn entry_size(header: &[u8]) -> Option<usize> {
+        if &header[0..4] != b"FILE" {
+            return None;
+        }
+
+        let usa_offset = LittleEndian::read_u16(&header[0x04..]) as usize;
+        let usa_size = LittleEndian::read_u16(&header[0x06..]) as usize;
+        let first_attribute_offset = LittleEndian::read_u16(&header[0x14..]) as usize;
+        let used_entry_size = LittleEndian::read_u32(&header[0x18..]) as usize;
+        let total_entry_size = LittleEndian::read_u32(&header[0x1c..]) as usize;
+
+        /* we need the record number, which is stored at 0x2c since NTFS 3.1 */
+        let valid = usa_offset >= 0x30
+            && usa_offset.is_multiple_of(2)
+            && (total_entry_size == 1024 || total_entry_size == 4096)
+            && usa_size == total_entry_size / SECTOR_SIZE + 1
+            && usa_offset + usa_size * 2 <= first_attribute_offset
+            && first_attribute_offset.is_multiple_of(8)
+            && first_attribute_offset + 8 <= used_entry_size
+            && used_entry_size <= total_entry_size;
+        valid.then_some(total_entry_size)
+    }
+
+    /// checks if the current position contains a new, valid base entry
+    fn try_carve(&mut self) -> Option<MftEntry> {
+        let entry_size = Self::entry_size(self.reader.data())?;
+        if !self.reader.fill(entry_size) {
+            return None;
+        }
+
+        let buffer = self.reader.data()[..entry_size].to_vec();
+        let record_number = LittleEndian::read_u32(&buffer[0x2c..]) as u64;
+        let entry = MftEntry::from_buffer(buffer, record_number).ok()?;
+        if entry.valid_fixup != Some(true) || entry.header.base_reference.entry != 0 || entry.header.base_reference.sequence != 0 {
+            return None;
+        }
+
+        let mut hasher = DefaultHasher::new();
+        entry.data.hash(&mut hasher);
+        if !self.known_entries.insert(hasher.finish()) {
+            return None;
+        }
+        Some(entry)
+    }
+}
+
+impl<R> Iterator for MftCarver<R> where R: Read {
+    /// the offset of the entry in the carved data, and the entry itself
+    type Item = (u64, MftEntry);
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.reader.fill(SECTOR_SIZE) {
+            let offset = self.reader.offset();
+            let entry = self.try_carve();
+            self.reader.advance(SECTOR_SIZE);
+            if let Some(entry) = entry {
+                return Some((offset, entry));
+            }
+        }
+        None
+    }
+}
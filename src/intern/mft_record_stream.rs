@@ -0,0 +1,79 @@
+use crate::intern::read_exact::read_until_eof;
+use anyhow::{Result, bail};
+use byteorder::{ByteOrder, LittleEndian};
+use mft::MftEntry;
+use std::io::Read;
+
+/// offset of the allocated size of an entry in the entry header
+const TOTAL_ENTRY_SIZE_OFFSET: usize = 0x1c;
+
+///
+/// Reads `$MFT` entries from a forward-only stream, like the output of a
+/// decompressor. In contrast to [`mft::MftParser`], neither seeking nor the
+/// total size of the `$MFT` are required, so the data are read only once.
+///
+/// As in [`mft::MftParser`], the size of all entries is taken from the header
+/// of the first entry.
+pub struct MftRecordStream<R> where R: Read {
+    reader: R,
+    entry_size: usize,
+    next_record: u64,
+    first_entry: Option<Vec<u8>>,
+}
+
+impl<R> MftRecordStream<R> where R: Read {
+    pub fn new(mut reader: R) -> Result<Self> {
+        let mut header = vec![0; TOTAL_ENTRY_SIZE_OFFSET + 4];
+        reader.read_exact(&mut header)?;
+        let entry_size = LittleEndian::read_u32(&header[TOTAL_ENTRY_SIZE_OFFSET..]) as usize;
+        if entry_size < header.len() || entry_size > 0x10000 {
+            bail!("invalid $MFT entry size: {}", entry_size);
+        }
+
+        let mut first_entry = header;
+        first_entry.resize(entry_size, 0);
+        reader.read_exact(&mut first_entry[TOTAL_ENTRY_SIZE_OFFSET + 4..])?;
+
+        Ok(Self {
+            reader,
+            entry_size,
+            next_record: 0,
+            first_entry: Some(first_entry),
+        })
+    }
+
+    /// reads the next entry, or returns `None` at the end of the stream
+    fn read_entry(&mut self) -> Option<Vec<u8>> {
+        if let Some(entry) = self.first_entry.take() {
+            return Some(entry);
+        }
+
+        let mut buffer = vec![0; self.entry_size];
+        let bytes = match read_until_eof(&mut self.reader, &mut buffer) {
+            Ok(bytes) => bytes,
+            Err(why) => {
+                log::error!("error while reading $MFT entry {}: {}", self.next_record, why);
+                return None;
+            }
+        };
+
+        match bytes {
+            0 => None,
+            bytes if bytes < buffer.len() => {
+                log::warn!("ignoring incomplete $MFT entry {} ({} bytes)", self.next_record, bytes);
+                None
+            }
+            _ => Some(buffer),
+        }
+    }
+}
+
+impl<R> Iterator for MftRecordStream<R> where R: Read {
+    type Item = mft::err::Result<MftEntry>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let buffer = self.read_entry()?;
+        let record_number = self.next_record;
+        self.next_record += 1;
+        Some(MftEntry::from_buffer(buffer, record_number))
+    }
+}
@@ -0,0 +1,127 @@
+use crate::intern::{FilenameInfo, TimestampTuple};
+use byteorder::{ByteOrder, LittleEndian};
+use mft::attribute::header::{MftAttributeHeader, ResidentialHeader};
+use mft::attribute::x10::StandardInfoAttr;
+use mft::attribute::x30::FileNameAttr;
+use mft::MftEntry;
+use std::io::{Cursor, Seek, SeekFrom};
+
+/// attributes are always aligned to 8 bytes
+const ATTRIBUTE_ALIGNMENT: usize = 8;
+
+/// size of the header of a resident attribute without a name
+pub(crate) const RESIDENT_HEADER_SIZE: usize = 0x18;
+
+const STANDARD_INFORMATION: u32 = 0x10;
+const FILE_NAME: u32 = 0x30;
+
+/// size of a `$STANDARD_INFORMATION` attribute value (since NTFS 3.0)
+const STANDARD_INFORMATION_SIZE: usize = 0x48;
+
+/// size of a `$FILE_NAME` attribute value without the name
+pub(crate) const FILE_NAME_HEADER_SIZE: usize = 0x42;
+
+pub enum SlackAttributeContent {
+    StandardInformation(TimestampTuple),
+    FileName(FilenameInfo),
+}
+
+///
+/// an attribute which has been found in the slack of an `$MFT` entry, i.e.
+/// after the `used_entry_size` bytes of the entry. Such attributes are
+/// remainders of former contents of that entry, which may even belong to
+/// another file.
+pub struct SlackAttribute {
+    offset: usize,
+    instance: u16,
+    content: SlackAttributeContent,
+}
+
+impl SlackAttribute {
+    /// returns the offset of the attribute in its `$MFT` entry
+    pub fn offset(&self) -> usize { self.offset }
+    pub fn instance(&self) -> u16 { self.instance }
+    pub fn content(&self) -> &SlackAttributeContent { &self.content }
+
+    pub fn timestamps(&self) -> &TimestampTuple {
+        match &self.content {
+            SlackAttributeContent::StandardInformation(timestamps) => timestamps,
+            SlackAttributeContent::FileName(fn_attr) => fn_attr.timestamps(),
+        }
+    }
+
+    /// searches the slack of `entry` for resident `$STANDARD_INFORMATION` and `$FILE_NAME` attributes
+    pub fn from_entry(entry: &MftEntry) -> Vec<Self> {
+        let data = &entry.data;
+        let end = std::cmp::min(entry.header.total_entry_size as usize, data.len());
+        let mut offset = (entry.header.used_entry_size as usize).next_multiple_of(ATTRIBUTE_ALIGNMENT);
+        let mut attributes = Vec::new();
+
+        while offset + RESIDENT_HEADER_SIZE <= end {
+            match Self::from_slice(&data[offset..end], offset) {
+                Some((attribute, length)) => {
+                    attributes.push(attribute);
+                    offset += length;
+                }
+                None => offset += ATTRIBUTE_ALIGNMENT,
+            }
+        }
+        attributes
+    }
+
+    /// tries to parse an attribute at the beginning of `data`, and returns the
+    /// attribute and its length, if it is plausible
+    pub(crate) fn from_slice(data: &[u8], offset: usize) -> Option<(Self, usize)> {
+        if data.len() < RESIDENT_HEADER_SIZE {
+            return None;
+        }
+        let type_code = LittleEndian::read_u32(&data[0x00..]);
+        let length = LittleEndian::read_u32(&data[0x04..]) as usize;
+        let non_resident = data[0x08];
+        let name_size = data[0x09];
+        let value_length = LittleEndian::read_u32(&data[0x10..]) as usize;
+        let value_offset = LittleEndian::read_u16(&data[0x14..]) as usize;
+
+        if non_resident != 0
+            || name_size != 0
+            || length > data.len()
+            || !length.is_multiple_of(ATTRIBUTE_ALIGNMENT)
+            || value_offset < RESIDENT_HEADER_SIZE
+            || value_offset + value_length > length {
+            return None;
+        }
+
+        let valid_length = match type_code {
+            STANDARD_INFORMATION => value_length == STANDARD_INFORMATION_SIZE,
+            FILE_NAME => value_length >= FILE_NAME_HEADER_SIZE
+                && value_length == FILE_NAME_HEADER_SIZE + data[value_offset + 0x40] as usize * 2,
+            _ => false,
+        };
+        if !valid_length {
+            return None;
+        }
+
+        let mut stream = Cursor::new(&data[..length]);
+        let header = MftAttributeHeader::from_stream(&mut stream).ok()??;
+        if !matches!(header.residential_header, ResidentialHeader::Resident(_)) {
+            return None;
+        }
+        stream.seek(SeekFrom::Start(value_offset as u64)).ok()?;
+
+        let content = match type_code {
+            STANDARD_INFORMATION => {
+                StandardInfoAttr::from_reader(&mut stream).ok()?;
+                SlackAttributeContent::StandardInformation(TimestampTuple::from_standard_info_value(&data[value_offset..length])?)
+            }
+            _ => {
+                let attr = FileNameAttr::from_stream(&mut stream).ok()?;
+                if attr.name.is_empty() {
+                    return None;
+                }
+                SlackAttributeContent::FileName(FilenameInfo::from(&attr, &header, TimestampTuple::from_file_name_value(&data[value_offset..length])?))
+            }
+        };
+
+        Some((Self { offset, instance: header.instance, content }, length))
+    }
+}
@@ -1,11 +1,67 @@
 mod preprocessed_mft;
 mod complete_mft_entry;
 mod timestamp_tuple;
+mod filetime;
+mod bodyfile_line;
 mod filename_info;
 mod usnjrnl;
+mod image;
+mod ntfs;
+mod triage_collection;
+mod compression;
+mod mft_record_stream;
+mod input_source;
+mod mft_carver;
+mod mft_slack;
+mod carving_reader;
+mod usn_reason;
+mod usn_source_info;
+mod file_attributes;
+mod usn_record;
+mod usn_carver;
+mod index_record;
+mod fixup;
+mod read_exact;
+mod logfile;
+mod logfile_event;
+mod report;
+mod file_lifecycle;
+mod usnjrnl_max;
+mod journal_coverage;
+mod burst_detector;
+mod timestomping;
+mod timestamp_class;
 
 pub use preprocessed_mft::{PreprocessedMft, ParentInfo};
 pub use complete_mft_entry::CompleteMftEntry;
 pub use timestamp_tuple::TimestampTuple;
+pub use filetime::Filetime;
+pub use bodyfile_line::BodyfileLine;
 pub use filename_info::FilenameInfo;
-pub use crate::intern::usnjrnl::UsnJrnl;
\ No newline at end of file
+pub use crate::intern::usnjrnl::{UsnJrnl, UsnRecordIterator};
+pub use image::{SharedReader, PartitionReader, PartitionTable, PartitionTableType, Partition, ReadSeek, open_image};
+pub use compression::Compression;
+pub use mft_record_stream::MftRecordStream;
+pub use mft_carver::MftCarver;
+pub use mft_slack::{SlackAttribute, SlackAttributeContent};
+pub use usn_reason::UsnReason;
+pub use usn_source_info::UsnSourceInfo;
+pub use file_attributes::FileAttributes;
+pub use usn_record::{UsnRecord, UsnExtent};
+pub use usn_carver::UsnCarver;
+pub use logfile::{LogRecord, LogRecordIterator};
+pub use logfile_event::{LogFileEvent, LogFileTimestamps, LogOperation, DEFAULT_CLUSTER_SIZE, DEFAULT_MFT_RECORD_SIZE};
+pub use file_lifecycle::{FileLifecycle, NameChange};
+pub use usnjrnl_max::{UsnJrnlMax, DEFAULT_ALLOCATION_DELTA};
+pub use journal_coverage::{JournalCoverage, UsnGap};
+pub use timestomping::{TimestompingFinding, TimestompingRule};
+pub use report::write_report;
+pub use timestamp_class::{TimestampClass, TimestampClassification};
+pub use burst_detector::{Burst, BurstDetector, BurstKind, DEFAULT_BURST_WINDOW, DEFAULT_BURST_MIN_FILES};
+pub use index_record::{IndexEntry, IndexRecordIterator, DEFAULT_INDEX_RECORD_SIZE};
+pub use input_source::InputSource;
+pub use triage_collection::{TriageCollection, TriageVolume};
+pub use image::{ImageFormat, SplitRawReader, VhdReader, VhdxReader};
+#[cfg(feature = "ewf")]
+pub use image::EwfReader;
+pub use ntfs::{NtfsBootSector, NtfsVolume, INDEX_ALLOCATION, NonResidentStream, DataRun, AttributeRecord, AttributeRecords, AttributeContent, parse_data_runs};
@@ -0,0 +1,129 @@
+use byteorder::{ByteOrder, LittleEndian};
+use mft::MftEntry;
+
+pub enum AttributeContent<'a> {
+    Resident(&'a [u8]),
+    NonResident {
+        first_vcn: u64,
+        last_vcn: u64,
+        data_size: u64,
+        data_runs: &'a [u8],
+    },
+}
+
+///
+/// A raw attribute record inside an `$MFT` entry. In contrast to
+/// [`mft::MftAttribute`], this gives access to the data runs of nonresident
+/// attributes and to the offset of the attribute inside of the entry.
+pub struct AttributeRecord<'a> {
+    offset: usize,
+    type_code: u32,
+    length: usize,
+    name: String,
+    instance: u16,
+    content: AttributeContent<'a>,
+}
+
+impl<'a> AttributeRecord<'a> {
+    /// tries to parse an attribute header at `offset`. Returns `None` if there
+    /// is no valid attribute header at this position
+    pub fn parse(data: &'a [u8], offset: usize) -> Option<Self> {
+        if offset + 0x18 > data.len() {
+            return None;
+        }
+        let header = &data[offset..];
+        let type_code = LittleEndian::read_u32(&header[0x00..0x04]);
+        let length = LittleEndian::read_u32(&header[0x04..0x08]) as usize;
+        if type_code == 0xffff_ffff || length < 0x18 || !length.is_multiple_of(8) || length > header.len() {
+            return None;
+        }
+        let header = &header[..length];
+
+        let name_length = header[0x09] as usize;
+        let name_offset = LittleEndian::read_u16(&header[0x0a..0x0c]) as usize;
+        let instance = LittleEndian::read_u16(&header[0x0e..0x10]);
+
+        let name = if name_length == 0 {
+            String::new()
+        } else {
+            if name_offset + name_length * 2 > length {
+                return None;
+            }
+            let name: Vec<u16> = header[name_offset..name_offset + name_length * 2]
+                .chunks(2)
+                .map(LittleEndian::read_u16)
+                .collect();
+            String::from_utf16_lossy(&name)
+        };
+
+        let content = match header[0x08] {
+            0 => {
+                let value_length = LittleEndian::read_u32(&header[0x10..0x14]) as usize;
+                let value_offset = LittleEndian::read_u16(&header[0x14..0x16]) as usize;
+                if value_offset + value_length > length {
+                    return None;
+                }
+                AttributeContent::Resident(&header[value_offset..value_offset + value_length])
+            }
+            1 => {
+                if length < 0x40 {
+                    return None;
+                }
+                let runs_offset = LittleEndian::read_u16(&header[0x20..0x22]) as usize;
+                if runs_offset > length {
+                    return None;
+                }
+                AttributeContent::NonResident {
+                    first_vcn: LittleEndian::read_u64(&header[0x10..0x18]),
+                    last_vcn: LittleEndian::read_u64(&header[0x18..0x20]),
+                    data_size: LittleEndian::read_u64(&header[0x30..0x38]),
+                    data_runs: &header[runs_offset..],
+                }
+            }
+            _ => return None,
+        };
+
+        Some(Self {
+            offset,
+            type_code,
+            length,
+            name,
+            instance,
+            content,
+        })
+    }
+
+    pub fn offset(&self) -> usize { self.offset }
+    pub fn type_code(&self) -> u32 { self.type_code }
+    pub fn length(&self) -> usize { self.length }
+    pub fn name(&self) -> &str { &self.name }
+    pub fn instance(&self) -> u16 { self.instance }
+    pub fn content(&self) -> &AttributeContent<'a> { &self.content }
+}
+
+///
+/// iterates over all attribute records of an `$MFT` entry
+pub struct AttributeRecords<'a> {
+    data: &'a [u8],
+    offset: usize,
+    end: usize,
+}
+
+impl<'a> AttributeRecords<'a> {
+    pub fn from(entry: &'a MftEntry) -> Self {
+        Self {
+            data: &entry.data,
+            offset: entry.header.first_attribute_record_offset as usize,
+            end: std::cmp::min(entry.header.used_entry_size as usize, entry.data.len()),
+        }
+    }
+}
+
+impl<'a> Iterator for AttributeRecords<'a> {
+    type Item = AttributeRecord<'a>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let record = AttributeRecord::parse(&self.data[..self.end], self.offset)?;
+        self.offset += record.length();
+        Some(record)
+    }
+}
@@ -0,0 +1,76 @@
+use anyhow::{Result, bail};
+use byteorder::{ByteOrder, LittleEndian};
+
+/// the largest exponent which is used for cluster sizes (2^31 bytes)
+const MAX_SHIFT: u32 = 31;
+
+/// the smallest and largest supported size of `$MFT` entries and index records
+const MIN_RECORD_SIZE: u64 = 0x200;
+const MAX_RECORD_SIZE: u64 = 0x10000;
+
+///
+/// The relevant parts of an NTFS volume boot record (`$Boot`)
+pub struct NtfsBootSector {
+    bytes_per_sector: u64,
+    cluster_size: u64,
+    total_sectors: u64,
+    mft_lcn: u64,
+    mft_record_size: u64,
+    index_record_size: u64,
+    serial_number: u64,
+}
+
+impl NtfsBootSector {
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        if data.len() < 512 || &data[3..11] != b"NTFS    " {
+            bail!("this is not an NTFS boot sector");
+        }
+
+        let bytes_per_sector = LittleEndian::read_u16(&data[0x0b..0x0d]) as u64;
+        if !(256..=4096).contains(&bytes_per_sector) || !bytes_per_sector.is_power_of_two() {
+            bail!("invalid number of bytes per sector: {}", bytes_per_sector);
+        }
+
+        /* values above 0x80 are used for cluster sizes of 64k and above */
+        let sectors_per_cluster = match data[0x0d] {
+            0 => bail!("invalid number of sectors per cluster: 0"),
+            v if v <= 0x80 => v as u64,
+            v if 256 - v as u32 <= MAX_SHIFT => 1 << (256 - v as u32),
+            v => bail!("invalid number of sectors per cluster: {:#x}", v),
+        };
+        let cluster_size = bytes_per_sector * sectors_per_cluster;
+
+        Ok(Self {
+            bytes_per_sector,
+            cluster_size,
+            total_sectors: LittleEndian::read_u64(&data[0x28..0x30]),
+            mft_lcn: LittleEndian::read_u64(&data[0x30..0x38]),
+            mft_record_size: Self::record_size(data[0x40] as i8, cluster_size)?,
+            index_record_size: Self::record_size(data[0x44] as i8, cluster_size)?,
+            serial_number: LittleEndian::read_u64(&data[0x48..0x50]),
+        })
+    }
+
+    /// positive values are counted in clusters, negative values `n` mean `2^-n` bytes
+    fn record_size(value: i8, cluster_size: u64) -> Result<u64> {
+        let size = if value > 0 {
+            value as u64 * cluster_size
+        } else if value < 0 && -(value as i32) as u32 <= MAX_SHIFT {
+            1 << -(value as i32)
+        } else {
+            bail!("invalid record size: {}", value)
+        };
+        if !(MIN_RECORD_SIZE..=MAX_RECORD_SIZE).contains(&size) {
+            bail!("unsupported record size: {} bytes", size);
+        }
+        Ok(size)
+    }
+
+    pub fn bytes_per_sector(&self) -> u64 { self.bytes_per_sector }
+    pub fn cluster_size(&self) -> u64 { self.cluster_size }
+    pub fn total_sectors(&self) -> u64 { self.total_sectors }
+    pub fn mft_lcn(&self) -> u64 { self.mft_lcn }
+    pub fn mft_record_size(&self) -> u64 { self.mft_record_size }
+    pub fn index_record_size(&self) -> u64 { self.index_record_size }
+    pub fn serial_number(&self) -> u64 { self.serial_number }
+}
@@ -0,0 +1,176 @@
+use anyhow::{Result, bail};
+use std::io::{self, Read, Seek, SeekFrom};
+use crate::intern::image::offset_position;
+
+///
+/// a contiguous range of clusters. Sparse runs don't have an `lcn`.
+#[derive(Debug, Clone)]
+pub struct DataRun {
+    vcn: u64,
+    lcn: Option<u64>,
+    length: u64,
+}
+
+impl DataRun {
+    pub fn vcn(&self) -> u64 { self.vcn }
+    pub fn lcn(&self) -> Option<u64> { self.lcn }
+    pub fn length(&self) -> u64 { self.length }
+    pub fn is_sparse(&self) -> bool { self.lcn.is_none() }
+}
+
+/// decodes the mapping pairs array of a nonresident attribute
+pub fn parse_data_runs(data: &[u8], first_vcn: u64) -> Result<Vec<DataRun>> {
+    let mut runs = Vec::new();
+    let mut offset = 0;
+    let mut vcn = first_vcn;
+    let mut lcn: i64 = 0;
+
+    while offset < data.len() && data[offset] != 0 {
+        let length_size = (data[offset] & 0x0f) as usize;
+        let offset_size = (data[offset] >> 4) as usize;
+        offset += 1;
+
+        if length_size == 0 || length_size > 8 || offset_size > 8 || offset + length_size + offset_size > data.len() {
+            bail!("invalid data run header at offset {}", offset - 1);
+        }
+
+        let length = read_unsigned(&data[offset..offset + length_size]);
+        offset += length_size;
+
+        let run_lcn = if offset_size == 0 {
+            None
+        } else {
+            lcn = match lcn.checked_add(read_signed(&data[offset..offset + offset_size])) {
+                Some(lcn) => lcn,
+                None => bail!("invalid data run offset at offset {}", offset),
+            };
+            offset += offset_size;
+            if lcn < 0 {
+                bail!("data run points to negative cluster {}", lcn);
+            }
+            Some(lcn as u64)
+        };
+
+        runs.push(DataRun {
+            vcn,
+            lcn: run_lcn,
+            length,
+        });
+        vcn = match vcn.checked_add(length) {
+            Some(vcn) => vcn,
+            None => bail!("invalid data run length {}", length),
+        };
+    }
+    Ok(runs)
+}
+
+fn read_unsigned(data: &[u8]) -> u64 {
+    data.iter().rev().fold(0, |acc, b| (acc << 8) | *b as u64)
+}
+
+fn read_signed(data: &[u8]) -> i64 {
+    let value = read_unsigned(data) as i64;
+    let unused_bits = 64 - 8 * data.len() as u32;
+    /* sign extension */
+    (value << unused_bits) >> unused_bits
+}
+
+///
+/// Provides [`Read`] + [`Seek`] access to the content of a nonresident
+/// attribute, whose clusters are described by a list of [`DataRun`]s.
+/// Sparse clusters (and clusters which are not covered by any data run)
+/// are read as zeros.
+pub struct NonResidentStream<R> where R: Read + Seek {
+    reader: R,
+    cluster_size: u64,
+    runs: Vec<DataRun>,
+    size: u64,
+    position: u64,
+}
+
+impl<R> NonResidentStream<R> where R: Read + Seek {
+    pub fn new(reader: R, cluster_size: u64, runs: Vec<DataRun>, size: u64) -> Self {
+        let mut runs = runs;
+        runs.sort_by_key(|r| r.vcn);
+        Self {
+            reader,
+            cluster_size,
+            runs,
+            size,
+            position: 0
+        }
+    }
+
+    pub fn size(&self) -> u64 { self.size }
+    pub fn runs(&self) -> &Vec<DataRun> { &self.runs }
+
+    /// returns the offset of the first byte which is not part of a sparse
+    /// run. Streams like `$UsnJrnl:$J` typically start with a large sparse region.
+    pub fn first_allocated_offset(&self) -> u64 {
+        match self.runs.iter().find(|r| !r.is_sparse()) {
+            Some(run) => std::cmp::min(run.vcn.saturating_mul(self.cluster_size), self.size),
+            None => self.size
+        }
+    }
+
+    /// returns the first run which ends behind `vcn`
+    fn find_run(&self, vcn: u64) -> Option<&DataRun> {
+        let idx = self.runs.partition_point(|r| r.vcn + r.length <= vcn);
+        self.runs.get(idx)
+    }
+
+    /// converts a number of clusters into bytes, failing if the data runs are invalid
+    fn cluster_offset(&self, clusters: u64) -> io::Result<u64> {
+        clusters.checked_mul(self.cluster_size)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("invalid cluster number {}", clusters)))
+    }
+}
+
+impl<R> Read for NonResidentStream<R> where R: Read + Seek {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut bytes_read = 0;
+        while bytes_read < buf.len() && self.position < self.size {
+            let vcn = self.position / self.cluster_size;
+            let end_of_data = std::cmp::min(self.size - self.position, (buf.len() - bytes_read) as u64);
+
+            let (chunk_size, lcn) = match self.find_run(vcn) {
+                Some(run) if run.vcn <= vcn => {
+                    let run_end = self.cluster_offset(run.vcn + run.length)?;
+                    let position_in_run = self.position - self.cluster_offset(run.vcn)?;
+                    let lcn_offset = match run.lcn {
+                        Some(lcn) => Some(self.cluster_offset(lcn)?.checked_add(position_in_run)
+                            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("invalid cluster number {}", lcn)))?),
+                        None => None,
+                    };
+                    (std::cmp::min(run_end - self.position, end_of_data), lcn_offset)
+                }
+                /* there is a gap in the list of data runs */
+                Some(run) => (std::cmp::min(self.cluster_offset(run.vcn)? - self.position, end_of_data), None),
+                None => (end_of_data, None),
+            };
+
+            let dst = &mut buf[bytes_read..bytes_read + chunk_size as usize];
+            match lcn {
+                None => dst.iter_mut().for_each(|b| *b = 0),
+                Some(offset) => {
+                    self.reader.seek(SeekFrom::Start(offset))?;
+                    self.reader.read_exact(dst)?;
+                }
+            }
+            bytes_read += chunk_size as usize;
+            self.position += chunk_size;
+        }
+        Ok(bytes_read)
+    }
+}
+
+impl<R> Seek for NonResidentStream<R> where R: Read + Seek {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.position = match pos {
+            SeekFrom::Start(p) => p,
+            SeekFrom::Current(p) => offset_position(self.position, p)?,
+            SeekFrom::End(p) => offset_position(self.size, p)?,
+        };
+        Ok(self.position)
+    }
+}
@@ -0,0 +1,9 @@
+mod boot_sector;
+mod attribute_record;
+mod data_runs;
+mod ntfs_volume;
+
+pub use boot_sector::NtfsBootSector;
+pub use attribute_record::{AttributeRecord, AttributeRecords, AttributeContent};
+pub use data_runs::{DataRun, NonResidentStream, parse_data_runs};
+pub use ntfs_volume::{NtfsVolume, INDEX_ALLOCATION};
@@ -0,0 +1,185 @@
+use crate::intern::ntfs::{NtfsBootSector, AttributeRecords, AttributeContent, DataRun, NonResidentStream, parse_data_runs};
+use anyhow::{Result, anyhow, bail};
+use mft::attribute::x20::AttributeListAttr;
+use mft::MftEntry;
+use std::collections::HashSet;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use winstructs::ntfs::mft_reference::MftReference;
+
+pub const ATTRIBUTE_LIST: u32 = 0x20;
+pub const DATA: u32 = 0x80;
+pub const INDEX_ALLOCATION: u32 = 0xa0;
+
+///
+/// Provides access to the metadata files of an NTFS volume, which is
+/// accessible using `reader`. Offset `0` of `reader` must point to the volume
+/// boot record.
+pub struct NtfsVolume<R> where R: Read + Seek + Clone {
+    reader: R,
+    boot_sector: NtfsBootSector,
+}
+
+impl<R> NtfsVolume<R> where R: Read + Seek + Clone {
+    pub fn new(reader: R) -> Result<Self> {
+        let mut reader = reader;
+        let mut sector = vec![0; 512];
+        reader.seek(SeekFrom::Start(0))?;
+        reader.read_exact(&mut sector)?;
+        let boot_sector = NtfsBootSector::from_bytes(&sector)?;
+        Ok(Self {
+            reader,
+            boot_sector
+        })
+    }
+
+    pub fn boot_sector(&self) -> &NtfsBootSector {
+        &self.boot_sector
+    }
+
+    /// opens the `$MFT`, whose location is described by the `$DATA` attribute of its own first entry
+    pub fn mft_stream(&self) -> Result<NonResidentStream<R>> {
+        let mut buffer = vec![0; self.boot_sector.mft_record_size() as usize];
+        let mut reader = self.reader.clone();
+        let mft_offset = match self.boot_sector.mft_lcn().checked_mul(self.boot_sector.cluster_size()) {
+            Some(offset) => offset,
+            None => bail!("invalid location of the $MFT: cluster {}", self.boot_sector.mft_lcn()),
+        };
+        reader.seek(SeekFrom::Start(mft_offset))?;
+        reader.read_exact(&mut buffer)?;
+        let entry = MftEntry::from_buffer(buffer, 0)?;
+        if &entry.header.signature != b"FILE" {
+            bail!("invalid signature of the first $MFT entry");
+        }
+
+        /*
+         * if the $MFT is heavily fragmented, some of its data runs are stored
+         * in extension entries, which must be read from the $MFT itself. 
+         * So, we start with the data runs stored in the base entry.
+         */
+        let (runs, size) = self.attribute_runs::<Cursor<Vec<u8>>>(&entry, DATA, "", None)?;
+        let mut mft = self.new_stream(runs, size);
+        self.attribute_stream(&entry, DATA, "", &mut mft)
+    }
+
+    /// reads the entry with the number `record_number` from `mft`
+    pub fn read_entry<S: Read + Seek>(&self, mft: &mut S, record_number: u64) -> Result<MftEntry> {
+        let record_size = self.boot_sector.mft_record_size();
+        let mut buffer = vec![0; record_size as usize];
+        mft.seek(SeekFrom::Start(record_number * record_size))?;
+        mft.read_exact(&mut buffer)?;
+        Ok(MftEntry::from_buffer(buffer, record_number)?)
+    }
+
+    /// opens the named or unnamed `$DATA` stream of the file referenced by `reference`
+    pub fn open_stream(&self, reference: &MftReference, stream_name: &str) -> Result<NonResidentStream<R>> {
+        let mut mft = self.mft_stream()?;
+        let entry = self.read_referenced_entry(&mut mft, reference)?;
+        self.attribute_stream(&entry, DATA, stream_name, &mut mft)
+    }
+
+    /// reads the content of the named or unnamed `$DATA` stream of the file
+    /// referenced by `reference`, which can be resident as well
+    pub fn read_stream(&self, reference: &MftReference, stream_name: &str) -> Result<Vec<u8>> {
+        let mut mft = self.mft_stream()?;
+        let entry = self.read_referenced_entry(&mut mft, reference)?;
+        let resident = AttributeRecords::from(&entry)
+            .filter(|a| a.type_code() == DATA && a.name() == stream_name)
+            .find_map(|a| match a.content() {
+                AttributeContent::Resident(data) => Some(data.to_vec()),
+                AttributeContent::NonResident { .. } => None,
+            });
+        match resident {
+            Some(content) => Ok(content),
+            None => {
+                let mut content = Vec::new();
+                self.attribute_stream(&entry, DATA, stream_name, &mut mft)?.read_to_end(&mut content)?;
+                Ok(content)
+            }
+        }
+    }
+
+    /// reads the entry of `reference`, and checks if it still belongs to that file
+    fn read_referenced_entry<S: Read + Seek>(&self, mft: &mut S, reference: &MftReference) -> Result<MftEntry> {
+        let entry = self.read_entry(mft, reference.entry)?;
+        if entry.header.sequence != reference.sequence {
+            bail!("$MFT entry {} has sequence number {}, but {} was expected", reference.entry, entry.header.sequence, reference.sequence);
+        }
+        Ok(entry)
+    }
+
+    /// opens the content of the nonresident attribute of type `type_code`
+    /// named `name`. All parts of the attribute which are stored in extension
+    /// entries are read from `mft`.
+    pub fn attribute_stream<S: Read + Seek>(&self, entry: &MftEntry, type_code: u32, name: &str, mft: &mut S) -> Result<NonResidentStream<R>> {
+        let (runs, size) = self.attribute_runs(entry, type_code, name, Some(mft))?;
+        Ok(self.new_stream(runs, size))
+    }
+
+    fn new_stream(&self, runs: Vec<DataRun>, size: u64) -> NonResidentStream<R> {
+        NonResidentStream::new(self.reader.clone(), self.boot_sector.cluster_size(), runs, size)
+    }
+
+    fn attribute_runs<S: Read + Seek>(&self, entry: &MftEntry, type_code: u32, name: &str, mft: Option<&mut S>) -> Result<(Vec<DataRun>, u64)> {
+        let mut runs = Vec::new();
+        let mut size = None;
+        Self::collect_runs(entry, type_code, name, &mut runs, &mut size)?;
+
+        if let Some(mft) = mft {
+            let mut visited = HashSet::new();
+            visited.insert(entry.header.record_number);
+
+            for list_entry in self.attribute_list(entry)?.entries {
+                if list_entry.attribute_type != type_code
+                    || list_entry.name != name
+                    || visited.contains(&list_entry.segment_reference.entry) {
+                    continue;
+                }
+                visited.insert(list_entry.segment_reference.entry);
+                let extension = self.read_entry(mft, list_entry.segment_reference.entry)?;
+                Self::collect_runs(&extension, type_code, name, &mut runs, &mut size)?;
+            }
+        }
+
+        match size {
+            Some(size) => Ok((runs, size)),
+            None => Err(anyhow!("no nonresident attribute of type {:#x} named '{}' found in entry {}", type_code, name, entry.header.record_number))
+        }
+    }
+
+    fn collect_runs(entry: &MftEntry, type_code: u32, name: &str, runs: &mut Vec<DataRun>, size: &mut Option<u64>) -> Result<()> {
+        for attribute in AttributeRecords::from(entry) {
+            if attribute.type_code() != type_code || attribute.name() != name {
+                continue;
+            }
+            match attribute.content() {
+                AttributeContent::NonResident{first_vcn, data_size, data_runs, ..} => {
+                    if *first_vcn == 0 {
+                        *size = Some(*data_size);
+                    }
+                    runs.extend(parse_data_runs(data_runs, *first_vcn)?);
+                }
+                AttributeContent::Resident(_) => bail!("attribute of type {:#x} named '{}' in entry {} is resident", type_code, name, entry.header.record_number),
+            }
+        }
+        Ok(())
+    }
+
+    /// reads the `$ATTRIBUTE_LIST` of `entry`, if there is any
+    fn attribute_list(&self, entry: &MftEntry) -> Result<AttributeListAttr> {
+        let content = match AttributeRecords::from(entry).find(|a| a.type_code() == ATTRIBUTE_LIST) {
+            None => return Ok(AttributeListAttr { entries: Vec::new() }),
+            Some(attribute) => match attribute.content() {
+                AttributeContent::Resident(data) => data.to_vec(),
+                AttributeContent::NonResident { .. } => {
+                    /* an attribute list never refers to another attribute list */
+                    let (runs, size) = self.attribute_runs::<Cursor<Vec<u8>>>(entry, ATTRIBUTE_LIST, "", None)?;
+                    let mut content = Vec::new();
+                    self.new_stream(runs, size).read_to_end(&mut content)?;
+                    content
+                }
+            }
+        };
+        let size = content.len() as u64;
+        Ok(AttributeListAttr::from_stream(&mut Cursor::new(content), Some(size))?)
+    }
+}
@@ -1,8 +1,15 @@
 use mft::MftEntry;
 use std::collections::HashMap;
 use winstructs::ntfs::mft_reference::MftReference;
-use crate::intern::CompleteMftEntry;
-use usnjrnl::CommonUsnRecord;
+use crate::intern::{Burst, BurstDetector, CompleteMftEntry, FileLifecycle, Filetime, IndexEntry, LogFileEvent, TimestampClass, TimestampClassification, TimestompingFinding, UsnRecord};
+use crate::intern::timestamp_class::classify_tuple;
+
+/// the folder which contains all files whose parent is unknown
+const ORPHAN_FILES: &str = "/$OrphanFiles";
+
+/// the entry numbers of `$MFT` and `$Volume`
+const MFT_ENTRY: u64 = 0;
+const VOLUME_ENTRY: u64 = 3;
 
 pub struct ParentInfo {
     pub full_path: String,
@@ -12,15 +19,90 @@ pub struct ParentInfo {
 
 #[derive(Default)]
 pub struct PreprocessedMft {
-    complete_entries: HashMap<MftReference, CompleteMftEntry>
+    complete_entries: HashMap<MftReference, CompleteMftEntry>,
+    carved_entries: Vec<CompleteMftEntry>,
+    index_entries: HashMap<MftReference, Vec<IndexEntry>>,
+    volume_tag: Option<String>,
+    show_slack: bool,
+    detect_timestomping: bool,
+    acquisition_time: Option<Filetime>,
+    timestamp_filter: Vec<TimestampClass>,
+
+    /// the references of `$MFT` and `$Volume`, whose sequence numbers may vary
+    mft_reference: Option<MftReference>,
+    volume_reference: Option<MftReference>,
 }
 
 
 impl PreprocessedMft {
+    /// sets the name of the volume, which is prepended to all paths in the bodyfile (like `C:/Windows`)
+    pub fn set_volume_tag(&mut self, volume_tag: Option<String>) {
+        self.volume_tag = volume_tag;
+    }
+
+    /// prepends the volume tag (if any) to `path`
+    pub fn tagged_path(&self, path: String) -> String {
+        match &self.volume_tag {
+            Some(tag) => format!("{}:{}", tag, path),
+            None => path,
+        }
+    }
+
+    /// enables the output of attributes found in the slack of `$MFT` entries
+    pub fn set_show_slack(&mut self, show_slack: bool) {
+        self.show_slack = show_slack;
+    }
+
+    pub fn show_slack(&self) -> bool {
+        self.show_slack
+    }
+
+    /// enables the annotation of `$STANDARD_INFORMATION` lines with the
+    /// rules of [`TimestompingFinding`] which match the entry
+    pub fn set_detect_timestomping(&mut self, detect_timestomping: bool) {
+        self.detect_timestomping = detect_timestomping;
+    }
+
+    pub fn detect_timestomping(&self) -> bool {
+        self.detect_timestomping
+    }
+
+    /// sets the time of the acquisition, after which no timestamp is plausible
+    pub fn set_acquisition_time(&mut self, acquisition_time: Option<Filetime>) {
+        self.acquisition_time = acquisition_time;
+    }
+
+    pub fn acquisition_time(&self) -> Option<Filetime> {
+        self.acquisition_time
+    }
+
+    /// exports only entries which have at least one timestamp of one of
+    /// `classes`. An empty list disables the filter.
+    pub fn set_timestamp_filter(&mut self, classes: Vec<TimestampClass>) {
+        self.timestamp_filter = classes;
+    }
+
+    /// returns the creation time of the volume, which is the creation time of
+    /// `$MFT` (or of `$Volume`, if `$MFT` is unknown)
+    pub fn volume_created(&self) -> Option<Filetime> {
+        [self.mft_reference, self.volume_reference]
+            .iter()
+            .flatten()
+            .filter_map(|r| self.complete_entries.get(r))
+            .filter_map(|e| e.standard_info_timestamps())
+            .map(|t| t.created())
+            .find(|t| !t.is_zero())
+    }
+
     pub fn add_entry(&mut self, entry: MftEntry) {
         let reference = MftReference::new(entry.header.record_number, entry.header.sequence);
 
         if PreprocessedMft::is_base_entry(&entry) {
+            match reference.entry {
+                MFT_ENTRY => self.mft_reference = Some(reference),
+                VOLUME_ENTRY => self.volume_reference = Some(reference),
+                _ => (),
+            }
             match self.complete_entries.get_mut(&reference) {
                 Some(e) => e.set_base_entry(reference, entry),
                 None => {
@@ -45,7 +127,44 @@ impl PreprocessedMft {
         }
     }
 
-    pub fn add_usnjrnl_records(&mut self, reference: MftReference, records: Vec<CommonUsnRecord>) {
+    /// adds a base entry which has been carved at `offset` from some data other than the `$MFT`.
+    /// Carved entries are stored separately, because the same entry may exist in the `$MFT` and
+    /// in the carved data, possibly with different contents. Carved entries without a `$FILE_NAME`
+    /// attribute are ignored, because they cannot be displayed in a meaningful way.
+    ///
+    /// Returns `true` if the entry has been added.
+    pub fn add_carved_entry(&mut self, entry: MftEntry, offset: u64) -> bool {
+        let reference = MftReference::new(entry.header.record_number, entry.header.sequence);
+        let ce = CompleteMftEntry::from_carved_entry(reference, entry, offset);
+        if ce.mft_filename().is_none() || ce.parent() == Some(&reference) {
+            return false;
+        }
+        self.carved_entries.push(ce);
+        true
+    }
+
+    /// adds an entry of a `$I30` index. Current entries are only needed if the file they refer
+    /// to is no longer allocated, and slack entries are only needed if they differ from the
+    /// current name of the file. Duplicates (e.g. from multiple sources) are ignored.
+    ///
+    /// Returns `true` if the entry has been added.
+    pub fn add_index_entry(&mut self, entry: IndexEntry) -> bool {
+        if let Some(file) = self.complete_entries.get(entry.file_reference()) {
+            if file.is_allocated() && (!entry.is_slack()
+                || file.parent() == Some(entry.parent()) && file.mft_filename() == Some(entry.filename())) {
+                return false;
+            }
+        }
+
+        let entries = self.index_entries.entry(*entry.file_reference()).or_default();
+        if entries.iter().any(|e| e.is_same_entry(&entry)) {
+            return false;
+        }
+        entries.push(entry);
+        true
+    }
+
+    pub fn add_usnjrnl_records(&mut self, reference: MftReference, records: Vec<UsnRecord>) {
         match self.complete_entries.get_mut(&reference) {
             Some(e) => {
                 e.add_usnjrnl_records(records);
@@ -57,45 +176,160 @@ impl PreprocessedMft {
         }
     }
 
+    /// adds events decoded from `$LogFile`. Events which do not contain the sequence
+    /// number of their `$MFT` entry are added to the entry with the same record number,
+    /// preferring allocated entries. Returns the number of events which could not be assigned.
+    pub fn add_logfile_events(&mut self, events: Vec<LogFileEvent>) -> usize {
+        let mut references: HashMap<u64, MftReference> = HashMap::new();
+        for (reference, entry) in self.complete_entries.iter() {
+            if entry.is_allocated() || !references.contains_key(&reference.entry) {
+                let _ = references.insert(reference.entry, *reference);
+            }
+        }
+
+        let mut unassigned = 0;
+        let mut events_by_file: HashMap<MftReference, Vec<LogFileEvent>> = HashMap::new();
+        for event in events {
+            let reference = match event.file_reference() {
+                Some(reference) => *reference,
+                None => match references.get(&event.record_number()) {
+                    Some(reference) => *reference,
+                    None => {
+                        unassigned += 1;
+                        continue;
+                    }
+                }
+            };
+            events_by_file.entry(reference).or_default().push(event);
+        }
+
+        for (reference, events) in events_by_file {
+            match self.complete_entries.get_mut(&reference) {
+                Some(e) => e.add_logfile_events(events),
+                None => {
+                    let ce = CompleteMftEntry::from_logfile_events(reference, events);
+                    let _ = self.complete_entries.insert(reference, ce);
+                }
+            }
+        }
+        unassigned
+    }
+
     pub fn is_base_entry(entry: &MftEntry) -> bool {
         entry.header.base_reference.entry == 0 && entry.header.base_reference.sequence == 0
     }
 
-    pub fn get_full_path(&self, reference: &MftReference) -> ParentInfo {
+    /// searches the entry of a parent folder
+    fn find_parent(&self, reference: &MftReference) -> Option<(MftReference, &CompleteMftEntry)> {
         if let Some(entry) = self.complete_entries.get(reference) {
-            return ParentInfo {
-                full_path: entry.get_full_path(self),
-                is_allocated: entry.is_allocated(),
-                reference: Some(*reference)
-            }
+            return Some((*reference, entry));
         }
 
         // if the parent folder was already deleted, the sequence number is incremented
         let deleted_ref = MftReference::new(reference.entry, reference.sequence + 1);
-        if let Some(entry) = self.complete_entries.get(&deleted_ref) {
-            if ! entry.is_allocated() {
-                return ParentInfo {
-                    full_path: entry.get_full_path(self),
-                    is_allocated: entry.is_allocated(),
-                    reference: Some(deleted_ref)
-                }
+        match self.complete_entries.get(&deleted_ref) {
+            Some(entry) if !entry.is_allocated() => Some((deleted_ref, entry)),
+            _ => None,
+        }
+    }
+
+    pub fn get_full_path(&self, reference: &MftReference) -> ParentInfo {
+        match self.find_parent(reference) {
+            Some((reference, entry)) => ParentInfo {
+                full_path: entry.get_full_path(self),
+                is_allocated: entry.is_allocated(),
+                reference: Some(reference)
+            },
+            None => ParentInfo {
+                full_path: ORPHAN_FILES.to_string(),
+                is_allocated: false,
+                reference: None
             }
         }
+    }
+
+    /// returns the path of the folder `reference` at the time of the `$UsnJrnl` record `usn`
+    pub fn get_path_at(&self, reference: &MftReference, usn: i64) -> String {
+        self.path_at(reference, usn, 0)
+    }
+
+    pub(crate) fn path_at(&self, reference: &MftReference, usn: i64, depth: usize) -> String {
+        match self.find_parent(reference) {
+            Some((_, entry)) => entry.path_at(self, usn, depth),
+            None => ORPHAN_FILES.to_string(),
+        }
+    }
 
-        ParentInfo {
-            full_path: "/$OrphanFiles".to_string(),
-            is_allocated: false,
-            reference: None
+    /// searches for the allocated entry with the absolute path `path` (like `/$Extend/$UsnJrnl`).
+    /// As in NTFS, the comparison of filenames is case insensitive.
+    pub fn find_by_path(&self, path: &str) -> Option<MftReference> {
+        let mut current = *self.complete_entries
+            .iter()
+            .find(|(reference, entry)| reference.entry == 5 && entry.is_allocated())?
+            .0;
+
+        for name in path.split('/').filter(|n| !n.is_empty()) {
+            current = *self.complete_entries
+                .iter()
+                .find(|(_, entry)| entry.is_allocated()
+                    && entry.parent() == Some(&current)
+                    && entry.mft_filename().is_some_and(|f| f.to_lowercase() == name.to_lowercase()))?
+                .0;
         }
+        Some(current)
     }
 
     pub fn bodyfile_lines_count(&self) -> usize {
-        self.complete_entries.values().map(|e| e.bodyfile_lines_count()).sum()
+        self.all_entries()
+            .filter(|e| self.matches_timestamp_filter(e))
+            .map(|e| e.bodyfile_lines_count() + if self.show_slack { e.slack_lines_count() } else { 0 })
+            .sum::<usize>()
+            + self.index_entries.values().flatten().filter(|e| self.index_entry_matches_timestamp_filter(e)).count()
     }
     
     pub fn iter_entries<'a>(&'a self, usnjrnl_longflags: bool) -> Box<dyn Iterator<Item=String> + 'a>{
-        Box::new(self.complete_entries
-            .values()
-            .flat_map(move |c| c.bodyfile_lines(self, usnjrnl_longflags)))
+        Box::new(self.all_entries()
+            .filter(move |c| self.matches_timestamp_filter(c))
+            .flat_map(move |c| c.bodyfile_lines(self, usnjrnl_longflags))
+            .chain(self.index_entries
+                .values()
+                .flatten()
+                .filter(move |e| self.index_entry_matches_timestamp_filter(e))
+                .map(move |e| e.bodyfile_line(self))))
+    }
+
+    fn matches_timestamp_filter(&self, entry: &CompleteMftEntry) -> bool {
+        self.timestamp_filter.is_empty()
+            || entry.timestamp_classes(self).iter().any(|c| self.timestamp_filter.contains(c))
+    }
+
+    fn index_entry_matches_timestamp_filter(&self, entry: &IndexEntry) -> bool {
+        self.timestamp_filter.is_empty()
+            || classify_tuple(entry.timestamps(), self).iter().any(|(_, _, class)| self.timestamp_filter.contains(class))
+    }
+
+    /// returns a summary of the life of every file
+    pub fn iter_lifecycles(&self) -> impl Iterator<Item=FileLifecycle> + '_ {
+        self.all_entries().map(move |e| FileLifecycle::from_entry(e, self))
+    }
+
+    /// returns all timestamps which are not [`TimestampClass::Valid`]
+    pub fn iter_invalid_timestamps(&self) -> impl Iterator<Item=TimestampClassification> + '_ {
+        self.all_entries()
+            .flat_map(move |e| TimestampClassification::classify_invalid(e, self))
+    }
+
+    pub fn iter_timestomping_findings(&self) -> impl Iterator<Item=TimestompingFinding> + '_ {
+        self.all_entries().flat_map(move |e| TimestompingFinding::check(e, self))
+    }
+
+    /// returns all bursts of encryption or deletion events found in `$UsnJrnl`
+    pub fn detect_bursts(&self, detector: &BurstDetector) -> Vec<Burst> {
+        detector.detect(self)
+    }
+
+    /// iterates over all complete and carved entries
+    pub(crate) fn all_entries(&self) -> impl Iterator<Item=&CompleteMftEntry> + '_ {
+        self.complete_entries.values().chain(self.carved_entries.iter())
     }
 }
@@ -0,0 +1,23 @@
+use anyhow::Result;
+use std::io::{ErrorKind, Read};
+
+/// reads exactly `buffer.len()` bytes from `reader`, or returns `Ok(false)`
+/// at the end of the stream
+pub(crate) fn read_exact_or_eof<R>(reader: &mut R, buffer: &mut [u8]) -> Result<bool> where R: Read {
+    Ok(read_until_eof(reader, buffer)? == buffer.len())
+}
+
+/// fills `buffer` from `reader` and returns the number of bytes read, which
+/// is less than `buffer.len()` only at the end of the stream
+pub(crate) fn read_until_eof<R>(reader: &mut R, buffer: &mut [u8]) -> Result<usize> where R: Read {
+    let mut bytes = 0;
+    while bytes < buffer.len() {
+        match reader.read(&mut buffer[bytes..]) {
+            Ok(0) => break,
+            Ok(count) => bytes += count,
+            Err(why) if why.kind() == ErrorKind::Interrupted => continue,
+            Err(why) => return Err(why.into()),
+        }
+    }
+    Ok(bytes)
+}
@@ -0,0 +1,53 @@
+use crate::intern::Filetime;
+use anyhow::Result;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+/// creates a line of a CSV file. Fields which contain a separator, a quote
+/// or a line break are quoted.
+pub fn csv_line<S>(fields: &[S]) -> String where S: AsRef<str> {
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|field| {
+            let field = field.as_ref();
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.to_owned()
+            }
+        })
+        .collect();
+    fields.join(",")
+}
+
+/// formats a timestamp for a report, or returns an empty string if there is
+/// none. Fractions of a second are only shown if there are any.
+pub fn format_timestamp(timestamp: Option<Filetime>) -> String {
+    let timestamp = match timestamp {
+        Some(timestamp) => timestamp,
+        None => return String::new(),
+    };
+    match timestamp.to_datetime() {
+        Some(datetime) if timestamp.subsec_intervals() == 0 => datetime.format("%Y-%m-%d %H:%M:%S").to_string(),
+        Some(datetime) => {
+            let fraction = format!("{:07}", timestamp.subsec_intervals());
+            format!("{}.{}", datetime.format("%Y-%m-%d %H:%M:%S"), fraction.trim_end_matches('0'))
+        }
+        None => String::new(),
+    }
+}
+
+/// writes the CSV `lines` of a report to `report`. The `header` is written
+/// only if the file is empty, so that the reports of several volumes can be
+/// appended to the same file.
+pub fn write_report<I>(report: &mut File, header: &str, lines: I) -> Result<()> where I: IntoIterator<Item=String> {
+    let mut writer = BufWriter::new(report);
+    if writer.get_ref().metadata()?.len() == 0 {
+        writeln!(writer, "{}", header)?;
+    }
+    for line in lines {
+        writeln!(writer, "{}", line)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
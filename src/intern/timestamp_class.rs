@@ -0,0 +1,166 @@
+use crate::intern::report::{csv_line, format_timestamp};
+use crate::intern::{CompleteMftEntry, Filetime, PreprocessedMft, TimestampTuple};
+use anyhow::{Result, bail};
+use std::fmt;
+use std::str::FromStr;
+use winstructs::ntfs::mft_reference::MftReference;
+
+/// the plausibility of a single timestamp
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TimestampClass {
+    Valid,
+
+    /// the timestamp has never been set
+    Zero,
+
+    /// the `FILETIME` is negative, which cannot be converted by Windows
+    Before1601,
+
+    /// the timestamp lies after the acquisition of the volume
+    AfterAcquisition,
+
+    /// the timestamp lies before the creation of the volume, i.e. of `$MFT` or `$Volume`
+    OutsideVolumeLifetime,
+}
+
+impl TimestampClass {
+    pub const NAMES: [&'static str; 5] = ["valid", "zero", "before_1601", "after_acquisition", "outside_volume_lifetime"];
+
+    /// classifies `timestamp`. Without `volume_created` or `acquisition_time`,
+    /// the respective bound is not checked.
+    pub fn of(timestamp: Filetime, volume_created: Option<Filetime>, acquisition_time: Option<Filetime>) -> Self {
+        if timestamp.is_zero() {
+            Self::Zero
+        } else if timestamp.is_before_1601() {
+            Self::Before1601
+        } else if acquisition_time.is_some_and(|a| timestamp > a) {
+            Self::AfterAcquisition
+        } else if volume_created.is_some_and(|v| timestamp < v) {
+            Self::OutsideVolumeLifetime
+        } else {
+            Self::Valid
+        }
+    }
+}
+
+impl fmt::Display for TimestampClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Valid => write!(f, "valid"),
+            Self::Zero => write!(f, "zero"),
+            Self::Before1601 => write!(f, "before_1601"),
+            Self::AfterAcquisition => write!(f, "after_acquisition"),
+            Self::OutsideVolumeLifetime => write!(f, "outside_volume_lifetime"),
+        }
+    }
+}
+
+impl FromStr for TimestampClass {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "valid" => Ok(Self::Valid),
+            "zero" => Ok(Self::Zero),
+            "before_1601" => Ok(Self::Before1601),
+            "after_acquisition" => Ok(Self::AfterAcquisition),
+            "outside_volume_lifetime" => Ok(Self::OutsideVolumeLifetime),
+            _ => bail!("unknown timestamp class: '{}'", s),
+        }
+    }
+}
+
+///
+/// the class of one of the timestamps of `$STANDARD_INFORMATION` or
+/// `$FILE_NAME`. The modification time is never classified as
+/// [`TimestampClass::OutsideVolumeLifetime`], because it is kept if a file
+/// is copied from another volume.
+pub struct TimestampClassification {
+    reference: MftReference,
+    path: String,
+    attribute: &'static str,
+    field: &'static str,
+    timestamp: Filetime,
+    class: TimestampClass,
+}
+
+impl TimestampClassification {
+    pub const CSV_HEADER: &'static str = "reference,path,attribute,field,timestamp,filetime,class";
+
+    pub fn reference(&self) -> &MftReference { &self.reference }
+    pub fn path(&self) -> &str { &self.path }
+
+    /// returns `$STANDARD_INFORMATION` or `$FILE_NAME`
+    pub fn attribute(&self) -> &str { self.attribute }
+
+    /// returns `created`, `modified`, `mft_modified` or `accessed`
+    pub fn field(&self) -> &str { self.field }
+    pub fn timestamp(&self) -> Filetime { self.timestamp }
+    pub fn class(&self) -> TimestampClass { self.class }
+
+    /// returns all timestamps of `entry` which are not [`TimestampClass::Valid`]
+    pub fn classify_invalid(entry: &CompleteMftEntry, mft: &PreprocessedMft) -> Vec<Self> {
+        let invalid: Vec<_> = classify_entry(entry, mft)
+            .into_iter()
+            .filter(|(_, _, _, class)| *class != TimestampClass::Valid)
+            .collect();
+        if invalid.is_empty() {
+            return Vec::new();
+        }
+
+        let path = mft.tagged_path(entry.get_full_path(mft));
+        invalid.into_iter()
+            .map(|(attribute, field, timestamp, class)| Self {
+                reference: *entry.base_entry(),
+                path: path.clone(),
+                attribute,
+                field,
+                timestamp,
+                class,
+            })
+            .collect()
+    }
+
+    pub fn to_csv_line(&self) -> String {
+        csv_line(&[
+            format!("{}-{}", self.reference.entry, self.reference.sequence),
+            self.path.clone(),
+            self.attribute.to_owned(),
+            self.field.to_owned(),
+            format_timestamp(Some(self.timestamp)),
+            format!("{:#x}", self.timestamp.raw()),
+            self.class.to_string(),
+        ])
+    }
+}
+
+/// classifies the timestamps of `$STANDARD_INFORMATION` and `$FILE_NAME` of `entry`
+/// as `(attribute, field, timestamp, class)`
+pub(crate) fn classify_entry(entry: &CompleteMftEntry, mft: &PreprocessedMft) -> Vec<(&'static str, &'static str, Filetime, TimestampClass)> {
+    let fn_timestamps = entry.filename_info().as_ref().map(|f| f.timestamps());
+    let attributes = [("$STANDARD_INFORMATION", entry.standard_info_timestamps()), ("$FILE_NAME", fn_timestamps)];
+    let mut classifications = Vec::new();
+    for (attribute, timestamps) in attributes.iter() {
+        if let Some(timestamps) = timestamps {
+            for (field, timestamp, class) in classify_tuple(timestamps, mft) {
+                classifications.push((*attribute, field, timestamp, class));
+            }
+        }
+    }
+    classifications
+}
+
+/// classifies the four timestamps of `timestamps`
+pub(crate) fn classify_tuple(timestamps: &TimestampTuple, mft: &PreprocessedMft) -> Vec<(&'static str, Filetime, TimestampClass)> {
+    let volume_created = mft.volume_created();
+    let acquisition_time = mft.acquisition_time();
+    [
+        ("created", timestamps.created(), volume_created),
+        ("modified", timestamps.modified(), None),
+        ("mft_modified", timestamps.mft_modified(), volume_created),
+        ("accessed", timestamps.accessed(), volume_created),
+    ]
+    .iter()
+    .map(|(field, timestamp, lower_bound)| (*field, *timestamp, TimestampClass::of(*timestamp, *lower_bound, acquisition_time)))
+    .collect()
+}
@@ -1,30 +1,65 @@
 
 use mft::attribute::x10::StandardInfoAttr;
 use mft::attribute::x30::FileNameAttr;
-use chrono::{DateTime, Utc};
-use std::cmp;
+use crate::intern::Filetime;
+use byteorder::{ByteOrder, LittleEndian};
 
+/// offset of the timestamps in the value of a `$FILE_NAME` attribute, after the parent reference
+const FILE_NAME_TIMESTAMPS_OFFSET: usize = 0x08;
+
+/// size of the four timestamps
+const TIMESTAMPS_SIZE: usize = 0x20;
+
+///
+/// the four timestamps of `$STANDARD_INFORMATION` or `$FILE_NAME`. The `mft`
+/// crate rounds timestamps to microseconds, so the raw attribute value should
+/// be used whenever it is available to keep the full precision of 100ns.
+#[derive(PartialEq, Eq)]
 pub struct TimestampTuple {
-    accessed: i64,
-    mft_modified: i64,
-    modified: i64,
-    created: i64,
+    accessed: Filetime,
+    mft_modified: Filetime,
+    modified: Filetime,
+    created: Filetime,
 }
 
 impl TimestampTuple {
-    fn win32_to_unix_timestamp(win32_ts: &DateTime<Utc>) -> i64 {
-        /* any values below 0 cannot be used as unix timestamp */
-        cmp::max(0, win32_ts.timestamp())
+    /// reads the timestamps from the raw value of a `$STANDARD_INFORMATION` attribute
+    pub fn from_standard_info_value(value: &[u8]) -> Option<Self> {
+        Self::from_filetimes(value, 0)
+    }
+
+    /// reads the timestamps from the raw value of a `$FILE_NAME` attribute
+    pub fn from_file_name_value(value: &[u8]) -> Option<Self> {
+        Self::from_filetimes(value, FILE_NAME_TIMESTAMPS_OFFSET)
+    }
+
+    fn from_filetimes(value: &[u8], offset: usize) -> Option<Self> {
+        if value.len() < offset + TIMESTAMPS_SIZE {
+            return None;
+        }
+        let filetime = |index: usize| Filetime::from(LittleEndian::read_u64(&value[offset + index * 8..]));
+        Some(Self {
+            created: filetime(0),
+            modified: filetime(1),
+            mft_modified: filetime(2),
+            accessed: filetime(3),
+        })
+    }
+
+    /// a timestamp which has been set by NTFS has a precision of 100ns, so it
+    /// is very unlikely that it has no fractions of a second
+    fn is_whole_second(filetime: Filetime) -> bool {
+        filetime.timestamp() > 0 && filetime.subsec_intervals() == 0
     }
 }
 
 impl From<&FileNameAttr> for TimestampTuple {
     fn from(attr: &FileNameAttr) -> TimestampTuple {
         TimestampTuple {
-            accessed: Self::win32_to_unix_timestamp(&attr.accessed),
-            mft_modified: Self::win32_to_unix_timestamp(&attr.mft_modified),
-            modified: Self::win32_to_unix_timestamp(&attr.modified),
-            created: Self::win32_to_unix_timestamp(&attr.created)
+            accessed: Filetime::from(&attr.accessed),
+            mft_modified: Filetime::from(&attr.mft_modified),
+            modified: Filetime::from(&attr.modified),
+            created: Filetime::from(&attr.created),
         }
     }
 }
@@ -33,17 +68,22 @@ impl From<&FileNameAttr> for TimestampTuple {
 impl From<&StandardInfoAttr> for TimestampTuple {
     fn from(attr: &StandardInfoAttr) -> TimestampTuple {
         TimestampTuple {
-            accessed: Self::win32_to_unix_timestamp(&attr.accessed),
-            mft_modified: Self::win32_to_unix_timestamp(&attr.mft_modified),
-            modified: Self::win32_to_unix_timestamp(&attr.modified),
-            created: Self::win32_to_unix_timestamp(&attr.created)
+            accessed: Filetime::from(&attr.accessed),
+            mft_modified: Filetime::from(&attr.mft_modified),
+            modified: Filetime::from(&attr.modified),
+            created: Filetime::from(&attr.created),
         }
     }
 }
 
 impl TimestampTuple {
-    pub fn accessed(&self) -> i64 {self.accessed}
-    pub fn mft_modified(&self) -> i64 {self.mft_modified}
-    pub fn modified(&self) -> i64 {self.modified}
-    pub fn created(&self) -> i64 {self.created}
-}
\ No newline at end of file
+    pub fn accessed(&self) -> Filetime {self.accessed}
+    pub fn mft_modified(&self) -> Filetime {self.mft_modified}
+    pub fn modified(&self) -> Filetime {self.modified}
+    pub fn created(&self) -> Filetime {self.created}
+
+    /// returns `true` if the creation or the modification time has no fractions of a second
+    pub fn has_zero_fraction(&self) -> bool {
+        Self::is_whole_second(self.created) || Self::is_whole_second(self.modified)
+    }
+}
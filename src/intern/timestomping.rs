@@ -0,0 +1,134 @@
+use crate::intern::report::{csv_line, format_timestamp};
+use crate::intern::{CompleteMftEntry, Filetime, PreprocessedMft, UsnReason};
+use std::fmt;
+use winstructs::ntfs::mft_reference::MftReference;
+
+/// `$STANDARD_INFORMATION` is written slightly before the `FILE_CREATE` record,
+/// so that both timestamps can differ by a second
+const USNJRNL_TOLERANCE: i64 = 2;
+
+/// a rule which detects timestamps that have probably been manipulated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimestompingRule {
+    /// the creation time of `$STANDARD_INFORMATION` is earlier than the one of `$FILE_NAME`
+    SiCreatedBeforeFn,
+
+    /// the creation or modification time of `$STANDARD_INFORMATION` has no fractions of a second
+    ZeroFraction,
+
+    /// the creation time of `$STANDARD_INFORMATION` is earlier than the `FILE_CREATE` record in `$UsnJrnl`
+    SiCreatedBeforeUsnJrnl,
+
+    /// a creation time is earlier than the creation of the volume
+    CreatedBeforeVolume,
+}
+
+impl fmt::Display for TimestompingRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SiCreatedBeforeFn => write!(f, "si_created_before_fn"),
+            Self::ZeroFraction => write!(f, "zero_fraction"),
+            Self::SiCreatedBeforeUsnJrnl => write!(f, "si_created_before_usnjrnl"),
+            Self::CreatedBeforeVolume => write!(f, "created_before_volume"),
+        }
+    }
+}
+
+///
+/// a hint that the timestamps of a file have been manipulated (timestomping).
+/// Tools which change timestamps can only write `$STANDARD_INFORMATION`, so
+/// its timestamps are compared with those of `$FILE_NAME`, of `$UsnJrnl` and
+/// of the volume.
+pub struct TimestompingFinding {
+    reference: MftReference,
+    path: String,
+    rule: TimestompingRule,
+    details: String,
+}
+
+impl TimestompingFinding {
+    pub const CSV_HEADER: &'static str = "reference,path,rule,details";
+
+    pub fn reference(&self) -> &MftReference { &self.reference }
+    pub fn path(&self) -> &str { &self.path }
+    pub fn rule(&self) -> TimestompingRule { self.rule }
+    pub fn details(&self) -> &str { &self.details }
+
+    /// applies all rules to `entry`
+    pub fn check(entry: &CompleteMftEntry, mft: &PreprocessedMft) -> Vec<Self> {
+        let si = match entry.standard_info_timestamps() {
+            Some(si) => si,
+            None => return Vec::new(),
+        };
+        let fn_timestamps = entry.filename_info().as_ref().map(|f| f.timestamps());
+        let mut matches: Vec<(TimestompingRule, String)> = Vec::new();
+        let mut add = |rule, details| matches.push((rule, details));
+
+        if let Some(fn_timestamps) = fn_timestamps {
+            if !si.created().is_zero() && si.created() < fn_timestamps.created() {
+                add(TimestompingRule::SiCreatedBeforeFn, format!(
+                    "$STANDARD_INFORMATION created {} is earlier than $FILE_NAME created {}",
+                    format_timestamp(Some(si.created())),
+                    format_timestamp(Some(fn_timestamps.created()))));
+            }
+        }
+
+        if si.has_zero_fraction() {
+            add(TimestompingRule::ZeroFraction,
+                "$STANDARD_INFORMATION created or modified has no fractions of a second".to_owned());
+        }
+
+        let file_create = entry
+            .usnjrnl_events()
+            .iter()
+            .map(|e| e.record())
+            .find(|r| r.reason().has_flag(UsnReason::FILE_CREATE))
+            .map(|r| Filetime::from(r.filetime()));
+        if let Some(file_create) = file_create {
+            if !si.created().is_zero() && si.created().timestamp() + USNJRNL_TOLERANCE < file_create.timestamp() {
+                add(TimestompingRule::SiCreatedBeforeUsnJrnl, format!(
+                    "$STANDARD_INFORMATION created {} is earlier than the FILE_CREATE record at {}",
+                    format_timestamp(Some(si.created())),
+                    format_timestamp(Some(file_create))));
+            }
+        }
+
+        if let Some(volume_created) = mft.volume_created() {
+            let created = [("$STANDARD_INFORMATION", Some(si)), ("$FILE_NAME", fn_timestamps)];
+            for (attribute, timestamps) in created.iter() {
+                if let Some(timestamps) = timestamps {
+                    if !timestamps.created().is_zero() && timestamps.created() < volume_created {
+                        add(TimestompingRule::CreatedBeforeVolume, format!(
+                            "{} created {} is earlier than the creation of the volume at {}",
+                            attribute,
+                            format_timestamp(Some(timestamps.created())),
+                            format_timestamp(Some(volume_created))));
+                    }
+                }
+            }
+        }
+        if matches.is_empty() {
+            return Vec::new();
+        }
+
+        /* the path is determined only once, and only if there is a finding */
+        let path = mft.tagged_path(entry.get_full_path(mft));
+        matches.into_iter()
+            .map(|(rule, details)| Self {
+                reference: *entry.base_entry(),
+                path: path.clone(),
+                rule,
+                details,
+            })
+            .collect()
+    }
+
+    pub fn to_csv_line(&self) -> String {
+        csv_line(&[
+            format!("{}-{}", self.reference.entry, self.reference.sequence),
+            self.path.clone(),
+            self.rule.to_string(),
+            self.details.clone(),
+        ])
+    }
+}
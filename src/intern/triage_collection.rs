@@ -0,0 +1,217 @@
+use anyhow::{Result, bail};
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+const ZIP_SIGNATURE: &[u8] = b"PK\x03\x04";
+
+/// names of the `$J` stream of `$Extend/$UsnJrnl`, as they are used by different tools
+const USNJRNL_NAMES: &[&str] = &["$UsnJrnl:$J", "$UsnJrnl_$J", "$J"];
+
+/// names of the `$Max` stream of `$Extend/$UsnJrnl`
+const USNJRNL_MAX_NAMES: &[&str] = &["$UsnJrnl:$Max", "$UsnJrnl_$Max", "$Max"];
+
+///
+/// the artifacts of a single volume, which have been found in a triage collection
+pub struct TriageVolume {
+    tag: String,
+    mft: PathBuf,
+    usnjrnl: Option<PathBuf>,
+    usnjrnl_max: Option<PathBuf>,
+    logfile: Option<PathBuf>,
+}
+
+impl TriageVolume {
+    /// a short name of the volume (like `C`), which is derived from the directory the `$MFT` was found in
+    pub fn tag(&self) -> &str { &self.tag }
+    pub fn mft(&self) -> &Path { &self.mft }
+    pub fn usnjrnl(&self) -> Option<&Path> { self.usnjrnl.as_deref() }
+    pub fn usnjrnl_max(&self) -> Option<&Path> { self.usnjrnl_max.as_deref() }
+    pub fn logfile(&self) -> Option<&Path> { self.logfile.as_deref() }
+}
+
+///
+/// Searches for NTFS artifacts in triage collections, which have been created by tools like
+/// KAPE or Velociraptor. A collection can be either a directory or a zip file. Filenames are
+/// URL-decoded, so that e.g. `$UsnJrnl%3A$J` is found as `$UsnJrnl:$J`.
+///
+/// Files stored in a zip file are extracted to a temporary directory, which is deleted
+/// when the [`TriageCollection`] is dropped.
+pub struct TriageCollection {
+    volumes: Vec<TriageVolume>,
+    _tempdir: Option<tempfile::TempDir>,
+}
+
+/// a file of the collection, with its URL-decoded path components
+struct CollectionFile<S> {
+    components: Vec<String>,
+    source: S,
+}
+
+impl TriageCollection {
+    /// checks if `path` is a directory or a zip file
+    pub fn is_triage_collection(path: &Path) -> bool {
+        if path.is_dir() {
+            return true;
+        }
+        let mut signature = [0; 4];
+        match File::open(path).and_then(|mut f| f.read_exact(&mut signature)) {
+            Ok(()) => signature == ZIP_SIGNATURE,
+            Err(_) => false,
+        }
+    }
+
+    pub fn open(path: &Path) -> Result<Self> {
+        let collection = if path.is_dir() {
+            Self::from_directory(path)?
+        } else {
+            Self::from_zip(path)?
+        };
+        if collection.volumes.is_empty() {
+            bail!("no $MFT found in {}", path.display());
+        }
+        Ok(collection)
+    }
+
+    pub fn volumes(&self) -> &[TriageVolume] {
+        &self.volumes
+    }
+
+    fn from_directory(path: &Path) -> Result<Self> {
+        let mut files = Vec::new();
+        Self::read_directory(path, &mut Vec::new(), &mut files)?;
+        let default_tag = Self::default_tag(path);
+        let volumes = Self::find_volumes(&files, &default_tag)
+            .into_iter()
+            .map(|(tag, mft, usnjrnl, usnjrnl_max, logfile)| TriageVolume {
+                tag,
+                mft: mft.source.clone(),
+                usnjrnl: usnjrnl.map(|f| f.source.clone()),
+                usnjrnl_max: usnjrnl_max.map(|f| f.source.clone()),
+                logfile: logfile.map(|f| f.source.clone()),
+            })
+            .collect();
+        Ok(Self { volumes, _tempdir: None })
+    }
+
+    fn read_directory(path: &Path, components: &mut Vec<String>, files: &mut Vec<CollectionFile<PathBuf>>) -> Result<()> {
+        for entry in fs::read_dir(path)? {
+            let entry = entry?;
+            components.push(url_decode(&entry.file_name().to_string_lossy()));
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                Self::read_directory(&entry.path(), components, files)?;
+            } else if file_type.is_file() {
+                files.push(CollectionFile { components: components.clone(), source: entry.path() });
+            }
+            components.pop();
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "zip")]
+    fn from_zip(path: &Path) -> Result<Self> {
+        let mut archive = zip::ZipArchive::new(File::open(path)?)?;
+        let files: Vec<CollectionFile<usize>> = (0..archive.len())
+            .filter_map(|idx| {
+                let file = archive.by_index(idx).ok()?;
+                if !file.is_file() {
+                    return None;
+                }
+                let components = file.name()
+                    .split(['/', '\\'])
+                    .filter(|c| !c.is_empty())
+                    .map(url_decode)
+                    .collect();
+                Some(CollectionFile { components, source: idx })
+            })
+            .collect();
+
+        let tempdir = tempfile::tempdir()?;
+        let mut extract = |file: &CollectionFile<usize>| -> Result<PathBuf> {
+            let destination = tempdir.path().join(format!("{}", file.source));
+            log::info!("extracting {} to {}", file.components.join("/"), destination.display());
+            std::io::copy(&mut archive.by_index(file.source)?, &mut File::create(&destination)?)?;
+            Ok(destination)
+        };
+
+        let mut volumes = Vec::new();
+        for (tag, mft, usnjrnl, usnjrnl_max, logfile) in Self::find_volumes(&files, &Self::default_tag(path)) {
+            volumes.push(TriageVolume {
+                tag,
+                mft: extract(mft)?,
+                usnjrnl: usnjrnl.map(&mut extract).transpose()?,
+                usnjrnl_max: usnjrnl_max.map(&mut extract).transpose()?,
+                logfile: logfile.map(&mut extract).transpose()?,
+            });
+        }
+        Ok(Self { volumes, _tempdir: Some(tempdir) })
+    }
+
+    #[cfg(not(feature = "zip"))]
+    fn from_zip(path: &Path) -> Result<Self> {
+        bail!("unable to read {}: zip support has not been enabled (feature 'zip')", path.display())
+    }
+
+    /// returns `(tag, $MFT, $UsnJrnl:$J, $UsnJrnl:$Max, $LogFile)` for every `$MFT` found in `files`
+    #[allow(clippy::type_complexity)]
+    fn find_volumes<'a, S>(files: &'a [CollectionFile<S>], default_tag: &str)
+        -> Vec<(String, &'a CollectionFile<S>, Option<&'a CollectionFile<S>>, Option<&'a CollectionFile<S>>, Option<&'a CollectionFile<S>>)> {
+        let find = |root: &[String], path: &[&str]| files.iter().find(|f|
+            f.components.len() == root.len() + path.len()
+            && f.components.starts_with(root)
+            && f.components[root.len()..].iter().zip(path.iter()).all(|(a, b)| a.eq_ignore_ascii_case(b)));
+
+        let mut volumes: Vec<_> = files.iter()
+            .filter(|f| f.components.last().is_some_and(|name| name.eq_ignore_ascii_case("$MFT")))
+            .map(|mft| {
+                let root = &mft.components[..mft.components.len() - 1];
+                let usnjrnl = USNJRNL_NAMES.iter().find_map(|name| find(root, &["$Extend", name]));
+                let usnjrnl_max = USNJRNL_MAX_NAMES.iter().find_map(|name| find(root, &["$Extend", name]));
+                let logfile = find(root, &["$LogFile"]);
+                let tag = match root.last() {
+                    Some(directory) => volume_tag(directory),
+                    None => default_tag.to_owned(),
+                };
+                log::info!("found $MFT of volume '{}' in {}", tag, mft.components.join("/"));
+                (tag, mft, usnjrnl, usnjrnl_max, logfile)
+            })
+            .collect();
+        volumes.sort_by(|a, b| a.0.cmp(&b.0));
+        volumes
+    }
+
+    fn default_tag(path: &Path) -> String {
+        path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default()
+    }
+}
+
+/// derives the volume tag from a directory name like `C`, `C:` or `\\.\C:`
+fn volume_tag(directory: &str) -> String {
+    let tag = directory
+        .trim_start_matches("\\\\.\\")
+        .trim_start_matches("\\\\?\\")
+        .trim_end_matches('\\')
+        .trim_end_matches(':');
+    if tag.is_empty() { directory.to_owned() } else { tag.to_owned() }
+}
+
+/// decodes all `%XX` sequences in `name`. Invalid sequences are kept as they are.
+fn url_decode(name: &str) -> String {
+    let bytes = name.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        if bytes[idx] == b'%' && idx + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[idx + 1..idx + 3]).unwrap_or("");
+            if let Ok(byte) = u8::from_str_radix(hex, 16) {
+                decoded.push(byte);
+                idx += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[idx]);
+        idx += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
@@ -0,0 +1,88 @@
+use crate::intern::carving_reader::CarvingReader;
+use crate::intern::usn_record::{USN_RECORD_HEADER_SIZE, USN_RECORD_MAX_SIZE, USN_RECORD_V2_FILENAME_OFFSET, USN_RECORD_V3_FILENAME_OFFSET};
+use crate::intern::{Filetime, UsnRecord};
+use chrono::{Duration, Utc};
+use std::io::Read;
+
+/// USN records are always aligned to 8 bytes
+const RECORD_ALIGNMENT: usize = 8;
+
+/// the smallest possible record is a `USN_RECORD_V2` with an empty filename
+const USN_RECORD_MIN_SIZE: usize = 0x40;
+
+/// 2000-01-01 00:00:00 UTC; there is no `$UsnJrnl` before Windows 2000
+const FILETIME_MIN: u64 = 125911584000000000;
+
+///
+/// Scans arbitrary binary data (unallocated clusters, volume slack, memory
+/// dumps, ...) for `USN_RECORD_V2` and `USN_RECORD_V3` records. A record is
+/// considered plausible if
+///
+///  - its length matches the length of its filename,
+///  - it has a known version number,
+///  - its filename starts directly after the record header,
+///  - its reason contains only known flags, and
+///  - its timestamp lies between 2000-01-01 and now.
+///
+/// The data are read only once, so any forward-only stream can be used.
+pub struct UsnCarver<R> where R: Read {
+    reader: CarvingReader<R>,
+    filetime_max: u64,
+}
+
+impl<R> UsnCarver<R> where R: Read {
+    pub fn new(reader: R) -> Self {
+        /* allow for some clock skew */
+        let now = Utc::now() + Duration::days(1);
+        Self {
+            reader: CarvingReader::new(reader),
+            filetime_max: Filetime::from(&now).raw(),
+        }
+    }
+
+    /// checks if the current position contains a plausible record
+    fn try_carve(&mut self) -> Option<UsnRecord> {
+        let header = self.reader.data();
+        let record_length = UsnRecord::record_length(header);
+        let major_version = UsnRecord::major_version_of(header);
+        let minor_version = u16::from_le_bytes([header[6], header[7]]);
+        if !(2..=3).contains(&major_version)
+            || minor_version != 0
+            || !(USN_RECORD_MIN_SIZE..=USN_RECORD_MAX_SIZE).contains(&record_length)
+            || !record_length.is_multiple_of(RECORD_ALIGNMENT)
+            || !self.reader.fill(record_length) {
+            return None;
+        }
+
+        let data = &self.reader.data()[..record_length];
+        let record = UsnRecord::from_bytes(data, self.reader.offset()).ok()?;
+        let filename_offset = if major_version == 2 { USN_RECORD_V2_FILENAME_OFFSET } else { USN_RECORD_V3_FILENAME_OFFSET };
+        let filename_length = u16::from_le_bytes([data[filename_offset - 4], data[filename_offset - 3]]) as usize;
+        let filename_start = u16::from_le_bytes([data[filename_offset - 2], data[filename_offset - 1]]) as usize;
+
+        if filename_start != filename_offset
+            || filename_length == 0
+            || record_length != (filename_offset + filename_length).next_multiple_of(RECORD_ALIGNMENT)
+            || !record.reason().is_valid()
+            || !(FILETIME_MIN..=self.filetime_max).contains(&record.filetime()) {
+            return None;
+        }
+        Some(record)
+    }
+}
+
+impl<R> Iterator for UsnCarver<R> where R: Read {
+    type Item = UsnRecord;
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.reader.fill(USN_RECORD_HEADER_SIZE) {
+            match self.try_carve() {
+                Some(record) => {
+                    self.reader.advance(UsnRecord::record_length(self.reader.data()));
+                    return Some(record);
+                }
+                None => self.reader.advance(RECORD_ALIGNMENT),
+            }
+        }
+        None
+    }
+}
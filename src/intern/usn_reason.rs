@@ -0,0 +1,80 @@
+use std::fmt;
+
+/// all reason flags, in the order in which they are displayed
+const REASON_FLAGS: [(u32, &str); 23] = [
+    (0x00008000, "USN_REASON_BASIC_INFO_CHANGE"),
+    (0x80000000, "USN_REASON_CLOSE"),
+    (0x00020000, "USN_REASON_COMPRESSION_CHANGE"),
+    (0x00000002, "USN_REASON_DATA_EXTEND"),
+    (0x00000001, "USN_REASON_DATA_OVERWRITE"),
+    (0x00000004, "USN_REASON_DATA_TRUNCATION"),
+    (0x00000400, "USN_REASON_EA_CHANGE"),
+    (0x00040000, "USN_REASON_ENCRYPTION_CHANGE"),
+    (0x00000100, "USN_REASON_FILE_CREATE"),
+    (0x00000200, "USN_REASON_FILE_DELETE"),
+    (0x00010000, "USN_REASON_HARD_LINK_CHANGE"),
+    (0x00004000, "USN_REASON_INDEXABLE_CHANGE"),
+    (0x00800000, "USN_REASON_INTEGRITY_CHANGE"),
+    (0x00000020, "USN_REASON_NAMED_DATA_EXTEND"),
+    (0x00000010, "USN_REASON_NAMED_DATA_OVERWRITE"),
+    (0x00000040, "USN_REASON_NAMED_DATA_TRUNCATION"),
+    (0x00080000, "USN_REASON_OBJECT_ID_CHANGE"),
+    (0x00002000, "USN_REASON_RENAME_NEW_NAME"),
+    (0x00001000, "USN_REASON_RENAME_OLD_NAME"),
+    (0x00100000, "USN_REASON_REPARSE_POINT_CHANGE"),
+    (0x00000800, "USN_REASON_SECURITY_CHANGE"),
+    (0x00200000, "USN_REASON_STREAM_CHANGE"),
+    (0x00400000, "USN_REASON_TRANSACTED_CHANGE"),
+];
+
+const USN_REASON_PREFIX: &str = "USN_REASON_";
+
+///
+/// the `Reason` field of an `$UsnJrnl` record. When displayed, the flags are
+/// separated by `+`. The `USN_REASON_` prefix is removed, unless the `+` flag
+/// is used in the format string (`{:+}`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UsnReason(u32);
+
+impl From<u32> for UsnReason {
+    fn from(value: u32) -> Self {
+        Self(value)
+    }
+}
+
+impl UsnReason {
+    pub const DATA_OVERWRITE: u32 = 0x00000001;
+    pub const DATA_EXTEND: u32 = 0x00000002;
+    pub const DATA_TRUNCATION: u32 = 0x00000004;
+    pub const FILE_CREATE: u32 = 0x00000100;
+    pub const FILE_DELETE: u32 = 0x00000200;
+    pub const RENAME_OLD_NAME: u32 = 0x00001000;
+    pub const RENAME_NEW_NAME: u32 = 0x00002000;
+    pub const CLOSE: u32 = 0x80000000;
+
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+
+    pub fn has_flag(&self, flag: u32) -> bool {
+        self.0 & flag != 0
+    }
+
+    /// checks if all bits of this value are known reason flags
+    pub fn is_valid(&self) -> bool {
+        let known_flags = REASON_FLAGS.iter().fold(0, |flags, (flag, _)| flags | flag);
+        self.0 != 0 && self.0 & !known_flags == 0
+    }
+}
+
+impl fmt::Display for UsnReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let usnjrnl_longflags = f.sign_plus();
+        let flags: Vec<&str> = REASON_FLAGS
+            .iter()
+            .filter(|(flag, _)| self.has_flag(*flag))
+            .map(|(_, name)| if usnjrnl_longflags { name } else { &name[USN_REASON_PREFIX.len()..] })
+            .collect();
+        write!(f, "{}", flags.join("+"))
+    }
+}
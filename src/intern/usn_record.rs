@@ -0,0 +1,308 @@
+use crate::intern::{FileAttributes, UsnReason, UsnSourceInfo};
+use anyhow::{Result, bail};
+use byteorder::{ByteOrder, LittleEndian};
+use chrono::{DateTime, Utc};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use winstructs::ntfs::mft_reference::MftReference;
+use winstructs::timestamp::WinTimestamp;
+
+/// the size of the common header of all record versions
+pub const USN_RECORD_HEADER_SIZE: usize = 8;
+
+/// offset of the filename in a `USN_RECORD_V2`
+pub const USN_RECORD_V2_FILENAME_OFFSET: usize = 0x3c;
+
+/// offset of the filename in a `USN_RECORD_V3`
+pub const USN_RECORD_V3_FILENAME_OFFSET: usize = 0x4c;
+
+/// offset of the first extent in a `USN_RECORD_V4`
+pub const USN_RECORD_V4_EXTENTS_OFFSET: usize = 0x40;
+
+/// size of a single `USN_RECORD_EXTENT`
+const USN_RECORD_EXTENT_SIZE: usize = 0x10;
+
+/// records are never larger than one page
+pub const USN_RECORD_MAX_SIZE: usize = 0x1000;
+
+/// a range of a file which has been modified, as stored in a `USN_RECORD_V4`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UsnExtent {
+    offset: i64,
+    length: i64,
+}
+
+impl UsnExtent {
+    pub fn offset(&self) -> i64 { self.offset }
+    pub fn length(&self) -> i64 { self.length }
+}
+
+///
+/// a single record of `$UsnJrnl:$J`, which can be a `USN_RECORD_V2`, a
+/// `USN_RECORD_V3` or a `USN_RECORD_V4`. Version 3 and 4 records use 128 bit
+/// file ids; on NTFS volumes, the upper 64 bit are zero and the lower 64 bit
+/// are the `$MFT` reference.
+///
+/// Version 4 records are written if range tracking is enabled, and contain
+/// the ranges of a file which have been modified. They have neither a
+/// timestamp nor a filename, so their timestamp is taken from the following
+/// record of the same file (see [`UsnRecord::set_filetime`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UsnRecord {
+    major_version: u16,
+    file_reference: MftReference,
+    parent_reference: MftReference,
+    has_mft_references: bool,
+    usn: i64,
+    timestamp: u64,
+    reason: UsnReason,
+    source_info: UsnSourceInfo,
+    security_id: u32,
+    file_attributes: FileAttributes,
+    filename: String,
+    extents: Vec<UsnExtent>,
+    remaining_extents: u32,
+    record_hash: u64,
+    offset: u64,
+    journal_index: usize,
+    is_carved: bool,
+}
+
+impl UsnRecord {
+    /// returns the length of the record, as stored in its header
+    pub fn record_length(header: &[u8]) -> usize {
+        LittleEndian::read_u32(&header[0..]) as usize
+    }
+
+    /// returns the major version of the record, as stored in its header
+    pub fn major_version_of(header: &[u8]) -> u16 {
+        LittleEndian::read_u16(&header[4..])
+    }
+
+    /// parses the record in `data`, which has been found at `offset`
+    pub fn from_bytes(data: &[u8], offset: u64) -> Result<Self> {
+        if data.len() < USN_RECORD_HEADER_SIZE {
+            bail!("USN record at offset {:#x} is too short", offset);
+        }
+        let record_length = Self::record_length(data);
+        let major_version = Self::major_version_of(data);
+
+        if major_version == 4 {
+            return Self::from_v4_bytes(data, offset);
+        }
+
+        let (filename_offset, file_reference, parent_reference, fields) = match major_version {
+            2 if record_length >= USN_RECORD_V2_FILENAME_OFFSET && data.len() >= record_length => (
+                USN_RECORD_V2_FILENAME_OFFSET,
+                LittleEndian::read_u64(&data[0x08..]) as u128,
+                LittleEndian::read_u64(&data[0x10..]) as u128,
+                &data[0x18..],
+            ),
+            3 if record_length >= USN_RECORD_V3_FILENAME_OFFSET && data.len() >= record_length => (
+                USN_RECORD_V3_FILENAME_OFFSET,
+                LittleEndian::read_u128(&data[0x08..]),
+                LittleEndian::read_u128(&data[0x18..]),
+                &data[0x28..],
+            ),
+            2 | 3 => bail!("invalid length of USN record at offset {:#x}: {}", offset, record_length),
+            version => bail!("unsupported version of USN record at offset {:#x}: {}", offset, version),
+        };
+
+        let filename_length = LittleEndian::read_u16(&fields[0x20..]) as usize;
+        let filename_start = LittleEndian::read_u16(&fields[0x22..]) as usize;
+        if filename_start < filename_offset || filename_start + filename_length > record_length || !filename_length.is_multiple_of(2) {
+            bail!("invalid filename in USN record at offset {:#x}", offset);
+        }
+        let filename: Vec<u16> = data[filename_start..filename_start + filename_length]
+            .chunks(2)
+            .map(LittleEndian::read_u16)
+            .collect();
+
+        Ok(Self {
+            major_version,
+            file_reference: MftReference::from(file_reference as u64),
+            parent_reference: MftReference::from(parent_reference as u64),
+            has_mft_references: Self::is_mft_reference(file_reference) && Self::is_mft_reference(parent_reference),
+            usn: LittleEndian::read_i64(&fields[0x00..]),
+            timestamp: LittleEndian::read_u64(&fields[0x08..]),
+            reason: UsnReason::from(LittleEndian::read_u32(&fields[0x10..])),
+            source_info: UsnSourceInfo::from(LittleEndian::read_u32(&fields[0x14..])),
+            security_id: LittleEndian::read_u32(&fields[0x18..]),
+            file_attributes: FileAttributes::from(LittleEndian::read_u32(&fields[0x1c..])),
+            filename: String::from_utf16_lossy(&filename),
+            extents: Vec::new(),
+            remaining_extents: 0,
+            record_hash: Self::hash_of(&data[..record_length]),
+            offset,
+            journal_index: 0,
+            is_carved: false,
+        })
+    }
+
+    /// parses a `USN_RECORD_V4`, which contains a list of modified ranges instead of a filename
+    fn from_v4_bytes(data: &[u8], offset: u64) -> Result<Self> {
+        let record_length = Self::record_length(data);
+        if record_length < USN_RECORD_V4_EXTENTS_OFFSET || data.len() < record_length {
+            bail!("invalid length of USN record at offset {:#x}: {}", offset, record_length);
+        }
+        let number_of_extents = LittleEndian::read_u16(&data[0x3c..]) as usize;
+        let extent_size = LittleEndian::read_u16(&data[0x3e..]) as usize;
+        if extent_size != USN_RECORD_EXTENT_SIZE
+            || USN_RECORD_V4_EXTENTS_OFFSET + number_of_extents * extent_size > record_length {
+            bail!("invalid extents in USN record at offset {:#x}", offset);
+        }
+        let extents = data[USN_RECORD_V4_EXTENTS_OFFSET..USN_RECORD_V4_EXTENTS_OFFSET + number_of_extents * extent_size]
+            .chunks(extent_size)
+            .map(|extent| UsnExtent {
+                offset: LittleEndian::read_i64(&extent[0x00..]),
+                length: LittleEndian::read_i64(&extent[0x08..]),
+            })
+            .collect();
+
+        let file_reference = LittleEndian::read_u128(&data[0x08..]);
+        let parent_reference = LittleEndian::read_u128(&data[0x18..]);
+        Ok(Self {
+            major_version: 4,
+            file_reference: MftReference::from(file_reference as u64),
+            parent_reference: MftReference::from(parent_reference as u64),
+            has_mft_references: Self::is_mft_reference(file_reference) && Self::is_mft_reference(parent_reference),
+            usn: LittleEndian::read_i64(&data[0x28..]),
+            timestamp: 0,
+            reason: UsnReason::from(LittleEndian::read_u32(&data[0x30..])),
+            source_info: UsnSourceInfo::from(LittleEndian::read_u32(&data[0x34..])),
+            security_id: 0,
+            file_attributes: FileAttributes::from(0),
+            filename: String::new(),
+            extents,
+            remaining_extents: LittleEndian::read_u32(&data[0x38..]),
+            record_hash: Self::hash_of(&data[..record_length]),
+            offset,
+            journal_index: 0,
+            is_carved: false,
+        })
+    }
+
+    fn hash_of(data: &[u8]) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        data.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// 128 bit file ids can only be used as `$MFT` reference if their upper half is unused
+    fn is_mft_reference(file_id: u128) -> bool {
+        file_id >> 64 == 0
+    }
+
+    pub fn major_version(&self) -> u16 {
+        self.major_version
+    }
+
+    pub fn file_reference(&self) -> &MftReference {
+        &self.file_reference
+    }
+
+    pub fn parent_reference(&self) -> &MftReference {
+        &self.parent_reference
+    }
+
+    /// returns `false` if the 128 bit file ids of this record cannot be
+    /// converted into `$MFT` references (e.g. because they have been written by ReFS)
+    pub fn has_mft_references(&self) -> bool {
+        self.has_mft_references
+    }
+
+    /// returns `true` if this is a `USN_RECORD_V4`, which describes modified ranges of a file
+    pub fn is_range_record(&self) -> bool {
+        self.major_version == 4
+    }
+
+    /// returns the modified ranges of a `USN_RECORD_V4`
+    pub fn extents(&self) -> &[UsnExtent] {
+        &self.extents
+    }
+
+    /// returns the number of extents which are stored in following `USN_RECORD_V4` records
+    pub fn remaining_extents(&self) -> u32 {
+        self.remaining_extents
+    }
+
+    pub fn usn(&self) -> i64 {
+        self.usn
+    }
+
+    /// returns the raw `FILETIME` value of the timestamp
+    pub fn filetime(&self) -> u64 {
+        self.timestamp
+    }
+
+    /// sets the timestamp of a record which has none, like a `USN_RECORD_V4`
+    pub fn set_filetime(&mut self, filetime: u64) {
+        self.timestamp = filetime;
+    }
+
+    pub fn timestamp(&self) -> DateTime<Utc> {
+        WinTimestamp::new(&self.timestamp.to_le_bytes()).unwrap().to_datetime()
+    }
+
+    pub fn reason(&self) -> &UsnReason {
+        &self.reason
+    }
+
+    pub fn source_info(&self) -> &UsnSourceInfo {
+        &self.source_info
+    }
+
+    /// returns the index of the security descriptor of the file in `$Secure`
+    pub fn security_id(&self) -> u32 {
+        self.security_id
+    }
+
+    pub fn file_attributes(&self) -> &FileAttributes {
+        &self.file_attributes
+    }
+
+    pub fn filename(&self) -> &str {
+        &self.filename
+    }
+
+    /// returns the offset where this record has been found
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// returns a hash of the raw bytes of this record, which is the same for
+    /// every copy of the record (e.g. in `$J` files of volume shadow copies)
+    pub fn record_hash(&self) -> u64 {
+        self.record_hash
+    }
+
+    /// returns the index of the `$J` file this record has been read from, if
+    /// more than one file has been given
+    pub fn journal_index(&self) -> usize {
+        self.journal_index
+    }
+
+    pub fn set_journal_index(&mut self, journal_index: usize) {
+        self.journal_index = journal_index;
+    }
+
+    /// returns `true` if this record has been carved from some data other than `$UsnJrnl:$J`
+    pub fn is_carved(&self) -> bool {
+        self.is_carved
+    }
+
+    pub fn set_carved(&mut self, is_carved: bool) {
+        self.is_carved = is_carved;
+    }
+
+    /// checks if both records describe the same event, regardless of where they have been found
+    pub fn is_same_event(&self, other: &Self) -> bool {
+        self.usn == other.usn
+            && self.timestamp == other.timestamp
+            && self.reason == other.reason
+            && self.file_reference == other.file_reference
+            && self.parent_reference == other.parent_reference
+            && self.filename == other.filename
+            && self.extents == other.extents
+    }
+}
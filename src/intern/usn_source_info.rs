@@ -0,0 +1,52 @@
+use std::fmt;
+
+/// all source info flags, in the order in which they are displayed
+const SOURCE_INFO_FLAGS: [(u32, &str); 4] = [
+    (0x00000002, "USN_SOURCE_AUXILIARY_DATA"),
+    (0x00000008, "USN_SOURCE_CLIENT_REPLICATION_MANAGEMENT"),
+    (0x00000001, "USN_SOURCE_DATA_MANAGEMENT"),
+    (0x00000004, "USN_SOURCE_REPLICATION_MANAGEMENT"),
+];
+
+const USN_SOURCE_PREFIX: &str = "USN_SOURCE_";
+
+///
+/// the `SourceInfo` field of an `$UsnJrnl` record, which is set if a change
+/// has not been caused by a user or an application, but e.g. by a storage
+/// management or replication service. When displayed, the flags are
+/// separated by `+`. The `USN_SOURCE_` prefix is removed, unless the `+` flag
+/// is used in the format string (`{:+}`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UsnSourceInfo(u32);
+
+impl From<u32> for UsnSourceInfo {
+    fn from(value: u32) -> Self {
+        Self(value)
+    }
+}
+
+impl UsnSourceInfo {
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+
+    pub fn has_flag(&self, flag: u32) -> bool {
+        self.0 & flag != 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl fmt::Display for UsnSourceInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let longflags = f.sign_plus();
+        let flags: Vec<&str> = SOURCE_INFO_FLAGS
+            .iter()
+            .filter(|(flag, _)| self.has_flag(*flag))
+            .map(|(_, name)| if longflags { name } else { &name[USN_SOURCE_PREFIX.len()..] })
+            .collect();
+        write!(f, "{}", flags.join("+"))
+    }
+}
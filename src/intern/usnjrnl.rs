@@ -1,14 +1,19 @@
-use std::{collections::hash_map::HashMap};
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+use anyhow::{Result, bail};
 use indicatif::ProgressBar;
-use usnjrnl::*;
 use winstructs::ntfs::mft_reference::MftReference;
+use crate::intern::read_exact::read_exact_or_eof;
+use crate::intern::usn_record::{USN_RECORD_HEADER_SIZE, USN_RECORD_MAX_SIZE};
+use crate::intern::{JournalCoverage, UsnRecord};
 
 pub type KeyType = MftReference;
-pub type ValueType = Vec<CommonUsnRecord>;
+pub type ValueType = Vec<UsnRecord>;
 
 #[derive(Default)]
 pub struct UsnJrnl {
-    entries: HashMap<KeyType, ValueType>
+    entries: HashMap<KeyType, ValueType>,
+    coverage: Vec<JournalCoverage>,
 }
 
 impl UsnJrnl {
@@ -21,35 +26,212 @@ impl UsnJrnl {
         self.entries.is_empty()
     }
 
+    /// returns a summary of every `$J` file whose records have been added
+    pub fn coverage(&self) -> &[JournalCoverage] {
+        &self.coverage
+    }
+
+    pub fn add_coverage(&mut self, coverage: JournalCoverage) {
+        self.coverage.push(coverage);
+    }
+
     #[allow(clippy::should_implement_trait)]
     pub fn into_iter(self) -> std::collections::hash_map::IntoIter<KeyType, ValueType> {
         self.entries.into_iter()
     }
 
-    pub fn from(reader: UsnJrnlReader, bar: ProgressBar) -> Self {
-        let mut entries: HashMap<KeyType, ValueType> = HashMap::new();
-        for entry in reader.into_iter() {
+    pub fn from<I>(records: I, bar: ProgressBar) -> Self where I: IntoIterator<Item=Result<UsnRecord>> {
+        let mut usnjrnl = Self::default();
+        let mut foreign_records = 0;
+        let mut invalid_records = 0;
+        for entry in records.into_iter() {
             match entry {
-                Err(_) => { /* ignore that error for now */ }
+                Err(why) => {
+                    log::debug!("{}", why);
+                    invalid_records += 1;
+                }
+                Ok(e) if !e.has_mft_references() => foreign_records += 1,
                 Ok(e) => {
-                    match &e.data {
-                        UsnRecordData::V2(data) => {
-                            match entries.get_mut(&data.FileReferenceNumber) {
-                                Some(ref mut v) => v.push(e),
-                                None => {
-                                    let _ = entries.insert(data.FileReferenceNumber, vec![e]);
-                                    bar.inc(1);
-                                }
-                            };
-                        }
+                    if usnjrnl.add_record(e) {
+                        bar.inc(1);
                     }
                 }
             }
         }
+        if foreign_records > 0 {
+            log::warn!("ignoring {} USN records whose file ids are no $MFT references", foreign_records);
+        }
+        if invalid_records > 0 {
+            log::warn!("ignoring {} invalid USN records", invalid_records);
+        }
+        usnjrnl.set_range_timestamps();
         bar.finish_at_current_pos();
+        usnjrnl
+    }
+
+    /// `USN_RECORD_V4` records have no timestamp. They are written before the
+    /// next regular record of the same file (which usually has the `CLOSE`
+    /// reason), so they get the timestamp of that record, or of the previous
+    /// one if there is no following record.
+    fn set_range_timestamps(&mut self) {
+        for records in self.entries.values_mut() {
+            if !records.iter().any(|r| r.is_range_record()) {
+                continue;
+            }
+            records.sort_by_key(|r| r.usn());
+            let mut next_filetime = None;
+            for record in records.iter_mut().rev() {
+                if !record.is_range_record() {
+                    next_filetime = Some(record.filetime());
+                } else if let Some(filetime) = next_filetime {
+                    record.set_filetime(filetime);
+                }
+            }
+            let mut previous_filetime = None;
+            for record in records.iter_mut() {
+                if !record.is_range_record() {
+                    previous_filetime = Some(record.filetime());
+                } else if record.filetime() == 0 {
+                    if let Some(filetime) = previous_filetime {
+                        record.set_filetime(filetime);
+                    }
+                }
+            }
+        }
+    }
+
+    /// adds `record` to the records of its file. Returns `true` if this is the first record of that file
+    fn add_record(&mut self, record: UsnRecord) -> bool {
+        match self.entries.get_mut(record.file_reference()) {
+            Some(ref mut v) => {
+                v.push(record);
+                false
+            }
+            None => {
+                let _ = self.entries.insert(*record.file_reference(), vec![record]);
+                true
+            }
+        }
+    }
+
+    /// marks all records as read from the `$J` file with index `journal_index`
+    pub fn set_journal_index(&mut self, journal_index: usize) {
+        for record in self.entries.values_mut().flatten() {
+            record.set_journal_index(journal_index);
+        }
+    }
+
+    /// adds the records of `other` (e.g. a `$J` file of a volume shadow copy),
+    /// except those which are already known. Records are considered to be the
+    /// same if they have the same USN and the same content. Returns the number
+    /// of records which have been ignored.
+    pub fn merge(&mut self, other: UsnJrnl) -> usize {
+        let mut duplicates = 0;
+        self.coverage.extend(other.coverage);
+        for (reference, records) in other.entries.into_iter() {
+            let known_records = self.entries.entry(reference).or_default();
+            let known: HashSet<_> = known_records.iter().map(|r| (r.usn(), r.record_hash())).collect();
+            for record in records.into_iter() {
+                if known.contains(&(record.usn(), record.record_hash())) {
+                    duplicates += 1;
+                } else {
+                    known_records.push(record);
+                }
+            }
+        }
+        duplicates
+    }
+
+    /// adds a record which has been carved from some data other than `$UsnJrnl:$J`.
+    /// If the same event is already known, the record is ignored and `false` is returned.
+    pub fn add_carved_record(&mut self, mut record: UsnRecord) -> bool {
+        if !record.has_mft_references() || self.entries
+            .get(record.file_reference())
+            .is_some_and(|records| records.iter().any(|r| r.is_same_event(&record))) {
+            return false;
+        }
+        record.set_carved(true);
+        let _ = self.add_record(record);
+        true
+    }
+}
+
+///
+/// iterates over the records of an `$UsnJrnl:$J` stream, which can be read
+/// from any [`Read`] object. Unused space between records (which occurs at the
+/// end of every page) is skipped.
+pub struct UsnRecordIterator<R> where R: Read {
+    data: R,
+    offset: u64,
+}
 
+impl<R> UsnRecordIterator<R> where R: Read {
+    pub fn new(data: R) -> Self {
+        Self::with_offset(data, 0)
+    }
+
+    /// creates an iterator for data which start at `offset` of `$UsnJrnl:$J`
+    /// (e.g. after skipping its sparse region), so that the offsets of the
+    /// records are relative to the start of `$J`
+    pub fn with_offset(data: R, offset: u64) -> Self {
         Self {
-            entries
+            data,
+            offset
+        }
+    }
+
+    /// reads exactly `buffer.len()` bytes and advances the offset, or returns
+    /// `Ok(false)` at the end of the stream
+    fn read_record_bytes(&mut self, buffer: &mut [u8]) -> Result<bool> {
+        if !read_exact_or_eof(&mut self.data, buffer)? {
+            return Ok(false);
         }
+        self.offset += buffer.len() as u64;
+        Ok(true)
     }
-}
\ No newline at end of file
+
+    /// skips the rest of the page which contains the record header at `header_offset`
+    fn skip_to_next_page(&mut self, header_offset: u64) -> Result<bool> {
+        let page_size = USN_RECORD_MAX_SIZE as u64;
+        let next_page = (header_offset / page_size + 1) * page_size;
+        let mut buffer = vec![0; next_page.saturating_sub(self.offset) as usize];
+        self.read_record_bytes(&mut buffer)
+    }
+
+    fn next_record(&mut self) -> Result<Option<UsnRecord>> {
+        let mut record = vec![0; USN_RECORD_HEADER_SIZE];
+        loop {
+            let offset = self.offset;
+            if !self.read_record_bytes(&mut record[..USN_RECORD_HEADER_SIZE])? {
+                return Ok(None);
+            }
+
+            let record_length = UsnRecord::record_length(&record);
+            if record_length == 0 {
+                /* there are no more records in this page */
+                if !self.skip_to_next_page(offset)? {
+                    return Ok(None);
+                }
+                continue;
+            }
+
+            if !(USN_RECORD_HEADER_SIZE..=USN_RECORD_MAX_SIZE).contains(&record_length) {
+                let _ = self.skip_to_next_page(offset)?;
+                bail!("invalid length of USN record at offset {:#x}: {}", offset, record_length);
+            }
+
+            record.resize(record_length, 0);
+            if !self.read_record_bytes(&mut record[USN_RECORD_HEADER_SIZE..])? {
+                return Ok(None);
+            }
+            return UsnRecord::from_bytes(&record, offset).map(Some);
+        }
+    }
+}
+
+impl<R> Iterator for UsnRecordIterator<R> where R: Read {
+    type Item = Result<UsnRecord>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record().transpose()
+    }
+}
@@ -0,0 +1,41 @@
+use anyhow::{Result, bail};
+use byteorder::{ByteOrder, LittleEndian};
+
+/// size of the content of `$UsnJrnl:$Max`
+const USNJRNL_MAX_SIZE: usize = 0x20;
+
+/// allocation delta of a change journal which has been created with the default settings
+pub const DEFAULT_ALLOCATION_DELTA: u64 = 0x800000;
+
+///
+/// the configuration of the change journal, as stored in `$UsnJrnl:$Max`. If
+/// `$J` grows larger than the maximum size, its oldest records are purged in
+/// blocks of the allocation delta.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UsnJrnlMax {
+    maximum_size: u64,
+    allocation_delta: u64,
+    journal_id: u64,
+    lowest_valid_usn: i64,
+}
+
+impl UsnJrnlMax {
+    pub fn maximum_size(&self) -> u64 { self.maximum_size }
+    pub fn allocation_delta(&self) -> u64 { self.allocation_delta }
+    pub fn journal_id(&self) -> u64 { self.journal_id }
+
+    /// returns the USN of the oldest record which has not been purged
+    pub fn lowest_valid_usn(&self) -> i64 { self.lowest_valid_usn }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        if data.len() < USNJRNL_MAX_SIZE {
+            bail!("$UsnJrnl:$Max is too short: {} bytes", data.len());
+        }
+        Ok(Self {
+            maximum_size: LittleEndian::read_u64(&data[0x00..]),
+            allocation_delta: LittleEndian::read_u64(&data[0x08..]),
+            journal_id: LittleEndian::read_u64(&data[0x10..]),
+            lowest_valid_usn: LittleEndian::read_i64(&data[0x18..]),
+        })
+    }
+}
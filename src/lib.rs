@@ -1,20 +1,16 @@
 mod intern;
 use std::thread;
-use buf_stream_reader::BufStreamReader;
-use flate2::read::GzDecoder;
-use usnjrnl::*;
 pub use intern::*;
-use mft::MftParser;
-use std::path::PathBuf;
-use anyhow::{Result, anyhow};
+use mft::{MftEntry, MftParser};
+use std::path::{Path, PathBuf};
+use anyhow::{Result, bail};
 use indicatif::{ProgressBar, ProgressStyle, MultiProgress};
-use std::io::{Write, ErrorKind, Read, BufReader};
+use std::io::{BufReader, Write, Read, Seek, SeekFrom};
 use std::fs::File;
-use std::convert::TryInto;
+use chrono::{DateTime, Utc};
 
 enum ProgressBarType {
     Spinner,
-    Bytes,
     Count(u64)
 }
 
@@ -24,20 +20,58 @@ pub enum BodyfileSink {
 }
 
 pub struct Mft2BodyfileTask {
-    mft_file: PathBuf,
-    usnjrnl: Option<PathBuf>,
+    mft_source: Option<InputSource>,
+    image_file: Option<PathBuf>,
+    image_format: Option<ImageFormat>,
+    usnjrnl_sources: Vec<InputSource>,
+    usnjrnl_max: Option<PathBuf>,
+    logfile_source: Option<InputSource>,
+    carve_sources: Vec<InputSource>,
+    usnjrnl_carve_sources: Vec<InputSource>,
+    i30_sources: Vec<InputSource>,
+    i30_from_image: bool,
     usnjrnl_longflags: bool,
+    mft_slack: bool,
+    volume_tag: Option<String>,
     with_progressbar: bool,
+    lifecycle_report: Option<File>,
+    journal_report: Option<File>,
+    burst_report: Option<File>,
+    timestomping_report: Option<File>,
+    detect_timestomping: bool,
+    timestamp_report: Option<File>,
+    timestamp_filter: Vec<TimestampClass>,
+    acquisition_time: Option<DateTime<Utc>>,
+    burst_detector: BurstDetector,
     output: BodyfileSink
 }
 
 impl Default for Mft2BodyfileTask {
     fn default() -> Self {
         Self {
-            mft_file: PathBuf::new(),
-            usnjrnl: None,
+            mft_source: None,
+            image_file: None,
+            image_format: None,
+            usnjrnl_sources: Vec::new(),
+            usnjrnl_max: None,
+            logfile_source: None,
+            carve_sources: Vec::new(),
+            usnjrnl_carve_sources: Vec::new(),
+            i30_sources: Vec::new(),
+            i30_from_image: false,
             usnjrnl_longflags: false,
+            mft_slack: false,
+            volume_tag: None,
             with_progressbar: false,
+            lifecycle_report: None,
+            journal_report: None,
+            burst_report: None,
+            timestomping_report: None,
+            detect_timestomping: false,
+            timestamp_report: None,
+            timestamp_filter: Vec::new(),
+            acquisition_time: None,
+            burst_detector: BurstDetector::default(),
             output: BodyfileSink::Stdout,
         }
     }
@@ -45,12 +79,103 @@ impl Default for Mft2BodyfileTask {
 
 impl Mft2BodyfileTask {
     pub fn with_mft_file(mut self, mft_file: PathBuf) -> Self {
-        self.mft_file = mft_file;
+        self.mft_source = Some(InputSource::File(mft_file));
+        self
+    }
+
+    pub fn with_mft_source(mut self, mft_source: InputSource) -> Self {
+        self.mft_source = Some(mft_source);
+        self
+    }
+
+    /// reads the `$MFT` from `reader` (e.g. stdin), which need not be seekable
+    pub fn with_mft_reader<R>(mut self, reader: R) -> Self where R: Read + Send + 'static {
+        self.mft_source = Some(InputSource::Reader(Box::new(reader)));
+        self
+    }
+
+    /// reads the `$MFT` from all NTFS volumes found in a disk or
+    /// volume image, instead of reading it from `mft_file`. If there is more
+    /// than one volume, the paths are tagged with the partition number (`p1`, `p2`, ...)
+    pub fn with_image_file(mut self, image_file: Option<PathBuf>) -> Self {
+        self.image_file = image_file;
+        self
+    }
+
+    /// sets the format of the image file. If no format is given, it is
+    /// detected automatically
+    pub fn with_image_format(mut self, image_format: Option<ImageFormat>) -> Self {
+        self.image_format = image_format;
         self
     }
 
     pub fn with_usnjrnl(mut self, usnjrnl: Option<PathBuf>) -> Self {
-        self.usnjrnl = usnjrnl;
+        self.usnjrnl_sources = usnjrnl.map(InputSource::File).into_iter().collect();
+        self
+    }
+
+    pub fn with_usnjrnl_source(mut self, usnjrnl_source: Option<InputSource>) -> Self {
+        self.usnjrnl_sources = usnjrnl_source.into_iter().collect();
+        self
+    }
+
+    /// reads several `$UsnJrnl:$J` files (e.g. from the live volume and from
+    /// volume shadow copies), whose records are merged. Records which occur in
+    /// more than one file are used only once.
+    pub fn with_usnjrnl_sources(mut self, usnjrnl_sources: Vec<InputSource>) -> Self {
+        self.usnjrnl_sources = usnjrnl_sources;
+        self
+    }
+
+    /// reads the configuration of the change journal from `$UsnJrnl:$Max`,
+    /// which is needed to find gaps in `$UsnJrnl:$J`
+    pub fn with_usnjrnl_max(mut self, usnjrnl_max: Option<PathBuf>) -> Self {
+        self.usnjrnl_max = usnjrnl_max;
+        self
+    }
+
+    /// reads `$UsnJrnl:$J` from `reader` (e.g. stdin), which need not be seekable
+    pub fn with_usnjrnl_reader<R>(mut self, reader: R) -> Self where R: Read + Send + 'static {
+        self.usnjrnl_sources = vec![InputSource::Reader(Box::new(reader))];
+        self
+    }
+
+    /// reads events from `$LogFile`, which are merged with the `$MFT` entries
+    pub fn with_logfile(mut self, logfile: Option<PathBuf>) -> Self {
+        self.logfile_source = logfile.map(InputSource::File);
+        self
+    }
+
+    pub fn with_logfile_source(mut self, logfile_source: Option<InputSource>) -> Self {
+        self.logfile_source = logfile_source;
+        self
+    }
+
+    /// carves `$MFT` entries from arbitrary data (like unallocated space,
+    /// `pagefile.sys` or memory dumps), which is done in addition to reading the `$MFT`.
+    /// If there is no `$MFT` at all, only the carved entries are exported.
+    pub fn with_carve_sources(mut self, carve_sources: Vec<InputSource>) -> Self {
+        self.carve_sources = carve_sources;
+        self
+    }
+
+    /// carves `$UsnJrnl` records from arbitrary data (like unallocated space or
+    /// volume slack), which are merged with the records read from `$UsnJrnl:$J`
+    pub fn with_usnjrnl_carve_sources(mut self, usnjrnl_carve_sources: Vec<InputSource>) -> Self {
+        self.usnjrnl_carve_sources = usnjrnl_carve_sources;
+        self
+    }
+
+    /// parses exported `$I30` streams (the content of `$INDEX_ALLOCATION:$I30`
+    /// of a directory), including entries found in the slack of INDX records
+    pub fn with_i30_sources(mut self, i30_sources: Vec<InputSource>) -> Self {
+        self.i30_sources = i30_sources;
+        self
+    }
+
+    /// parses the `$I30` index of every directory, if a disk or volume image is used
+    pub fn with_i30_from_image(mut self, i30_from_image: bool) -> Self {
+        self.i30_from_image = i30_from_image;
         self
     }
 
@@ -59,19 +184,102 @@ impl Mft2BodyfileTask {
         self
     }
 
+    /// exports `$STANDARD_INFORMATION` and `$FILE_NAME` attributes which
+    /// are found in the slack of `$MFT` entries
+    pub fn with_mft_slack(mut self, mft_slack: bool) -> Self {
+        self.mft_slack = mft_slack;
+        self
+    }
+
+    /// prepends `volume_tag` to every path in the bodyfile, to distinguish
+    /// files from different volumes
+    pub fn with_volume_tag(mut self, volume_tag: Option<String>) -> Self {
+        self.volume_tag = volume_tag;
+        self
+    }
+
     pub fn with_progressbar(mut self, with_progressbar: bool) -> Self {
         self.with_progressbar = with_progressbar;
         self
     }
 
+    /// writes a CSV report with one row per file, which summarizes when the file
+    /// has been created, renamed, moved, modified and deleted. If the file is not
+    /// empty, the report is appended to it (e.g. for multiple volumes).
+    pub fn with_lifecycle_report(mut self, lifecycle_report: Option<File>) -> Self {
+        self.lifecycle_report = lifecycle_report;
+        self
+    }
+
+    /// writes a CSV report with one row per `$UsnJrnl:$J` file, which shows the
+    /// range of USNs and timestamps it covers, the number of records per reason,
+    /// and gaps in the USNs. If the file is not empty, the report is appended to it.
+    pub fn with_journal_report(mut self, journal_report: Option<File>) -> Self {
+        self.journal_report = journal_report;
+        self
+    }
+
+    /// writes a CSV report with one row per burst of encryption or deletion
+    /// events in `$UsnJrnl` (see [`BurstDetector`]). If the file is not empty,
+    /// the report is appended to it.
+    pub fn with_burst_report(mut self, burst_report: Option<File>) -> Self {
+        self.burst_report = burst_report;
+        self
+    }
+
+    /// sets the window and the number of files which are used to find bursts
+    pub fn with_burst_detector(mut self, burst_detector: BurstDetector) -> Self {
+        self.burst_detector = burst_detector;
+        self
+    }
+
+    /// annotates the `$STANDARD_INFORMATION` lines of the bodyfile with the
+    /// timestomping rules which match the file (see [`TimestompingFinding`])
+    pub fn with_detect_timestomping(mut self, detect_timestomping: bool) -> Self {
+        self.detect_timestomping = detect_timestomping;
+        self
+    }
+
+    /// writes a CSV report with one row per timestomping rule which matches
+    /// a file. If the file is not empty, the report is appended to it.
+    pub fn with_timestomping_report(mut self, timestomping_report: Option<File>) -> Self {
+        self.timestomping_report = timestomping_report;
+        self
+    }
+
+    /// sets the time of the acquisition, which is used to find timestamps in
+    /// the future (see [`TimestampClass`]). The default is the current time.
+    pub fn with_acquisition_time(mut self, acquisition_time: Option<DateTime<Utc>>) -> Self {
+        self.acquisition_time = acquisition_time;
+        self
+    }
+
+    /// exports only files which have at least one timestamp of one of
+    /// `classes`. An empty list exports all files.
+    pub fn with_timestamp_filter(mut self, classes: Vec<TimestampClass>) -> Self {
+        self.timestamp_filter = classes;
+        self
+    }
+
+    /// writes a CSV report with one row per timestamp which is not valid. If
+    /// the file is not empty, the report is appended to it.
+    pub fn with_timestamp_report(mut self, timestamp_report: Option<File>) -> Self {
+        self.timestamp_report = timestamp_report;
+        self
+    }
+
     pub fn with_output(mut self, output: BodyfileSink) -> Self {
         self.output = output;
         self
     }
 
     pub fn fill_preprocessed_mft<T>(mut parser: MftParser<T>, bar: Option<ProgressBar>) -> PreprocessedMft where T: std::io::Read + std::io::Seek{
+        Self::fill_preprocessed_mft_from_entries(parser.iter_entries().filter_map(Result::ok), bar)
+    }
+
+    pub fn fill_preprocessed_mft_from_entries<I>(entries: I, bar: Option<ProgressBar>) -> PreprocessedMft where I: Iterator<Item=MftEntry> {
         let mut pp = PreprocessedMft::default();
-        for mft_entry in parser.iter_entries().filter_map(Result::ok) {
+        for mft_entry in entries {
             if let Some(b) = bar.as_ref() {
                 b.inc(1);
             }
@@ -101,75 +309,367 @@ impl Mft2BodyfileTask {
         pp
     }
 
-    fn read_usnjrnl(usnjrnl_path: &Option<PathBuf>, bar: ProgressBar) -> UsnJrnl {
-        match usnjrnl_path {
-            Some(jrnl_path) => UsnJrnl::from(UsnJrnlReader::from(jrnl_path).unwrap(), bar),
-            None => UsnJrnl::default()
+    /// reads `$UsnJrnl:$J` from (possibly compressed) files or streams, and
+    /// merges their records
+    fn read_usnjrnl(usnjrnl_sources: Vec<InputSource>, usnjrnl_max: Option<UsnJrnlMax>, bar: ProgressBar) -> Result<UsnJrnl> {
+        let mut usnjrnl = UsnJrnl::default();
+        for (index, source) in usnjrnl_sources.into_iter().enumerate() {
+            let name = source.to_string();
+            let (_, reader) = source.open()?;
+            let reader = BufReader::new(reader);
+            let mut coverage = JournalCoverage::new(name.clone(), usnjrnl_max);
+            let mut records = UsnJrnl::from(UsnRecordIterator::new(reader).inspect(|r| coverage.add(r)), bar.clone());
+            Self::check_coverage(&coverage);
+            records.add_coverage(coverage);
+            records.set_journal_index(index);
+            let duplicates = usnjrnl.merge(records);
+            if duplicates > 0 {
+                log::info!("ignoring {} records of {} which are already known", duplicates, name);
+            }
         }
+        Ok(usnjrnl)
     }
 
-    pub fn run(self) -> Result<()> {
+    /// reads `$UsnJrnl:$Max`, if it has been given
+    fn read_usnjrnl_max(&self) -> Result<Option<UsnJrnlMax>> {
+        match self.usnjrnl_max.as_ref() {
+            Some(path) => Ok(Some(UsnJrnlMax::from_bytes(&std::fs::read(path)?)?)),
+            None => Ok(None),
+        }
+    }
 
-        /* not to be mixed with MultiCar ;-) */
-        let multi_bar = MultiProgress::new();
+    fn check_coverage(coverage: &JournalCoverage) {
+        let gaps = coverage.gaps().count();
+        if gaps > 0 {
+            log::warn!("found {} gaps in the USNs of {}, some records have been lost", gaps, coverage.name());
+        }
+    }
+
+    /// reads the `$J` stream of `$Extend/$UsnJrnl`, if it exists. The sparse
+    /// region at the beginning of `$J` is skipped.
+    fn read_usnjrnl_from_volume<R>(volume: &NtfsVolume<R>, pp: &PreprocessedMft, bar: ProgressBar) -> Result<UsnJrnl> where R: Read + Seek + Clone {
+        let reference = match pp.find_by_path("/$Extend/$UsnJrnl") {
+            Some(reference) => reference,
+            None => {
+                log::warn!("this volume has no $UsnJrnl");
+                bar.finish_and_clear();
+                return Ok(UsnJrnl::default());
+            }
+        };
+
+        let usnjrnl_max = match volume.read_stream(&reference, "$Max").and_then(|data| UsnJrnlMax::from_bytes(&data)) {
+            Ok(usnjrnl_max) => Some(usnjrnl_max),
+            Err(why) => {
+                log::warn!("unable to read $UsnJrnl:$Max: {}", why);
+                None
+            }
+        };
+
+        let mut stream = volume.open_stream(&reference, "$J")?;
+        let offset = stream.first_allocated_offset();
+        log::info!("skipping {} bytes of sparse data in $UsnJrnl:$J", offset);
+        stream.seek(SeekFrom::Start(offset))?;
+        let reader = BufReader::new(stream);
+        let mut coverage = JournalCoverage::new("$Extend/$UsnJrnl:$J".to_owned(), usnjrnl_max);
+        let mut usnjrnl = UsnJrnl::from(UsnRecordIterator::with_offset(reader, offset).inspect(|r| coverage.add(r)), bar);
+        Self::check_coverage(&coverage);
+        usnjrnl.add_coverage(coverage);
+        Ok(usnjrnl)
+    }
 
-        let pp_thread = 
-        if cfg!(feature="gzip") {
-            match self.mft_file.file_name() {
-                Some(filename) => {
-                    if filename.to_string_lossy().ends_with(".gz") {
-                        let file = File::open(&self.mft_file)?;
-                        let mut reader = BufReader::new(GzDecoder::new(file));
-                        let mut size = 0;
-                        let mut buffer = vec![0; 4096*4096];
-
-                        /* calculate the size of the uncompressed data */
-                        let bar = self.new_progress_bar("obtaining size of uncompressed data", ProgressBarType::Bytes);
-                        loop {
-                            match reader.read(&mut buffer) {
-                                Ok(bytes) => {
-                                    if bytes == 0 { break }
-                                    else { size += bytes; bar.inc(bytes.try_into().unwrap());}
-                                }
-                                Err(why) => if why.kind() == ErrorKind::UnexpectedEof {
-                                    break;
-                                }
-                            }
-                        }
-                        bar.finish_and_clear();
-
-                        let file = File::open(&self.mft_file)?;
-                        let reader = BufStreamReader::new(GzDecoder::new(file), 4096);
-                        let parser = MftParser::from_read_seek(reader, Some(size.try_into().unwrap()))?;
-                        let parser_bar = multi_bar.add(self.new_progress_bar("parsing $MFT entries", ProgressBarType::Spinner));
-                        thread::spawn(move|| Self::fill_preprocessed_mft(parser, Some(parser_bar)))
-                    } else {
-                        let file = File::open(&self.mft_file)?;
-                        let parser = MftParser::from_read_seek(file, None).unwrap();
-                        let parser_bar = multi_bar.add(self.new_progress_bar("parsing $MFT entries", ProgressBarType::Count(parser.get_entry_count())));
-                        thread::spawn(move|| Self::fill_preprocessed_mft(parser, Some(parser_bar)))
+    /// reads `$LogFile` from a (possibly compressed) file or stream. `cluster_size` and
+    /// `mft_record_size` are needed to find the `$MFT` entries which are modified by log records.
+    fn read_logfile(logfile_source: Option<InputSource>, cluster_size: u64, mft_record_size: u64, bar: ProgressBar) -> Result<Vec<LogFileEvent>> {
+        match logfile_source {
+            Some(source) => {
+                let (_, reader) = source.open()?;
+                Ok(Self::decode_logfile(BufReader::new(reader), cluster_size, mft_record_size, bar))
+            }
+            None => {
+                bar.finish_and_clear();
+                Ok(Vec::new())
+            }
+        }
+    }
+
+    /// reads `$LogFile` from the volume, if it exists
+    fn read_logfile_from_volume<R>(volume: &NtfsVolume<R>, pp: &PreprocessedMft, bar: ProgressBar) -> Result<Vec<LogFileEvent>> where R: Read + Seek + Clone {
+        let stream = match pp.find_by_path("/$LogFile") {
+            Some(reference) => volume.open_stream(&reference, "")?,
+            None => {
+                log::warn!("this volume has no $LogFile");
+                bar.finish_and_clear();
+                return Ok(Vec::new());
+            }
+        };
+        let boot_sector = volume.boot_sector();
+        Ok(Self::decode_logfile(BufReader::new(stream), boot_sector.cluster_size(), boot_sector.mft_record_size(), bar))
+    }
+
+    fn decode_logfile<R>(reader: R, cluster_size: u64, mft_record_size: u64, bar: ProgressBar) -> Vec<LogFileEvent> where R: Read {
+        let mut events = Vec::new();
+        for record in LogRecordIterator::new(reader) {
+            match record {
+                Err(why) => log::warn!("{}", why),
+                Ok(record) => {
+                    if let Some(event) = LogFileEvent::from_record(&record, cluster_size, mft_record_size) {
+                        events.push(event);
+                        bar.inc(1);
                     }
                 }
-                None => {
-                    return Err(anyhow!(std::io::Error::new(ErrorKind::InvalidInput, "missing filename")))
+            }
+        }
+        bar.finish_at_current_pos();
+        events
+    }
+
+    /// merges the `$LogFile` events into `pp`
+    fn add_logfile_events(pp: &mut PreprocessedMft, events: Vec<LogFileEvent>) {
+        let unassigned = pp.add_logfile_events(events);
+        if unassigned > 0 {
+            log::warn!("ignoring {} $LogFile events which refer to unknown $MFT entries", unassigned);
+        }
+    }
+
+    pub fn run(mut self) -> Result<()> {
+        let stdout = std::io::stdout();
+        let mut output: Box<dyn Write> = match std::mem::replace(&mut self.output, BodyfileSink::Stdout) {
+            BodyfileSink::Stdout     => Box::new(stdout.lock()),
+            BodyfileSink::File(file) => Box::new(file)
+        };
+
+        match self.image_file.clone() {
+            Some(image_file) => self.run_on_image(&image_file, &mut output),
+            None if self.mft_source.is_none() && !(self.carve_sources.is_empty() && self.usnjrnl_carve_sources.is_empty() && self.i30_sources.is_empty()) => {
+                let usnjrnl_bar = self.new_progress_bar("parsing $UsnJrnl:$J entries", ProgressBarType::Spinner);
+                let usnjrnl = Self::read_usnjrnl(std::mem::take(&mut self.usnjrnl_sources), self.read_usnjrnl_max()?, usnjrnl_bar)?;
+                let logfile_bar = self.new_progress_bar("parsing $LogFile records", ProgressBarType::Spinner);
+                let mut pp = PreprocessedMft::default();
+                Self::add_logfile_events(&mut pp, Self::read_logfile(self.logfile_source.take(), DEFAULT_CLUSTER_SIZE, DEFAULT_MFT_RECORD_SIZE, logfile_bar)?);
+                let volume_tag = self.volume_tag.clone();
+                self.export(pp, usnjrnl, volume_tag, &mut output)
+            }
+            None => self.run_on_mft_file(&mut output)
+        }
+    }
+
+    fn run_on_image(&mut self, image_file: &Path, output: &mut dyn Write) -> Result<()> {
+        let image_format = match self.image_format {
+            Some(image_format) => image_format,
+            None => ImageFormat::from_path(image_file)?,
+        };
+        let reader = SharedReader::new(image_format.open(image_file)?);
+        let partition_table = PartitionTable::from_reader(&mut reader.clone())?;
+
+        let mut volumes = Vec::new();
+        for partition in partition_table.partitions() {
+            let partition_reader = PartitionReader::new(reader.clone(), partition.start(), partition.size());
+            match NtfsVolume::new(partition_reader) {
+                Ok(volume) => {
+                    log::info!("found NTFS volume in partition {} at offset {:#x}", partition.index(), partition.start());
+                    volumes.push((partition.index(), volume));
+                }
+                Err(why) => log::info!("ignoring partition {} ({}): {}", partition.index(), partition.description(), why),
+            }
+        }
+
+        if volumes.is_empty() {
+            bail!("no NTFS volume found in {}", image_file.display());
+        }
+        /* an external journal belongs to only one of the volumes, and we cannot tell which */
+        if volumes.len() > 1 && !(self.usnjrnl_sources.is_empty() && self.logfile_source.is_none()) {
+            bail!("found {} NTFS volumes in {}, so an external $UsnJrnl or $LogFile cannot be assigned to one of them", volumes.len(), image_file.display());
+        }
+
+        /* the files of different volumes are distinguished by the partition number, e.g. `p2:/Windows` */
+        let volumes_count = volumes.len();
+        for (index, volume) in volumes {
+            let volume_tag = match &self.volume_tag {
+                Some(tag) if volumes_count > 1 => Some(format!("{}:p{}", tag, index)),
+                None if volumes_count > 1 => Some(format!("p{}", index)),
+                tag => tag.clone(),
+            };
+            let mft = volume.mft_stream()?;
+            let mft_size = mft.size();
+            let parser = MftParser::from_read_seek(mft, Some(mft_size))?;
+            let parser_bar = self.new_progress_bar("parsing $MFT entries", ProgressBarType::Count(parser.get_entry_count()));
+            let mut pp = Self::fill_preprocessed_mft(parser, Some(parser_bar));
+            if self.i30_from_image {
+                self.read_indexes_from_volume(&volume, &mut pp)?;
+            }
+
+            let usnjrnl_bar = self.new_progress_bar("parsing $UsnJrnl:$J entries", ProgressBarType::Spinner);
+            let usnjrnl = if self.usnjrnl_sources.is_empty() {
+                Self::read_usnjrnl_from_volume(&volume, &pp, usnjrnl_bar)?
+            } else {
+                Self::read_usnjrnl(std::mem::take(&mut self.usnjrnl_sources), self.read_usnjrnl_max()?, usnjrnl_bar)?
+            };
+
+            let logfile_bar = self.new_progress_bar("parsing $LogFile records", ProgressBarType::Spinner);
+            let events = match self.logfile_source.take() {
+                Some(source) => {
+                    let boot_sector = volume.boot_sector();
+                    Self::read_logfile(Some(source), boot_sector.cluster_size(), boot_sector.mft_record_size(), logfile_bar)?
                 }
+                None => match Self::read_logfile_from_volume(&volume, &pp, logfile_bar) {
+                    Ok(events) => events,
+                    Err(why) => {
+                        log::warn!("unable to read $LogFile: {}", why);
+                        Vec::new()
+                    }
+                }
+            };
+            Self::add_logfile_events(&mut pp, events);
+            self.export(pp, usnjrnl, volume_tag, output)?;
+        }
+        Ok(())
+    }
+
+    fn run_on_mft_file(&mut self, output: &mut dyn Write) -> Result<()> {
+        /* not to be mixed with MultiCar ;-) */
+        let multi_bar = MultiProgress::new();
+
+        let pp_thread = match self.mft_source.take() {
+            None => bail!("missing $MFT file"),
+            Some(InputSource::File(path)) if Compression::from_path(&path)? == Compression::None => {
+                let file = File::open(&path)?;
+                let parser = MftParser::from_read_seek(file, None)?;
+                let parser_bar = multi_bar.add(self.new_progress_bar("parsing $MFT entries", ProgressBarType::Count(parser.get_entry_count())));
+                thread::spawn(move|| Self::fill_preprocessed_mft(parser, Some(parser_bar)))
+            }
+            Some(source) => {
+                /* compressed data and streams are not seekable, so we read the entries as a stream */
+                let (_, reader) = source.open()?;
+                let stream = MftRecordStream::new(reader)?;
+                let parser_bar = multi_bar.add(self.new_progress_bar("parsing $MFT entries", ProgressBarType::Spinner));
+                thread::spawn(move|| Self::fill_preprocessed_mft_from_entries(stream.filter_map(Result::ok), Some(parser_bar)))
             }
-        } else {
-            let parser = MftParser::from_path(&self.mft_file).unwrap();
-            let parser_bar = multi_bar.add(self.new_progress_bar("parsing $MFT entries", ProgressBarType::Count(parser.get_entry_count())));
-            thread::spawn(move|| Self::fill_preprocessed_mft(parser, Some(parser_bar)))
         };
 
         let usnjrnl_bar = multi_bar.add(self.new_progress_bar("parsing $UsnJrnl:$J entries", ProgressBarType::Spinner));
-        let usnjrnl_path = self.usnjrnl.clone();
+        let usnjrnl_sources = std::mem::take(&mut self.usnjrnl_sources);
+        let usnjrnl_max = self.read_usnjrnl_max()?;
         let usnjrnl_thread = thread::spawn(move||
-            Self::read_usnjrnl(&usnjrnl_path, usnjrnl_bar)
+            Self::read_usnjrnl(usnjrnl_sources, usnjrnl_max, usnjrnl_bar)
         );
  
         let _ = multi_bar.join();
         let mut pp = pp_thread.join().unwrap();
-        let usnjrnl = usnjrnl_thread.join().unwrap();
+        let usnjrnl = usnjrnl_thread.join().unwrap()?;
+
+        let logfile_bar = self.new_progress_bar("parsing $LogFile records", ProgressBarType::Spinner);
+        Self::add_logfile_events(&mut pp, Self::read_logfile(self.logfile_source.take(), DEFAULT_CLUSTER_SIZE, DEFAULT_MFT_RECORD_SIZE, logfile_bar)?);
+
+        let volume_tag = self.volume_tag.clone();
+        self.export(pp, usnjrnl, volume_tag, output)
+    }
+
+    /// adds all `$MFT` entries which can be carved from the carve sources.
+    /// The sources are consumed, so that they are carved only once.
+    fn carve_mft_entries(&mut self, pp: &mut PreprocessedMft) -> Result<()> {
+        for source in std::mem::take(&mut self.carve_sources) {
+            let bar = self.new_progress_bar("carving $MFT entries", ProgressBarType::Spinner);
+            log::info!("carving $MFT entries from {}", source);
+            let (_, reader) = source.open()?;
+            let mut carved_entries = 0;
+            for (offset, entry) in MftCarver::new(reader) {
+                if pp.add_carved_entry(entry, offset) {
+                    carved_entries += 1;
+                    bar.inc(1);
+                }
+            }
+            bar.finish_and_clear();
+            log::info!("carved {} $MFT entries", carved_entries);
+        }
+        Ok(())
+    }
+
+    /// adds all `$UsnJrnl` records which can be carved from the usnjrnl carve sources.
+    /// The sources are consumed, so that they are carved only once.
+    fn carve_usnjrnl_records(&mut self, usnjrnl: &mut UsnJrnl) -> Result<()> {
+        for source in std::mem::take(&mut self.usnjrnl_carve_sources) {
+            let bar = self.new_progress_bar("carving $UsnJrnl records", ProgressBarType::Spinner);
+            log::info!("carving $UsnJrnl records from {}", source);
+            let (_, reader) = source.open()?;
+            let mut carved_records = 0;
+            for record in UsnCarver::new(reader) {
+                if usnjrnl.add_carved_record(record) {
+                    carved_records += 1;
+                    bar.inc(1);
+                }
+            }
+            bar.finish_and_clear();
+            log::info!("carved {} $UsnJrnl records", carved_records);
+        }
+        Ok(())
+    }
+
+    /// adds the entries of all `$I30` streams of the i30 sources.
+    /// The sources are consumed, so that they are read only once.
+    fn read_i30_sources(&mut self, pp: &mut PreprocessedMft) -> Result<()> {
+        for source in std::mem::take(&mut self.i30_sources) {
+            let bar = self.new_progress_bar("parsing $I30 entries", ProgressBarType::Spinner);
+            log::info!("parsing $I30 entries from {}", source);
+            let (_, reader) = source.open()?;
+            let mut index_entries = 0;
+            for entry in IndexRecordIterator::new(reader, DEFAULT_INDEX_RECORD_SIZE) {
+                if pp.add_index_entry(entry) {
+                    index_entries += 1;
+                    bar.inc(1);
+                }
+            }
+            bar.finish_and_clear();
+            log::info!("found {} $I30 entries", index_entries);
+        }
+        Ok(())
+    }
 
+    /// adds the entries of the `$INDEX_ALLOCATION:$I30` attributes of all allocated directories
+    fn read_indexes_from_volume<R>(&self, volume: &NtfsVolume<R>, pp: &mut PreprocessedMft) -> Result<()> where R: Read + Seek + Clone {
+        let mft = volume.mft_stream()?;
+        let mft_size = mft.size();
+        let mut parser = MftParser::from_read_seek(mft, Some(mft_size))?;
+        let mut mft = volume.mft_stream()?;
+        let record_size = volume.boot_sector().index_record_size() as usize;
+
+        let bar = self.new_progress_bar("parsing $I30 entries", ProgressBarType::Count(parser.get_entry_count()));
+        let mut index_entries = 0;
+        for entry in parser.iter_entries().filter_map(Result::ok) {
+            bar.inc(1);
+            if !(entry.is_allocated() && entry.is_dir() && PreprocessedMft::is_base_entry(&entry)) {
+                continue;
+            }
+            /* small directories have no $INDEX_ALLOCATION at all */
+            let stream = match volume.attribute_stream(&entry, INDEX_ALLOCATION, "$I30", &mut mft) {
+                Ok(stream) => stream,
+                Err(_) => continue,
+            };
+            for index_entry in IndexRecordIterator::new(BufReader::new(stream), record_size) {
+                if pp.add_index_entry(index_entry) {
+                    index_entries += 1;
+                }
+            }
+        }
+        bar.finish_and_clear();
+        log::info!("found {} $I30 entries", index_entries);
+        Ok(())
+    }
+
+    fn export(&mut self, mut pp: PreprocessedMft, mut usnjrnl: UsnJrnl, volume_tag: Option<String>, output: &mut dyn Write) -> Result<()> {
+        pp.set_volume_tag(volume_tag);
+        pp.set_show_slack(self.mft_slack);
+        pp.set_detect_timestomping(self.detect_timestomping);
+        pp.set_acquisition_time(Some(Filetime::from(&self.acquisition_time.unwrap_or_else(Utc::now))));
+        pp.set_timestamp_filter(self.timestamp_filter.clone());
+        if pp.volume_created().is_none() {
+            log::warn!("unable to find the creation time of $MFT or $Volume, timestamps are not compared with the creation of the volume");
+        }
+        self.carve_mft_entries(&mut pp)?;
+        self.carve_usnjrnl_records(&mut usnjrnl)?;
+        self.read_i30_sources(&mut pp)?;
+        if let Some(report) = self.journal_report.as_mut() {
+            write_report(report, JournalCoverage::CSV_HEADER, usnjrnl.coverage().iter().map(JournalCoverage::to_csv_line))?;
+        }
         if ! usnjrnl.is_empty() {
             let bar = self.new_progress_bar("merging $UsnJrnl entries", ProgressBarType::Count(usnjrnl.len() as u64));
             for (reference, records) in usnjrnl.into_iter() {
@@ -180,34 +680,42 @@ impl Mft2BodyfileTask {
         }
 
         let bar = &self.new_progress_bar("exporting bodyfile lines", ProgressBarType::Count(pp.bodyfile_lines_count() as u64));
-        let stdout = std::io::stdout();
-        let mut stdout_lock: Box<dyn Write> = match self.output {
-            BodyfileSink::Stdout     => Box::new(stdout.lock()),
-            BodyfileSink::File(file) => Box::new(file)
-        };
         for entry in pp.iter_entries(self.usnjrnl_longflags) {
-            stdout_lock.write_all(entry.as_bytes())?;
-            stdout_lock.write_all("\n".as_bytes())?;
+            output.write_all(entry.as_bytes())?;
+            output.write_all("\n".as_bytes())?;
             bar.inc(1);
         }
-        stdout_lock.flush()?;
+        output.flush()?;
         bar.finish();
+
+        if let Some(report) = self.lifecycle_report.as_mut() {
+            write_report(report, FileLifecycle::CSV_HEADER, pp.iter_lifecycles().map(|l| l.to_csv_line()))?;
+        }
+        if let Some(report) = self.timestomping_report.as_mut() {
+            write_report(report, TimestompingFinding::CSV_HEADER, pp.iter_timestomping_findings().map(|f| f.to_csv_line()))?;
+        }
+        if let Some(report) = self.timestamp_report.as_mut() {
+            write_report(report, TimestampClassification::CSV_HEADER, pp.iter_invalid_timestamps().map(|c| c.to_csv_line()))?;
+        }
+        if let Some(report) = self.burst_report.as_mut() {
+            let bursts = pp.detect_bursts(&self.burst_detector);
+            if !bursts.is_empty() {
+                log::warn!("found {} bursts of encryption or deletion events in $UsnJrnl", bursts.len());
+            }
+            write_report(report, Burst::CSV_HEADER, bursts.iter().map(Burst::to_csv_line))?;
+        }
         Ok(())
     }
 
     fn new_progress_bar(&self, message: &'static str, bar_type: ProgressBarType) -> ProgressBar {
         let bar = match bar_type {
             ProgressBarType::Count(count) => ProgressBar::new(count).with_message(message),
-            ProgressBarType::Bytes => ProgressBar::new_spinner().with_message(message),
             ProgressBarType::Spinner => ProgressBar::new_spinner().with_message(message)
         };
         let style = match bar_type {
             ProgressBarType::Count(_count) => ProgressStyle::default_bar()
                 .template("[{elapsed_precise}] {bar:40.cyan/blue} {pos:>9}/{len:9}({percent}%) {msg}")
                 .progress_chars("##-"),
-            ProgressBarType::Bytes => ProgressStyle::default_spinner()
-                .template("[{elapsed_precise}] {spinner:40} {bytes:>9} {msg}")
-                .tick_chars("|/-\\"),
             ProgressBarType::Spinner => ProgressStyle::default_spinner()
                 .template("[{elapsed_precise}] {spinner:40} {pos:>9} {msg}")
                 .tick_chars("|/-\\"),
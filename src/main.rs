@@ -1,41 +1,73 @@
 mod intern;
 
 pub use intern::*;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use clap::{App, Arg};
 use anyhow::Result;
 use simplelog::{TermLogger, LevelFilter, Config, TerminalMode, ColorChoice};
 use libmft2bodyfile::{Mft2BodyfileTask, BodyfileSink};
 use std::fs::File;
+use std::io::Read;
+use chrono::{DateTime, NaiveDateTime, Utc};
 
 struct Mft2BodyfileApplication {
-    mft_file: PathBuf,
-    usnjrnl: Option<PathBuf>,
+    mft_source: Option<libmft2bodyfile::InputSource>,
+    image_file: Option<PathBuf>,
+    image_format: Option<libmft2bodyfile::ImageFormat>,
+    triage_collection: Option<PathBuf>,
+    usnjrnl: Vec<libmft2bodyfile::InputSource>,
+    usnjrnl_max: Option<PathBuf>,
+    logfile: Option<libmft2bodyfile::InputSource>,
+    carve_sources: Vec<libmft2bodyfile::InputSource>,
+    usnjrnl_carve_sources: Vec<libmft2bodyfile::InputSource>,
+    i30_sources: Vec<libmft2bodyfile::InputSource>,
+    i30_from_image: bool,
     output: BodyfileSink,
+    lifecycle_report: Option<File>,
+    journal_report: Option<File>,
+    burst_report: Option<File>,
+    timestomping_report: Option<File>,
+    detect_timestomping: bool,
+    timestamp_report: Option<File>,
+    timestamp_filter: Vec<libmft2bodyfile::TimestampClass>,
+    acquisition_time: Option<DateTime<Utc>>,
+    burst_detector: libmft2bodyfile::BurstDetector,
     usnjrnl_longflags: bool,
+    mft_slack: bool,
 }
 
 impl Mft2BodyfileApplication {
     pub fn new() -> Self {
         Self {
-            mft_file: PathBuf::new(),
-            usnjrnl: None,
+            mft_source: None,
+            image_file: None,
+            image_format: None,
+            triage_collection: None,
+            usnjrnl: Vec::new(),
+            usnjrnl_max: None,
+            logfile: None,
+            carve_sources: Vec::new(),
+            usnjrnl_carve_sources: Vec::new(),
+            i30_sources: Vec::new(),
+            i30_from_image: false,
             output: BodyfileSink::Stdout,
-            usnjrnl_longflags: false
+            lifecycle_report: None,
+            journal_report: None,
+            burst_report: None,
+            timestomping_report: None,
+            detect_timestomping: false,
+            timestamp_report: None,
+            timestamp_filter: Vec::new(),
+            acquisition_time: None,
+            burst_detector: libmft2bodyfile::BurstDetector::default(),
+            usnjrnl_longflags: false,
+            mft_slack: false,
         }
     }
 
     fn parse_options(&mut self) -> Result<()> {
-        let usnjrnl_help;
-        let mft2bodyfile_help;
-
-        if cfg!(feature = "gzip") {
-            usnjrnl_help = "path to $UsnJrnl:$J file (optional; file ending with .gz will be treated as being gzipped)";
-            mft2bodyfile_help = "path to $MFT (file ending with .gz will be treated as being gzipped)";
-        } else {
-            usnjrnl_help = "path to $UsnJrnl:$J file (optional)";
-            mft2bodyfile_help = "path to $MFT";
-        }
+        let usnjrnl_help = "path to $UsnJrnl:$J file (optional; can be given more than once, e.g. for copies from volume shadow copies, whose records are merged; use '-' to read from stdin; compressed files are detected automatically)";
+        let mft2bodyfile_help = "path to $MFT, to a disk or volume image (raw, split raw, E01, VHD or VHDX) or to a triage collection (directory or zip file); use '-' to read $MFT from stdin; compressed $MFT files are detected automatically";
 
         let app = App::new(env!("CARGO_PKG_NAME"))
             .version(env!("CARGO_PKG_VERSION"))
@@ -44,7 +76,7 @@ impl Mft2BodyfileApplication {
             .arg(
                 Arg::with_name("MFT_FILE")
                     .help(mft2bodyfile_help)
-                    .required(true)
+                    .required_unless_one(&["carve", "carve-journal", "i30"])
                     .multiple(false)
                     .takes_value(true),
             )
@@ -53,12 +85,131 @@ impl Mft2BodyfileApplication {
                     .short("J").long("journal")
                     .help(usnjrnl_help)
                     .takes_value(true)
+                    .multiple(true)
+                    .number_of_values(1)
+            )
+            .arg(
+                Arg::with_name("journal-max")
+                    .long("journal-max")
+                    .help("path to $UsnJrnl:$Max file (optional; contains the maximum size and the allocation delta of the journal, which are used to find gaps in $UsnJrnl:$J)")
+                    .takes_value(true)
+                    .number_of_values(1)
+            )
+            .arg(
+                Arg::with_name("logfile")
+                    .short("L").long("logfile")
+                    .help("path to $LogFile file (optional; compressed files are detected automatically)")
+                    .takes_value(true)
+                    .number_of_values(1)
+            )
+            .arg(
+                Arg::with_name("carve")
+                    .long("carve")
+                    .help("carve $MFT entries from any file, like unallocated space, pagefile.sys or memory dumps (can be used multiple times; carved entries are marked as such)")
+                    .takes_value(true)
+                    .multiple(true)
                     .number_of_values(1)
             )
+            .arg(
+                Arg::with_name("carve-journal")
+                    .long("carve-journal")
+                    .help("carve $UsnJrnl records from any file, like unallocated space or volume slack (can be used multiple times; carved records are marked as such)")
+                    .takes_value(true)
+                    .multiple(true)
+                    .number_of_values(1)
+            )
+            .arg(
+                Arg::with_name("i30")
+                    .long("i30")
+                    .help("parse an exported $I30 index ($INDEX_ALLOCATION:$I30 of a directory), including deleted entries found in its slack (can be used multiple times)")
+                    .takes_value(true)
+                    .multiple(true)
+                    .number_of_values(1)
+            )
+            .arg(
+                Arg::with_name("indexes")
+                    .long("indexes")
+                    .help("parse the $I30 index of every directory, including deleted entries found in its slack (only for disk or volume images)")
+            )
             .arg(
                 Arg::with_name("journal-long-flags")
                     .long("journal-long-flags")
-                    .help("don't remove the USN_REASON_ prefix from the $UsnJrnl reason output")
+                    .help("don't remove the USN_REASON_, USN_SOURCE_ and FILE_ATTRIBUTE_ prefixes from the $UsnJrnl output")
+            )
+            .arg(
+                Arg::with_name("slack")
+                    .long("slack")
+                    .help("export $STANDARD_INFORMATION and $FILE_NAME attributes found in the slack of $MFT entries")
+            )
+            .arg(
+                Arg::with_name("lifecycle")
+                    .long("lifecycle")
+                    .help("write a CSV report which summarizes the lifecycle of every file")
+                    .takes_value(true)
+                    .number_of_values(1)
+            )
+            .arg(
+                Arg::with_name("journal-report")
+                    .long("journal-report")
+                    .help("write a CSV report which summarizes the USNs and timestamps covered by every $UsnJrnl:$J file, including gaps")
+                    .takes_value(true)
+                    .number_of_values(1)
+            )
+            .arg(
+                Arg::with_name("burst-report")
+                    .long("burst-report")
+                    .help("write a CSV report of bursts of $UsnJrnl events which are typical for ransomware, like many files which get the same new extension or which are deleted")
+                    .takes_value(true)
+                    .number_of_values(1)
+            )
+            .arg(
+                Arg::with_name("burst-window")
+                    .long("burst-window")
+                    .help("length of the sliding window which is used to find bursts, in seconds (default: 60)")
+                    .takes_value(true)
+                    .number_of_values(1)
+            )
+            .arg(
+                Arg::with_name("burst-files")
+                    .long("burst-files")
+                    .help("number of different files which must be changed within the window to form a burst (default: 20)")
+                    .takes_value(true)
+                    .number_of_values(1)
+            )
+            .arg(
+                Arg::with_name("timestomping")
+                    .long("timestomping")
+                    .help("annotate $STANDARD_INFORMATION lines with the timestomping rules which match the file")
+            )
+            .arg(
+                Arg::with_name("timestomping-report")
+                    .long("timestomping-report")
+                    .help("write a CSV report of all files whose timestamps have probably been manipulated, with the rules which match")
+                    .takes_value(true)
+                    .number_of_values(1)
+            )
+            .arg(
+                Arg::with_name("acquisition-time")
+                    .long("acquisition-time")
+                    .help("time of the acquisition in UTC, like '2021-08-30 20:48:42'; later timestamps are flagged as after_acquisition (default: now)")
+                    .takes_value(true)
+                    .number_of_values(1)
+            )
+            .arg(
+                Arg::with_name("timestamp-report")
+                    .long("timestamp-report")
+                    .help("write a CSV report of all timestamps which are zero, before 1601, after the acquisition or outside the lifetime of the volume")
+                    .takes_value(true)
+                    .number_of_values(1)
+            )
+            .arg(
+                Arg::with_name("timestamp-filter")
+                    .long("timestamp-filter")
+                    .help("export only files with at least one timestamp of the given classes")
+                    .takes_value(true)
+                    .multiple(true)
+                    .use_delimiter(true)
+                    .possible_values(&libmft2bodyfile::TimestampClass::NAMES)
             )
             .arg(
                 Arg::with_name("output")
@@ -69,24 +220,117 @@ impl Mft2BodyfileApplication {
             );
         let matches = app.get_matches();
         self.usnjrnl_longflags = matches.is_present("journal-long-flags");
-        let filename = matches.value_of("MFT_FILE").expect("missing $MFT filename");
+        self.mft_slack = matches.is_present("slack");
+        self.i30_from_image = matches.is_present("indexes");
+        let filename = match matches.value_of("MFT_FILE") {
+            Some(filename) => filename,
+            None => return self.parse_common_options(&matches),
+        };
+
+        if filename == "-" {
+            self.mft_source = Some(libmft2bodyfile::InputSource::Reader(Box::new(std::io::stdin())));
+            return self.parse_common_options(&matches);
+        }
 
         let fp = PathBuf::from(&filename);
-        if ! (fp.exists() && fp.is_file()) {
+        if ! fp.exists() {
             return Err(anyhow::Error::msg(format!("File {} does not exist", &filename)));
+        }
+
+        if ! (fp.is_file() || fp.is_dir()) {
+            /* named pipes and devices cannot be read twice, so we cannot check their contents */
+            self.mft_source = Some(libmft2bodyfile::InputSource::Reader(Box::new(File::open(&fp)?)));
+            return self.parse_common_options(&matches);
+        }
+
+        if libmft2bodyfile::TriageCollection::is_triage_collection(&fp) {
+            self.triage_collection = Some(fp);
+            return self.parse_common_options(&matches);
+        }
+
+        let image_format = libmft2bodyfile::ImageFormat::from_path(&fp)?;
+        if image_format == libmft2bodyfile::ImageFormat::Raw && Self::is_mft_file(&fp)? {
+            self.mft_source = Some(libmft2bodyfile::InputSource::File(fp));
         } else {
-            self.mft_file = fp;
+            self.image_file = Some(fp);
+            self.image_format = Some(image_format);
         }
+        self.parse_common_options(&matches)
+    }
 
-        if let Some(usnjrnl_filename) = matches.value_of("journal") {
+    fn parse_common_options(&mut self, matches: &clap::ArgMatches) -> Result<()> {
+        for usnjrnl_filename in matches.values_of("journal").into_iter().flatten() {
             let fp = PathBuf::from(&usnjrnl_filename);
-            if ! (fp.exists() && fp.is_file()) {
+            if usnjrnl_filename == "-" {
+                if matches!(self.mft_source, Some(libmft2bodyfile::InputSource::Reader(_))) {
+                    return Err(anyhow::Error::msg("$MFT and $UsnJrnl:$J cannot both be read from a stream"));
+                }
+                if self.usnjrnl.iter().any(|s| matches!(s, libmft2bodyfile::InputSource::Reader(_))) {
+                    return Err(anyhow::Error::msg("only one $UsnJrnl:$J file can be read from a stream"));
+                }
+                self.usnjrnl.push(libmft2bodyfile::InputSource::Reader(Box::new(std::io::stdin())));
+            } else if ! fp.exists() || fp.is_dir() {
                 return Err(anyhow::Error::msg(format!("File {} does not exist", &usnjrnl_filename)));
             } else {
-                self.usnjrnl = Some(fp);
+                self.usnjrnl.push(libmft2bodyfile::InputSource::File(fp));
             }
         }
 
+        if let Some(usnjrnl_max_filename) = matches.value_of("journal-max") {
+            let fp = PathBuf::from(&usnjrnl_max_filename);
+            if ! fp.exists() || fp.is_dir() {
+                return Err(anyhow::Error::msg(format!("File {} does not exist", &usnjrnl_max_filename)));
+            }
+            self.usnjrnl_max = Some(fp);
+        }
+
+        if let Some(logfile_filename) = matches.value_of("logfile") {
+            let fp = PathBuf::from(&logfile_filename);
+            if ! fp.exists() || fp.is_dir() {
+                return Err(anyhow::Error::msg(format!("File {} does not exist", &logfile_filename)));
+            }
+            self.logfile = Some(libmft2bodyfile::InputSource::File(fp));
+        }
+
+        self.carve_sources = Self::carve_sources(matches, "carve")?;
+        self.usnjrnl_carve_sources = Self::carve_sources(matches, "carve-journal")?;
+        self.i30_sources = Self::carve_sources(matches, "i30")?;
+
+        if let Some(lifecycle_report) = matches.value_of("lifecycle") {
+            self.lifecycle_report = Some(File::create(lifecycle_report)?);
+        }
+
+        if let Some(journal_report) = matches.value_of("journal-report") {
+            self.journal_report = Some(File::create(journal_report)?);
+        }
+
+        self.detect_timestomping = matches.is_present("timestomping");
+        if let Some(timestomping_report) = matches.value_of("timestomping-report") {
+            self.timestomping_report = Some(File::create(timestomping_report)?);
+        }
+
+        if let Some(acquisition_time) = matches.value_of("acquisition-time") {
+            self.acquisition_time = Some(Self::parse_acquisition_time(acquisition_time)?);
+        }
+        if let Some(timestamp_report) = matches.value_of("timestamp-report") {
+            self.timestamp_report = Some(File::create(timestamp_report)?);
+        }
+        if let Some(classes) = matches.values_of("timestamp-filter") {
+            self.timestamp_filter = classes.map(str::parse).collect::<Result<_>>()?;
+        }
+
+        if let Some(burst_report) = matches.value_of("burst-report") {
+            self.burst_report = Some(File::create(burst_report)?);
+        }
+        if let Some(window) = matches.value_of("burst-window") {
+            let window = window.parse().map_err(|_| anyhow::Error::msg(format!("invalid window: {}", window)))?;
+            self.burst_detector = self.burst_detector.with_window(window);
+        }
+        if let Some(min_files) = matches.value_of("burst-files") {
+            let min_files = min_files.parse().map_err(|_| anyhow::Error::msg(format!("invalid number of files: {}", min_files)))?;
+            self.burst_detector = self.burst_detector.with_min_files(min_files);
+        }
+
         if let Some(output) = matches.value_of("output") {
             if output != "-" {
                 self.output = BodyfileSink::File(File::create(output)?);
@@ -96,16 +340,122 @@ impl Mft2BodyfileApplication {
         Ok(())
     }
 
+    fn carve_sources(matches: &clap::ArgMatches, option: &str) -> Result<Vec<libmft2bodyfile::InputSource>> {
+        let mut sources = Vec::new();
+        for carve_filename in matches.values_of(option).into_iter().flatten() {
+            let fp = PathBuf::from(&carve_filename);
+            if ! fp.exists() || fp.is_dir() {
+                return Err(anyhow::Error::msg(format!("File {} does not exist", &carve_filename)));
+            } else if fp.is_file() {
+                sources.push(libmft2bodyfile::InputSource::File(fp));
+            } else {
+                /* named pipes and devices cannot be opened twice */
+                sources.push(libmft2bodyfile::InputSource::Reader(Box::new(File::open(&fp)?)));
+            }
+        }
+        Ok(sources)
+    }
+
+    /// parses a time in UTC, either in RFC 3339 format or like `2021-08-30 20:48:42`
+    fn parse_acquisition_time(time: &str) -> Result<DateTime<Utc>> {
+        if let Ok(time) = DateTime::parse_from_rfc3339(time) {
+            return Ok(time.with_timezone(&Utc));
+        }
+        match NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M:%S") {
+            Ok(time) => Ok(time.and_utc()),
+            Err(_) => Err(anyhow::Error::msg(format!("invalid acquisition time: {}", time))),
+        }
+    }
+
+    /// checks if `file` is an (optionally compressed) `$MFT` file. Otherwise,
+    /// we assume that it is a disk or volume image
+    fn is_mft_file(file: &Path) -> Result<bool> {
+        let mut magic = [0; 6];
+        let bytes = File::open(file)?.read(&mut magic)?;
+        Ok(bytes >= 4 && (&magic[0..4] == b"FILE"
+            || &magic[0..4] == b"BAAD"
+            || libmft2bodyfile::Compression::from_magic(&magic[..bytes]) != libmft2bodyfile::Compression::None))
+    }
+
     pub fn run(mut self) -> Result<()> {
         self.parse_options()?;
-        let task = Mft2BodyfileTask::default()
-            .with_mft_file(self.mft_file)
-            .with_usnjrnl(self.usnjrnl)
+        if let Some(triage_collection) = self.triage_collection.take() {
+            return self.run_on_triage_collection(&triage_collection);
+        }
+
+        let mut task = Mft2BodyfileTask::default()
+            .with_image_file(self.image_file)
+            .with_image_format(self.image_format)
+            .with_usnjrnl_sources(self.usnjrnl)
+            .with_usnjrnl_max(self.usnjrnl_max)
+            .with_logfile_source(self.logfile)
+            .with_carve_sources(self.carve_sources)
+            .with_usnjrnl_carve_sources(self.usnjrnl_carve_sources)
             .with_usnjrnl_longflags(self.usnjrnl_longflags)
+            .with_mft_slack(self.mft_slack)
+            .with_i30_sources(self.i30_sources)
+            .with_i30_from_image(self.i30_from_image)
             .with_progressbar(true)
+            .with_lifecycle_report(self.lifecycle_report)
+            .with_journal_report(self.journal_report)
+            .with_burst_report(self.burst_report)
+            .with_burst_detector(self.burst_detector)
+            .with_detect_timestomping(self.detect_timestomping)
+            .with_timestomping_report(self.timestomping_report)
+            .with_acquisition_time(self.acquisition_time)
+            .with_timestamp_filter(self.timestamp_filter)
+            .with_timestamp_report(self.timestamp_report)
             .with_output(self.output);
+        if let Some(mft_source) = self.mft_source {
+            task = task.with_mft_source(mft_source);
+        }
         task.run()
     }
+
+    /// runs a separate task for every volume found in the triage collection
+    fn run_on_triage_collection(self, path: &Path) -> Result<()> {
+        if self.i30_from_image {
+            return Err(anyhow::Error::msg("the --indexes option cannot be used with triage collections, which contain no $I30 indexes"));
+        }
+        let collection = libmft2bodyfile::TriageCollection::open(path)?;
+        if !self.usnjrnl.is_empty() || self.usnjrnl_max.is_some() {
+            log::warn!("ignoring the -J and --journal-max options, because $UsnJrnl is read from the triage collection");
+        }
+        if self.logfile.is_some() {
+            log::warn!("ignoring the -L option, because $LogFile is read from the triage collection");
+        }
+        if !(self.carve_sources.is_empty() && self.usnjrnl_carve_sources.is_empty() && self.i30_sources.is_empty()) {
+            log::warn!("ignoring the --carve, --carve-journal and --i30 options, which cannot be used with triage collections");
+        }
+
+        for volume in collection.volumes() {
+            let output = match &self.output {
+                BodyfileSink::Stdout => BodyfileSink::Stdout,
+                BodyfileSink::File(file) => BodyfileSink::File(file.try_clone()?),
+            };
+            Mft2BodyfileTask::default()
+                .with_mft_file(volume.mft().to_path_buf())
+                .with_usnjrnl(volume.usnjrnl().map(Path::to_path_buf))
+                .with_usnjrnl_max(volume.usnjrnl_max().map(Path::to_path_buf))
+                .with_logfile(volume.logfile().map(Path::to_path_buf))
+                .with_volume_tag(Some(volume.tag().to_owned()))
+                .with_usnjrnl_longflags(self.usnjrnl_longflags)
+                .with_mft_slack(self.mft_slack)
+                .with_progressbar(true)
+                .with_lifecycle_report(self.lifecycle_report.as_ref().map(File::try_clone).transpose()?)
+                .with_journal_report(self.journal_report.as_ref().map(File::try_clone).transpose()?)
+                .with_burst_report(self.burst_report.as_ref().map(File::try_clone).transpose()?)
+                .with_burst_detector(self.burst_detector)
+                .with_detect_timestomping(self.detect_timestomping)
+                .with_timestomping_report(self.timestomping_report.as_ref().map(File::try_clone).transpose()?)
+                .with_acquisition_time(self.acquisition_time)
+                .with_timestamp_filter(self.timestamp_filter.clone())
+                .with_timestamp_report(self.timestamp_report.as_ref().map(File::try_clone).transpose()?)
+                .with_output(output)
+                .run()?;
+        }
+        Ok(())
+    }
 }
 
 fn main() -> Result<()> {
@@ -0,0 +1,355 @@
+#![allow(dead_code)]
+
+//! helpers to create synthetic NTFS structures for the integration tests
+
+use libmft2bodyfile::{Mft2BodyfileTask, BodyfileSink};
+use mft::MftParser;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+pub const RECORD_SIZE: usize = 1024;
+
+/// 2021-08-30 20:48:42 UTC
+pub const FILETIME: u64 = 132748301220000000;
+
+pub fn get_mft_file() -> PathBuf {
+    let prj_root = env!("CARGO_MANIFEST_DIR");
+    let mut mft_file = PathBuf::from(prj_root);
+    mft_file.push("tests");
+    mft_file.push("data");
+    mft_file.push("control");
+    mft_file.push("MFT");
+    mft_file
+}
+
+pub fn read_control_mft() -> Vec<u8> {
+    let mut mft = Vec::new();
+    File::open(get_mft_file()).unwrap().read_to_end(&mut mft).unwrap();
+    mft
+}
+
+pub fn file_reference(entry: u64, sequence: u16) -> u64 {
+    entry | ((sequence as u64) << 48)
+}
+
+fn pad8(data: &mut Vec<u8>) {
+    while !data.len().is_multiple_of(8) {
+        data.push(0);
+    }
+}
+
+fn utf16(name: &str) -> Vec<u8> {
+    name.encode_utf16().flat_map(|c| c.to_le_bytes()).collect()
+}
+
+/// creates a `$STANDARD_INFORMATION` attribute value
+pub fn standard_information(timestamp: u64) -> Vec<u8> {
+    let mut value = Vec::new();
+    for _ in 0..4 {
+        value.extend_from_slice(&timestamp.to_le_bytes());
+    }
+    value.resize(72, 0);
+    value
+}
+
+/// creates a `$FILE_NAME` attribute value
+pub fn file_name(parent: u64, name: &str, timestamp: u64) -> Vec<u8> {
+    let mut value = Vec::new();
+    value.extend_from_slice(&parent.to_le_bytes());
+    for _ in 0..4 {
+        value.extend_from_slice(&timestamp.to_le_bytes());
+    }
+    value.extend_from_slice(&0u64.to_le_bytes());
+    value.extend_from_slice(&0u64.to_le_bytes());
+    value.extend_from_slice(&0x20u32.to_le_bytes());
+    value.extend_from_slice(&0u32.to_le_bytes());
+    value.push(name.encode_utf16().count() as u8);
+    value.push(3); /* Win32AndDos */
+    value.extend(utf16(name));
+    value
+}
+
+pub fn resident_attribute(type_code: u32, name: &str, instance: u16, value: &[u8]) -> Vec<u8> {
+    let mut name_bytes = utf16(name);
+    pad8(&mut name_bytes);
+    let value_offset = 0x18 + name_bytes.len();
+
+    let mut attr = Vec::new();
+    attr.extend_from_slice(&type_code.to_le_bytes());
+    attr.extend_from_slice(&0u32.to_le_bytes());
+    attr.push(0);
+    attr.push(name.encode_utf16().count() as u8);
+    attr.extend_from_slice(&0x18u16.to_le_bytes());
+    attr.extend_from_slice(&0u16.to_le_bytes());
+    attr.extend_from_slice(&instance.to_le_bytes());
+    attr.extend_from_slice(&(value.len() as u32).to_le_bytes());
+    attr.extend_from_slice(&(value_offset as u16).to_le_bytes());
+    attr.push(0);
+    attr.push(0);
+    attr.extend(name_bytes);
+    attr.extend_from_slice(value);
+    pad8(&mut attr);
+    let length = attr.len() as u32;
+    attr[4..8].copy_from_slice(&length.to_le_bytes());
+    attr
+}
+
+pub fn nonresident_attribute(type_code: u32, name: &str, instance: u16, clusters: u64, data_size: u64, runs: &[u8]) -> Vec<u8> {
+    let mut name_bytes = utf16(name);
+    pad8(&mut name_bytes);
+    let runs_offset = 0x40 + name_bytes.len();
+
+    let mut attr = Vec::new();
+    attr.extend_from_slice(&type_code.to_le_bytes());
+    attr.extend_from_slice(&0u32.to_le_bytes());
+    attr.push(1);
+    attr.push(name.encode_utf16().count() as u8);
+    attr.extend_from_slice(&0x40u16.to_le_bytes());
+    attr.extend_from_slice(&0u16.to_le_bytes());
+    attr.extend_from_slice(&instance.to_le_bytes());
+    attr.extend_from_slice(&0u64.to_le_bytes());
+    attr.extend_from_slice(&(clusters - 1).to_le_bytes());
+    attr.extend_from_slice(&(runs_offset as u16).to_le_bytes());
+    attr.extend_from_slice(&[0; 6]);
+    attr.extend_from_slice(&(clusters * 512).to_le_bytes());
+    attr.extend_from_slice(&data_size.to_le_bytes());
+    attr.extend_from_slice(&data_size.to_le_bytes());
+    attr.extend(name_bytes);
+    attr.extend_from_slice(runs);
+    attr.push(0);
+    pad8(&mut attr);
+    let length = attr.len() as u32;
+    attr[4..8].copy_from_slice(&length.to_le_bytes());
+    attr
+}
+
+/// creates an allocated `$MFT` entry of 1024 bytes, including the fixup array
+pub fn mft_entry(record_number: u32, sequence: u16, attributes: &[Vec<u8>]) -> Vec<u8> {
+    let mut entry = vec![0; RECORD_SIZE];
+    entry[0..4].copy_from_slice(b"FILE");
+    entry[0x04..0x06].copy_from_slice(&0x30u16.to_le_bytes());
+    entry[0x06..0x08].copy_from_slice(&3u16.to_le_bytes());
+    entry[0x10..0x12].copy_from_slice(&sequence.to_le_bytes());
+    entry[0x12..0x14].copy_from_slice(&1u16.to_le_bytes());
+    entry[0x14..0x16].copy_from_slice(&0x38u16.to_le_bytes());
+    entry[0x16..0x18].copy_from_slice(&1u16.to_le_bytes());
+    entry[0x1c..0x20].copy_from_slice(&(RECORD_SIZE as u32).to_le_bytes());
+    entry[0x28..0x2a].copy_from_slice(&(attributes.len() as u16).to_le_bytes());
+    entry[0x2c..0x30].copy_from_slice(&record_number.to_le_bytes());
+
+    let mut offset = 0x38;
+    for attr in attributes {
+        entry[offset..offset + attr.len()].copy_from_slice(attr);
+        offset += attr.len();
+    }
+    entry[offset..offset + 4].copy_from_slice(&0xffff_ffffu32.to_le_bytes());
+    entry[0x18..0x1c].copy_from_slice(&((offset + 8) as u32).to_le_bytes());
+
+    /* fixups: the update sequence number is 0x0001 */
+    entry[0x30..0x32].copy_from_slice(&1u16.to_le_bytes());
+    for sector in 0..2 {
+        let end = (sector + 1) * 512;
+        let original = [entry[end - 2], entry[end - 1]];
+        entry[0x32 + sector * 2..0x34 + sector * 2].copy_from_slice(&original);
+        entry[end - 2..end].copy_from_slice(&1u16.to_le_bytes());
+    }
+    entry
+}
+
+/// creates a `USN_RECORD_V2`
+pub fn usn_record_v2(file: u64, parent: u64, usn: i64, timestamp: u64, reason: u32, name: &str) -> Vec<u8> {
+    let mut record = Vec::new();
+    record.extend_from_slice(&0u32.to_le_bytes());
+    record.extend_from_slice(&2u16.to_le_bytes());
+    record.extend_from_slice(&0u16.to_le_bytes());
+    record.extend_from_slice(&file.to_le_bytes());
+    record.extend_from_slice(&parent.to_le_bytes());
+    record.extend_from_slice(&usn.to_le_bytes());
+    record.extend_from_slice(&timestamp.to_le_bytes());
+    record.extend_from_slice(&reason.to_le_bytes());
+    record.extend_from_slice(&0u32.to_le_bytes());
+    record.extend_from_slice(&0u32.to_le_bytes());
+    record.extend_from_slice(&0x20u32.to_le_bytes());
+    let name = utf16(name);
+    record.extend_from_slice(&(name.len() as u16).to_le_bytes());
+    record.extend_from_slice(&0x3cu16.to_le_bytes());
+    record.extend(name);
+    pad8(&mut record);
+    let length = record.len() as u32;
+    record[0..4].copy_from_slice(&length.to_le_bytes());
+    record
+}
+
+/// creates a `USN_RECORD_V3`, which uses 128 bit file ids
+pub fn usn_record_v3(file: u64, parent: u64, usn: i64, timestamp: u64, reason: u32, name: &str) -> Vec<u8> {
+    let mut record = Vec::new();
+    record.extend_from_slice(&0u32.to_le_bytes());
+    record.extend_from_slice(&3u16.to_le_bytes());
+    record.extend_from_slice(&0u16.to_le_bytes());
+    record.extend_from_slice(&(file as u128).to_le_bytes());
+    record.extend_from_slice(&(parent as u128).to_le_bytes());
+    record.extend_from_slice(&usn.to_le_bytes());
+    record.extend_from_slice(&timestamp.to_le_bytes());
+    record.extend_from_slice(&reason.to_le_bytes());
+    record.extend_from_slice(&0u32.to_le_bytes());
+    record.extend_from_slice(&0u32.to_le_bytes());
+    record.extend_from_slice(&0x20u32.to_le_bytes());
+    let name = utf16(name);
+    record.extend_from_slice(&(name.len() as u16).to_le_bytes());
+    record.extend_from_slice(&0x4cu16.to_le_bytes());
+    record.extend(name);
+    pad8(&mut record);
+    let length = record.len() as u32;
+    record[0..4].copy_from_slice(&length.to_le_bytes());
+    record
+}
+
+/// creates a `USN_RECORD_V4`, which contains modified ranges instead of a filename
+pub fn usn_record_v4(file: u64, parent: u64, usn: i64, reason: u32, extents: &[(i64, i64)]) -> Vec<u8> {
+    let mut record = Vec::new();
+    record.extend_from_slice(&0u32.to_le_bytes());
+    record.extend_from_slice(&4u16.to_le_bytes());
+    record.extend_from_slice(&0u16.to_le_bytes());
+    record.extend_from_slice(&(file as u128).to_le_bytes());
+    record.extend_from_slice(&(parent as u128).to_le_bytes());
+    record.extend_from_slice(&usn.to_le_bytes());
+    record.extend_from_slice(&reason.to_le_bytes());
+    record.extend_from_slice(&0u32.to_le_bytes());
+    record.extend_from_slice(&0u32.to_le_bytes());
+    record.extend_from_slice(&(extents.len() as u16).to_le_bytes());
+    record.extend_from_slice(&0x10u16.to_le_bytes());
+    for (offset, length) in extents {
+        record.extend_from_slice(&offset.to_le_bytes());
+        record.extend_from_slice(&length.to_le_bytes());
+    }
+    let length = record.len() as u32;
+    record[0..4].copy_from_slice(&length.to_le_bytes());
+    record
+}
+
+/// creates an entry of a `$I30` index, which contains a `$FILE_NAME` attribute value
+pub fn index_entry(file: u64, file_name: &[u8]) -> Vec<u8> {
+    let mut entry = Vec::new();
+    entry.extend_from_slice(&file.to_le_bytes());
+    entry.extend_from_slice(&0u16.to_le_bytes());
+    entry.extend_from_slice(&(file_name.len() as u16).to_le_bytes());
+    entry.extend_from_slice(&0u32.to_le_bytes());
+    entry.extend_from_slice(file_name);
+    pad8(&mut entry);
+    let length = entry.len() as u16;
+    entry[8..10].copy_from_slice(&length.to_le_bytes());
+    entry
+}
+
+/// creates an INDX record of 4096 bytes, which contains `entries` followed by
+/// the last entry of the node, and `slack` directly after the last entry
+pub fn indx_record(entries: &[Vec<u8>], slack: &[Vec<u8>]) -> Vec<u8> {
+    const SIZE: usize = 0x1000;
+    let mut record = vec![0; SIZE];
+    record[0..4].copy_from_slice(b"INDX");
+    record[0x04..0x06].copy_from_slice(&0x28u16.to_le_bytes());
+    record[0x06..0x08].copy_from_slice(&9u16.to_le_bytes());
+
+    let mut offset = 0x40;
+    for entry in entries {
+        record[offset..offset + entry.len()].copy_from_slice(entry);
+        offset += entry.len();
+    }
+    /* the last entry has no content */
+    record[offset + 8..offset + 10].copy_from_slice(&0x10u16.to_le_bytes());
+    record[offset + 12..offset + 16].copy_from_slice(&2u32.to_le_bytes());
+    offset += 0x10;
+    let index_length = offset;
+    for entry in slack {
+        record[offset..offset + entry.len()].copy_from_slice(entry);
+        offset += entry.len();
+    }
+
+    record[0x18..0x1c].copy_from_slice(&0x28u32.to_le_bytes());
+    record[0x1c..0x20].copy_from_slice(&((index_length - 0x18) as u32).to_le_bytes());
+    record[0x20..0x24].copy_from_slice(&((SIZE - 0x18) as u32).to_le_bytes());
+
+    /* fixups: the update sequence number is 0x0001 */
+    record[0x28..0x2a].copy_from_slice(&1u16.to_le_bytes());
+    for sector in 0..8 {
+        let end = (sector + 1) * 512;
+        let original = [record[end - 2], record[end - 1]];
+        record[0x2a + sector * 2..0x2c + sector * 2].copy_from_slice(&original);
+        record[end - 2..end].copy_from_slice(&1u16.to_le_bytes());
+    }
+    record
+}
+
+/// the control `$MFT` is located at this cluster (with a cluster size of 512 bytes)
+pub const MFT_LCN: u64 = 85680;
+pub const USNJRNL_LCN: u64 = MFT_LCN + 200;
+pub const USNJRNL_ENTRY: u32 = 40;
+pub const I30_LCN: u64 = USNJRNL_LCN + 32;
+pub const PARTITION_LBA: u64 = 2048;
+
+pub fn boot_sector(total_sectors: u64) -> Vec<u8> {
+    let mut sector = vec![0; 512];
+    sector[0..3].copy_from_slice(&[0xeb, 0x52, 0x90]);
+    sector[3..11].copy_from_slice(b"NTFS    ");
+    sector[0x0b..0x0d].copy_from_slice(&512u16.to_le_bytes());
+    sector[0x0d] = 1;
+    sector[0x28..0x30].copy_from_slice(&total_sectors.to_le_bytes());
+    sector[0x30..0x38].copy_from_slice(&MFT_LCN.to_le_bytes());
+    sector[0x40] = 0xf6; /* 1024 bytes per record */
+    sector[0x44] = 0xf4; /* 4096 bytes per index record */
+    sector[0x1fe..0x200].copy_from_slice(&[0x55, 0xaa]);
+    sector
+}
+
+/// creates a sparse image which contains `mft` at the original location of
+/// the control `$MFT`, and `clusters` at the specified cluster numbers
+pub fn create_image(mft: &[u8], with_mbr: bool, clusters: &[(u64, Vec<u8>)]) -> tempfile::NamedTempFile {
+    let volume_sectors = USNJRNL_LCN + 64;
+    let volume_offset = if with_mbr { PARTITION_LBA * 512 } else { 0 };
+
+    let mut image = tempfile::NamedTempFile::new().unwrap();
+    let file = image.as_file_mut();
+    if with_mbr {
+        let mut mbr = vec![0; 512];
+        mbr[0x1be + 4] = 0x07;
+        mbr[0x1be + 8..0x1be + 12].copy_from_slice(&(PARTITION_LBA as u32).to_le_bytes());
+        mbr[0x1be + 12..0x1be + 16].copy_from_slice(&(volume_sectors as u32).to_le_bytes());
+        mbr[0x1fe..0x200].copy_from_slice(&[0x55, 0xaa]);
+        file.write_all(&mbr).unwrap();
+    }
+    file.seek(SeekFrom::Start(volume_offset)).unwrap();
+    file.write_all(&boot_sector(volume_sectors)).unwrap();
+    file.seek(SeekFrom::Start(volume_offset + MFT_LCN * 512)).unwrap();
+    file.write_all(mft).unwrap();
+    for (lcn, data) in clusters {
+        file.seek(SeekFrom::Start(volume_offset + lcn * 512)).unwrap();
+        file.write_all(data).unwrap();
+    }
+    file.set_len(volume_offset + volume_sectors * 512).unwrap();
+    image
+}
+
+pub fn run_on_image(image: &Path) -> Vec<String> {
+    let output = tempfile::NamedTempFile::new().unwrap();
+    Mft2BodyfileTask::default()
+        .with_image_file(Some(image.to_path_buf()))
+        .with_output(BodyfileSink::File(output.reopen().unwrap()))
+        .run()
+        .unwrap();
+
+    let mut content = String::new();
+    File::open(output.path()).unwrap().read_to_string(&mut content).unwrap();
+    let mut lines: Vec<String> = content.lines().map(|l| l.to_owned()).collect();
+    lines.sort();
+    lines
+}
+
+pub fn expected_lines() -> Vec<String> {
+    let parser = MftParser::from_path(get_mft_file()).unwrap();
+    let mut lines: Vec<String> = Mft2BodyfileTask::fill_preprocessed_mft(parser, None)
+        .iter_entries(false)
+        .collect();
+    lines.sort();
+    lines
+}
@@ -0,0 +1,61 @@
+mod common;
+
+use common::*;
+use libmft2bodyfile::{BodyfileSink, BurstDetector, Mft2BodyfileTask};
+use std::io::Cursor;
+
+const DATA_OVERWRITE: u32 = 0x00000001;
+const FILE_DELETE: u32 = 0x00000200;
+const RENAME_OLD_NAME: u32 = 0x00001000;
+const RENAME_NEW_NAME: u32 = 0x00002000;
+
+const SECOND: u64 = 10_000_000;
+
+fn journal() -> Vec<u8> {
+    let root = file_reference(5, 5);
+    let mut usn = 0;
+    let mut data = Vec::new();
+    let mut add = |file: u64, timestamp: u64, reason: u32, name: &str| {
+        data.extend(usn_record_v2(file_reference(file, 1), root, usn, timestamp, reason, name));
+        usn += 0x100;
+    };
+
+    /* five documents are encrypted within five seconds */
+    for i in 0..5 {
+        let name = format!("doc{}.docx", i);
+        add(100 + i, FILETIME + i * SECOND, RENAME_OLD_NAME, &name);
+        add(100 + i, FILETIME + i * SECOND, RENAME_NEW_NAME, &format!("{}.locked", name));
+        add(100 + i, FILETIME + i * SECOND, DATA_OVERWRITE, &format!("{}.locked", name));
+    }
+
+    /* a single backup file an hour later is no burst */
+    add(110, FILETIME + 3600 * SECOND, RENAME_OLD_NAME, "notes.txt");
+    add(110, FILETIME + 3600 * SECOND, RENAME_NEW_NAME, "notes.bak");
+
+    /* three temporary files are deleted two hours later */
+    for i in 0..3 {
+        add(120 + i, FILETIME + (7200 + 2 * i) * SECOND, FILE_DELETE, &format!("~tmp{}.tmp", i));
+    }
+    data
+}
+
+#[test]
+fn test_burst_report() {
+    let report = tempfile::NamedTempFile::new().unwrap();
+    Mft2BodyfileTask::default()
+        .with_mft_file(get_mft_file())
+        .with_usnjrnl_reader(Cursor::new(journal()))
+        .with_burst_detector(BurstDetector::default().with_window(10).with_min_files(3))
+        .with_burst_report(Some(report.reopen().unwrap()))
+        .with_output(BodyfileSink::File(tempfile::tempfile().unwrap()))
+        .run()
+        .unwrap();
+
+    let content = std::fs::read_to_string(report.path()).unwrap();
+    let lines: Vec<&str> = content.lines().collect();
+    assert_eq!(lines, vec![
+        "kind,extension,start,end,files,events,directories,extensions",
+        "encryption,locked,2021-08-30 20:48:42,2021-08-30 20:48:46,5,10,/=10,docx=10",
+        "deletion,,2021-08-30 22:48:42,2021-08-30 22:48:46,3,3,/=3,tmp=3",
+    ]);
+}
@@ -0,0 +1,143 @@
+mod common;
+
+use common::*;
+use libmft2bodyfile::{BodyfileSink, InputSource, Mft2BodyfileTask, MftCarver, UsnCarver};
+use std::fs::File;
+use std::io::{Cursor, Read};
+
+fn carved_entry(record_number: u32, name: &str) -> Vec<u8> {
+    mft_entry(record_number, 3, &[
+        resident_attribute(0x10, "", 0, &standard_information(FILETIME)),
+        resident_attribute(0x30, "", 1, &file_name(file_reference(5, 5), name, FILETIME)),
+        resident_attribute(0x80, "", 2, b"content"),
+    ])
+}
+
+/// creates some garbage which contains two valid entries, a duplicate and
+/// an entry with broken fixups
+fn carving_data() -> Vec<u8> {
+    let mut data: Vec<u8> = (0..0x1200).map(|i| (i % 251) as u8).collect();
+    data.extend(carved_entry(1000, "carved.txt"));
+    data.extend(vec![0xaa; 0x200]);
+    data.extend(carved_entry(1000, "carved.txt"));
+    let mut broken = carved_entry(1001, "broken.txt");
+    broken[0x1fe] = 0xff;
+    data.extend(broken);
+    data.extend(carved_entry(1002, "other.txt"));
+
+    /* an unaligned entry must not be found */
+    data.extend(vec![0; 0x10]);
+    data.extend(carved_entry(1003, "unaligned.txt"));
+    data
+}
+
+fn run(task: Mft2BodyfileTask) -> Vec<String> {
+    let output = tempfile::NamedTempFile::new().unwrap();
+    task.with_output(BodyfileSink::File(output.reopen().unwrap())).run().unwrap();
+    let mut content = String::new();
+    File::open(output.path()).unwrap().read_to_string(&mut content).unwrap();
+    let mut lines: Vec<String> = content.lines().map(|l| l.to_owned()).collect();
+    lines.sort();
+    lines
+}
+
+#[test]
+fn test_carver() {
+    let offsets: Vec<(u64, u64)> = MftCarver::new(Cursor::new(carving_data()))
+        .map(|(offset, entry)| (offset, entry.header.record_number))
+        .collect();
+    assert_eq!(offsets, vec![(0x1200, 1000), (0x2000, 1002)]);
+}
+
+#[test]
+fn test_carving_with_mft() {
+    let lines = run(Mft2BodyfileTask::default()
+        .with_mft_file(get_mft_file())
+        .with_carve_sources(vec![InputSource::Reader(Box::new(Cursor::new(carving_data())))]));
+
+    let carved: Vec<&String> = lines.iter().filter(|l| l.contains("(carved at ")).collect();
+    assert_eq!(carved.len(), 4, "{:?}", carved);
+    assert!(carved.iter().any(|l| l.starts_with("0|/carved.txt (carved at 0x1200)|1000-128-2|")), "{:?}", carved);
+    assert!(carved.iter().any(|l| l.starts_with("0|/other.txt ($FILE_NAME) (carved at 0x2000)|1002-48-1|")), "{:?}", carved);
+
+    let mut uncarved: Vec<String> = lines.iter().filter(|l| !l.contains("(carved at ")).cloned().collect();
+    uncarved.sort();
+    assert_eq!(uncarved, expected_lines());
+}
+
+#[test]
+fn test_carving_without_mft() {
+    let lines = run(Mft2BodyfileTask::default()
+        .with_carve_sources(vec![InputSource::Reader(Box::new(Cursor::new(carving_data())))]));
+    assert_eq!(lines.len(), 4, "{:?}", lines);
+
+    /* without an $MFT, the parent folder cannot be resolved */
+    assert!(lines.iter().all(|l| l.contains("/$OrphanFiles/")), "{:?}", lines);
+}
+
+fn journal_record() -> Vec<u8> {
+    usn_record_v2(file_reference(39, 2), file_reference(5, 5), 0x100, FILETIME, 0x100, "created_file.txt")
+}
+
+/// creates some garbage which contains a copy of the record in `$UsnJrnl:$J`,
+/// two new records, and some implausible records
+fn usn_carving_data() -> Vec<u8> {
+    let mut data: Vec<u8> = (0..0x100).map(|i| (i % 251) as u8).collect();
+    data.extend(journal_record());
+    data.extend(usn_record_v2(file_reference(39, 2), file_reference(5, 5), 0x80, FILETIME - 10_000_000, 0x200, "old_file.txt"));
+    data.extend(vec![0; 0x18]);
+    data.extend(usn_record_v3(file_reference(41, 1), file_reference(5, 5), 0x40, FILETIME, 0x80000100, "v3_file.txt"));
+
+    /* timestamp in 1980 */
+    data.extend(usn_record_v2(file_reference(42, 1), file_reference(5, 5), 0, 119600064000000000, 0x100, "too_old.txt"));
+
+    /* unknown reason flag */
+    data.extend(usn_record_v2(file_reference(43, 1), file_reference(5, 5), 0, FILETIME, 0x01000000, "invalid_reason.txt"));
+
+    /* unaligned record */
+    data.extend(vec![0; 4]);
+    data.extend(usn_record_v2(file_reference(44, 1), file_reference(5, 5), 0, FILETIME, 0x100, "unaligned.txt"));
+    data
+}
+
+#[test]
+fn test_usn_carver() {
+    let records: Vec<(u64, String)> = UsnCarver::new(Cursor::new(usn_carving_data()))
+        .map(|r| (r.offset(), r.filename().to_owned()))
+        .collect();
+    assert_eq!(records, vec![
+        (0x100, "created_file.txt".to_owned()),
+        (0x160, "old_file.txt".to_owned()),
+        (0x1d0, "v3_file.txt".to_owned()),
+    ]);
+}
+
+#[test]
+fn test_usn_carving() {
+    let lines = run(Mft2BodyfileTask::default()
+        .with_mft_file(get_mft_file())
+        .with_usnjrnl_reader(Cursor::new(journal_record()))
+        .with_usnjrnl_carve_sources(vec![InputSource::Reader(Box::new(Cursor::new(usn_carving_data())))]));
+
+    let journal_lines: Vec<&String> = lines.iter().filter(|l| l.contains("($UsnJrnl ")).collect();
+    assert_eq!(journal_lines.len(), 3, "{:?}", journal_lines);
+
+    /* the record which is also stored in $UsnJrnl:$J is not marked as carved */
+    assert!(journal_lines.iter().any(|l| l.contains("|/created_file.txt ($UsnJrnl current='/RECYCLER/S-1-5-21-3958095517-222395546-2225589205-500/INFO2' reason=FILE_CREATE attributes=ARCHIVE usn=0x100 offset=0x0)|")), "{:?}", journal_lines);
+    assert!(journal_lines.iter().any(|l| l.contains("|/old_file.txt ($UsnJrnl current='/RECYCLER/S-1-5-21-3958095517-222395546-2225589205-500/INFO2' reason=FILE_DELETE attributes=ARCHIVE usn=0x80) (carved at 0x160)|")), "{:?}", journal_lines);
+    assert!(journal_lines.iter().any(|l| l.contains("|/v3_file.txt ($UsnJrnl reason=CLOSE+FILE_CREATE attributes=ARCHIVE usn=0x40) (carved at 0x1d0)|")), "{:?}", journal_lines);
+}
+
+#[test]
+fn test_carved_timestamp_precision() {
+    /* the last digit is lost if the timestamp is rounded to microseconds */
+    let entry = mft_entry(1000, 3, &[
+        resident_attribute(0x10, "", 0, &standard_information(FILETIME + 1234567)),
+        resident_attribute(0x30, "", 1, &file_name(file_reference(5, 5), "precise.txt", FILETIME + 1)),
+    ]);
+    let lines = run(Mft2BodyfileTask::default()
+        .with_mft_file(get_mft_file())
+        .with_carve_sources(vec![InputSource::Reader(Box::new(Cursor::new(entry)))]));
+    assert!(lines.contains(&"0|/precise.txt (carved at 0x0)|1000-0-0||0|0|0|1630356522.1234567|1630356522.1234567|1630356522.1234567|1630356522.1234567".to_owned()), "{:?}", lines);
+    assert!(lines.contains(&"0|/precise.txt ($FILE_NAME) (carved at 0x0)|1000-48-1||0|0|0|1630356522.0000001|1630356522.0000001|1630356522.0000001|1630356522.0000001".to_owned()), "{:?}", lines);
+}
@@ -0,0 +1,165 @@
+mod common;
+
+use common::*;
+use libmft2bodyfile::{BodyfileSink, Compression, Mft2BodyfileTask, MftRecordStream};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+
+fn compress(compression: Compression, data: &[u8]) -> Vec<u8> {
+    let mut output = Vec::new();
+    match compression {
+        Compression::None => output.extend_from_slice(data),
+        #[cfg(feature = "gzip")]
+        Compression::Gzip => {
+            let mut encoder = flate2::write::GzEncoder::new(&mut output, flate2::Compression::default());
+            encoder.write_all(data).unwrap();
+            encoder.finish().unwrap();
+        }
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => output = zstd::encode_all(data, 0).unwrap(),
+        #[cfg(feature = "xz")]
+        Compression::Xz => {
+            let mut encoder = xz2::write::XzEncoder::new(&mut output, 6);
+            encoder.write_all(data).unwrap();
+            encoder.finish().unwrap();
+        }
+        #[cfg(feature = "bzip2")]
+        Compression::Bzip2 => {
+            let mut encoder = bzip2::write::BzEncoder::new(&mut output, bzip2::Compression::default());
+            encoder.write_all(data).unwrap();
+            encoder.finish().unwrap();
+        }
+        #[cfg(feature = "lz4")]
+        Compression::Lz4 => {
+            let mut encoder = lz4_flex::frame::FrameEncoder::new(&mut output);
+            encoder.write_all(data).unwrap();
+            encoder.finish().unwrap();
+        }
+        #[allow(unreachable_patterns)]
+        _ => unreachable!(),
+    }
+    output
+}
+
+fn run_on_mft(mft: &Path, usnjrnl: &Path) -> Vec<String> {
+    let output = tempfile::NamedTempFile::new().unwrap();
+    Mft2BodyfileTask::default()
+        .with_mft_file(mft.to_path_buf())
+        .with_usnjrnl(Some(usnjrnl.to_path_buf()))
+        .with_output(BodyfileSink::File(output.reopen().unwrap()))
+        .run()
+        .unwrap();
+    let mut content = String::new();
+    File::open(output.path()).unwrap().read_to_string(&mut content).unwrap();
+    let mut lines: Vec<String> = content.lines().map(|l| l.to_owned()).collect();
+    lines.sort();
+    lines
+}
+
+fn test_compression(compression: Compression) {
+    let dir = tempfile::tempdir().unwrap();
+
+    /* do not use a file extension, because the compression must be detected by its magic bytes */
+    let mft = dir.path().join("MFT");
+    let compressed_mft = compress(compression, &read_control_mft());
+    File::create(&mft).unwrap().write_all(&compressed_mft).unwrap();
+    assert_eq!(Compression::from_path(&mft).unwrap(), compression);
+
+    let usnjrnl = dir.path().join("J");
+    let records = usn_record_v2(file_reference(39, 2), file_reference(5, 5), 0, FILETIME, 0x100, "created_file.txt");
+    File::create(&usnjrnl).unwrap().write_all(&compress(compression, &records)).unwrap();
+
+    let lines = run_on_mft(&mft, &usnjrnl);
+    let mut expected = expected_lines();
+    let journal_line = lines.iter().find(|l| l.contains("/created_file.txt ($UsnJrnl ")).expect("missing $UsnJrnl line");
+    expected.push(journal_line.clone());
+    expected.sort();
+    assert_eq!(lines, expected);
+}
+
+#[test]
+fn test_uncompressed() {
+    test_compression(Compression::None);
+}
+
+#[test]
+#[cfg(feature = "gzip")]
+fn test_gzip() {
+    test_compression(Compression::Gzip);
+}
+
+#[test]
+#[cfg(feature = "zstd")]
+fn test_zstd() {
+    test_compression(Compression::Zstd);
+}
+
+#[test]
+#[cfg(feature = "xz")]
+fn test_xz() {
+    test_compression(Compression::Xz);
+}
+
+#[test]
+#[cfg(feature = "bzip2")]
+fn test_bzip2() {
+    test_compression(Compression::Bzip2);
+}
+
+#[test]
+#[cfg(feature = "lz4")]
+fn test_lz4() {
+    test_compression(Compression::Lz4);
+}
+
+/// a forward-only reader which returns at most 100 bytes per read
+struct SlowReader<'a>(&'a [u8]);
+
+impl Read for SlowReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let count = std::cmp::min(std::cmp::min(buf.len(), 100), self.0.len());
+        buf[..count].copy_from_slice(&self.0[..count]);
+        self.0 = &self.0[count..];
+        Ok(count)
+    }
+}
+
+#[test]
+#[cfg(feature = "gzip")]
+fn test_reader_input() {
+    let mft = compress(Compression::Gzip, &read_control_mft());
+    let records = usn_record_v2(file_reference(39, 2), file_reference(5, 5), 0, FILETIME, 0x100, "created_file.txt");
+    let output = tempfile::NamedTempFile::new().unwrap();
+    Mft2BodyfileTask::default()
+        .with_mft_reader(std::io::Cursor::new(mft))
+        .with_usnjrnl_reader(std::io::Cursor::new(records))
+        .with_output(BodyfileSink::File(output.reopen().unwrap()))
+        .run()
+        .unwrap();
+    let mut content = String::new();
+    File::open(output.path()).unwrap().read_to_string(&mut content).unwrap();
+    let mut lines: Vec<String> = content.lines().map(|l| l.to_owned()).collect();
+    lines.sort();
+
+    let mut expected = expected_lines();
+    let journal_line = lines.iter().find(|l| l.contains("/created_file.txt ($UsnJrnl ")).expect("missing $UsnJrnl line");
+    expected.push(journal_line.clone());
+    expected.sort();
+    assert_eq!(lines, expected);
+}
+
+#[test]
+fn test_mft_record_stream() {
+    let mft = read_control_mft();
+    let stream = MftRecordStream::new(SlowReader(&mft)).unwrap();
+    let mut lines: Vec<String> = Mft2BodyfileTask::fill_preprocessed_mft_from_entries(stream.filter_map(Result::ok), None)
+        .iter_entries(false)
+        .collect();
+    lines.sort();
+    assert_eq!(lines, expected_lines());
+
+    /* an incomplete last entry is ignored */
+    let stream = MftRecordStream::new(SlowReader(&mft[..RECORD_SIZE * 3 + 10])).unwrap();
+    assert_eq!(stream.count(), 3);
+}
@@ -2,8 +2,6 @@ use libmft2bodyfile::{Mft2BodyfileTask, PreprocessedMft};
 use mft::MftParser;
 use std::path::PathBuf;
 use std::collections::hash_set::HashSet;
-use bodyfile::Bodyfile3Line;
-use std::convert::TryFrom;
 
 #[macro_use]
 extern crate more_asserts;
@@ -18,6 +16,26 @@ fn get_mft_file() -> PathBuf {
     mft_file
 }
 
+/// a bodyfile line, whose timestamps may have fractions of a second
+struct ParsedLine {
+    name: String,
+    inode: String,
+}
+
+impl ParsedLine {
+    fn parse(line: &str) -> Self {
+        let columns: Vec<&str> = line.split('|').collect();
+        assert_eq!(columns.len(), 11, "invalid bodyfile line: {}", line);
+        for timestamp in &columns[7..] {
+            assert!(timestamp.parse::<f64>().is_ok(), "invalid timestamp in bodyfile line: {}", line);
+        }
+        Self {
+            name: columns[1].to_owned(),
+            inode: columns[2].to_owned(),
+        }
+    }
+}
+
 fn get_parsed_mft() -> PreprocessedMft {
     let mft_file = get_mft_file();
     let parser = MftParser::from_path(mft_file).unwrap();
@@ -26,20 +44,20 @@ fn get_parsed_mft() -> PreprocessedMft {
 
 #[test]
 fn test_root_entry() {
-    let root_entries: Vec<Bodyfile3Line> = get_parsed_mft()
+    let root_entries: Vec<ParsedLine> = get_parsed_mft()
                         .iter_entries(false)
-                        .map(|l| Bodyfile3Line::try_from(l.as_ref()).unwrap_or_else(|_| { panic!("{}", l) }))
-                        .filter(|l| l.get_inode().starts_with("5-"))
+                        .map(|l| ParsedLine::parse(l.as_ref()))
+                        .filter(|l| l.inode.starts_with("5-"))
                         .collect();
 
     assert_ge!(root_entries.len(), 1);
     assert_le!(root_entries.len(), 4);
     for e in root_entries.iter() {
-        assert_ge!(e.get_name().len(), 1);
-        if e.get_name().len() == 1 {
-            assert_eq!(e.get_name(), "/");
+        assert_ge!(e.name.len(), 1);
+        if e.name.len() == 1 {
+            assert_eq!(e.name, "/");
         } else {
-            assert!(e.get_name().starts_with("/ "));
+            assert!(e.name.starts_with("/ "));
         }
     }
 }
@@ -64,9 +82,9 @@ fn test_deleted_entries() {
 
     let mut deleted_entries: HashSet<String> = get_parsed_mft()
                         .iter_entries(false)
-                        .map(|l| Bodyfile3Line::try_from(l.as_ref()).unwrap_or_else(|_| { panic!("{}", l) }))
-                        .filter(|l| l.get_name().contains("deleted"))
-                        .map(|l| l.get_name().to_owned())
+                        .map(|l| ParsedLine::parse(l.as_ref()))
+                        .filter(|l| l.name.contains("deleted"))
+                        .map(|l| l.name)
                         .collect();
 
     eprintln!("{:?}", deleted_entries);
@@ -75,4 +93,14 @@ fn test_deleted_entries() {
         deleted_entries.remove(entry);
     }
     assert!(deleted_entries.is_empty(), "the following entries were unexpected: '{:?}'", deleted_entries);
-}
\ No newline at end of file
+}
+#[test]
+fn test_fractional_timestamps() {
+    let lines: Vec<String> = get_parsed_mft().iter_entries(false).collect();
+
+    /* NTFS stores timestamps with a precision of 100ns */
+    assert!(lines.contains(&"0|/$MFT|0-128-1||0|0|16384|1187613169.171875|1187613169.171875|1187613169.171875|1187613169.171875".to_owned()), "{:?}", lines);
+
+    /* a zero FILETIME is 1601-01-01, which is not replaced by 1970-01-01 */
+    assert!(lines.contains(&"0|/$Secure ($FILE_NAME)|9-48-7||0|0|0|-11644473600|-11644473600|-11644473600|-11644473600".to_owned()), "{:?}", lines);
+}
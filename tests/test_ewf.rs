@@ -0,0 +1,244 @@
+#![cfg(feature = "ewf")]
+
+mod common;
+
+use common::*;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use libmft2bodyfile::EwfReader;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+const SECTORS_PER_CHUNK: usize = 64;
+const CHUNK_SIZE: usize = SECTORS_PER_CHUNK * 512;
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+fn zlib(data: &[u8]) -> Vec<u8> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn ewf1_section(segment: &mut Vec<u8>, section_type: &str, data: &[u8], is_last: bool) {
+    let offset = segment.len() as u64;
+    let size = 76 + data.len() as u64;
+    let mut descriptor = vec![0; 76];
+    descriptor[..section_type.len()].copy_from_slice(section_type.as_bytes());
+    let next = if is_last { offset } else { offset + size };
+    descriptor[16..24].copy_from_slice(&next.to_le_bytes());
+    descriptor[24..32].copy_from_slice(&size.to_le_bytes());
+    let checksum = adler32(&descriptor[..72]);
+    descriptor[72..76].copy_from_slice(&checksum.to_le_bytes());
+    segment.extend(descriptor);
+    segment.extend_from_slice(data);
+}
+
+/// writes `image` as EWF1 image, which is split into segments of `chunks_per_segment` chunks.
+/// Chunks which contain only zeros are compressed, all others are stored uncompressed.
+fn write_ewf1(image: &[u8], first_segment: &Path, chunks_per_segment: usize) {
+    let chunks: Vec<&[u8]> = image.chunks(CHUNK_SIZE).collect();
+    let segments: Vec<&[&[u8]]> = chunks.chunks(chunks_per_segment).collect();
+
+    for (idx, segment_chunks) in segments.iter().enumerate() {
+        let mut segment = Vec::new();
+        segment.extend_from_slice(b"EVF\x09\x0d\x0a\xff\x00\x01");
+        segment.extend_from_slice(&(idx as u16 + 1).to_le_bytes());
+        segment.extend_from_slice(&[0, 0]);
+
+        if idx == 0 {
+            let mut volume = vec![0; 94];
+            volume[4..8].copy_from_slice(&(chunks.len() as u32).to_le_bytes());
+            volume[8..12].copy_from_slice(&(SECTORS_PER_CHUNK as u32).to_le_bytes());
+            volume[12..16].copy_from_slice(&512u32.to_le_bytes());
+            volume[16..24].copy_from_slice(&(image.len() as u64 / 512).to_le_bytes());
+            ewf1_section(&mut segment, "volume", &volume, false);
+        }
+
+        let sectors_offset = segment.len() as u64;
+        let mut sectors = Vec::new();
+        let mut offsets = Vec::new();
+        for chunk in segment_chunks.iter() {
+            let offset = (sectors_offset + 76 + sectors.len() as u64) as u32;
+            if chunk.iter().all(|b| *b == 0) {
+                offsets.push(offset | 0x8000_0000);
+                sectors.extend(zlib(chunk));
+            } else {
+                offsets.push(offset);
+                sectors.extend_from_slice(chunk);
+                sectors.extend_from_slice(&adler32(chunk).to_le_bytes());
+            }
+        }
+        ewf1_section(&mut segment, "sectors", &sectors, false);
+
+        let mut table = vec![0; 24];
+        table[0..4].copy_from_slice(&(offsets.len() as u32).to_le_bytes());
+        table.extend(offsets.iter().flat_map(|o| o.to_le_bytes()));
+        ewf1_section(&mut segment, "table", &table, false);
+
+        let is_last = idx == segments.len() - 1;
+        ewf1_section(&mut segment, if is_last { "done" } else { "next" }, &[], true);
+
+        let path = first_segment.with_extension(format!("E{:02}", idx + 1));
+        File::create(path).unwrap().write_all(&segment).unwrap();
+    }
+}
+
+fn ewf2_section(segment: &mut Vec<u8>, section_type: u32, data: &[u8], previous_offset: &mut u64) {
+    segment.extend_from_slice(data);
+    let offset = segment.len() as u64;
+    let mut descriptor = vec![0; 64];
+    descriptor[0..4].copy_from_slice(&section_type.to_le_bytes());
+    descriptor[8..16].copy_from_slice(&previous_offset.to_le_bytes());
+    descriptor[16..24].copy_from_slice(&(data.len() as u64).to_le_bytes());
+    descriptor[24..28].copy_from_slice(&64u32.to_le_bytes());
+    let checksum = adler32(&descriptor[..60]);
+    descriptor[60..64].copy_from_slice(&checksum.to_le_bytes());
+    segment.extend(descriptor);
+    *previous_offset = offset;
+}
+
+fn ewf2_properties(keys: &[&str], values: &[&str]) -> Vec<u8> {
+    let text = format!("1\nmain\n{}\n{}\n\n", keys.join("\t"), values.join("\t"));
+    let mut data = vec![0xff, 0xfe];
+    data.extend(text.encode_utf16().flat_map(|c| c.to_le_bytes()));
+    zlib(&data)
+}
+
+/// writes `image` as EWF2 image, which is split into segments of `chunks_per_segment` chunks.
+/// Chunks which contain only zeros are stored as pattern fill, all others are compressed.
+fn write_ewf2(image: &[u8], first_segment: &Path, chunks_per_segment: usize) {
+    let chunks: Vec<&[u8]> = image.chunks(CHUNK_SIZE).collect();
+    let segments: Vec<&[&[u8]]> = chunks.chunks(chunks_per_segment).collect();
+    let extension = first_segment.extension().unwrap().to_str().unwrap().to_owned();
+
+    for (idx, segment_chunks) in segments.iter().enumerate() {
+        let mut segment = Vec::new();
+        segment.extend_from_slice(b"EVF2\x0d\x0a\x81\x00\x02\x01");
+        segment.extend_from_slice(&1u16.to_le_bytes());
+        segment.extend_from_slice(&(idx as u32 + 1).to_le_bytes());
+        segment.resize(32, 0);
+        let mut previous_offset = 0;
+
+        if idx == 0 {
+            let sectors = (image.len() / 512).to_string();
+            ewf2_section(&mut segment, 0x01, &ewf2_properties(&["sn", "bp", "ts"], &["0815", "512", &sectors]), &mut previous_offset);
+            ewf2_section(&mut segment, 0x02, &ewf2_properties(&["nm", "sb"], &["test", &SECTORS_PER_CHUNK.to_string()]), &mut previous_offset);
+        }
+
+        let mut entries = Vec::new();
+        let mut sectors_data = Vec::new();
+        let sectors_offset = segment.len() as u64;
+        for chunk in segment_chunks.iter() {
+            if chunk.iter().all(|b| *b == 0) {
+                entries.extend(0u64.to_le_bytes());
+                entries.extend(0u32.to_le_bytes());
+                entries.extend(0x05u32.to_le_bytes());
+            } else {
+                let compressed = zlib(chunk);
+                entries.extend((sectors_offset + sectors_data.len() as u64).to_le_bytes());
+                entries.extend((compressed.len() as u32).to_le_bytes());
+                entries.extend(0x01u32.to_le_bytes());
+                sectors_data.extend(compressed);
+            }
+        }
+        ewf2_section(&mut segment, 0x03, &sectors_data, &mut previous_offset);
+
+        let mut table = vec![0; 32];
+        table[8..12].copy_from_slice(&((entries.len() / 16) as u32).to_le_bytes());
+        table.extend(entries);
+        ewf2_section(&mut segment, 0x04, &table, &mut previous_offset);
+
+        let is_last = idx == segments.len() - 1;
+        ewf2_section(&mut segment, if is_last { 0x0f } else { 0x0d }, &[], &mut previous_offset);
+
+        /* keep the case of the prefix, e.g. `Ex01`, `Ex02`, ... */
+        let path = first_segment.with_extension(format!("{}{:02}", &extension[..2], idx + 1));
+        File::create(path).unwrap().write_all(&segment).unwrap();
+    }
+}
+
+fn read_image(image: &tempfile::NamedTempFile) -> Vec<u8> {
+    let mut data = Vec::new();
+    File::open(image.path()).unwrap().read_to_end(&mut data).unwrap();
+    data
+}
+
+#[test]
+fn test_ewf1_segments() {
+    let raw_image = create_image(&read_control_mft(), true, &[]);
+    let raw_data = read_image(&raw_image);
+    let dir = tempfile::tempdir().unwrap();
+    let first_segment = dir.path().join("image.E01");
+    write_ewf1(&raw_data, &first_segment, 500);
+    assert!(dir.path().join("image.E03").exists());
+
+    let mut reader = EwfReader::open(&first_segment).unwrap();
+    assert_eq!(reader.size(), raw_data.len() as u64);
+
+    let mft_offset = ((PARTITION_LBA + MFT_LCN) * 512) as usize;
+    let mut mft = vec![0; 4096];
+    reader.seek(SeekFrom::Start(mft_offset as u64)).unwrap();
+    reader.read_exact(&mut mft).unwrap();
+    assert_eq!(mft, raw_data[mft_offset..mft_offset + 4096]);
+
+    assert_eq!(run_on_image(&first_segment), expected_lines());
+}
+
+#[test]
+fn test_ewf2() {
+    let raw_image = create_image(&read_control_mft(), false, &[]);
+    let raw_data = read_image(&raw_image);
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("image.Ex01");
+    write_ewf2(&raw_data, &path, usize::MAX);
+
+    let mut reader = EwfReader::open(&path).unwrap();
+    assert_eq!(reader.size(), raw_data.len() as u64);
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data).unwrap();
+    assert!(data == raw_data);
+
+    assert_eq!(run_on_image(&path), expected_lines());
+}
+
+#[test]
+fn test_ewf2_segments() {
+    let raw_image = create_image(&read_control_mft(), true, &[]);
+    let raw_data = read_image(&raw_image);
+    let dir = tempfile::tempdir().unwrap();
+    let first_segment = dir.path().join("image.Ex01");
+    write_ewf2(&raw_data, &first_segment, 500);
+    assert!(dir.path().join("image.Ex03").exists());
+
+    let mut reader = EwfReader::open(&first_segment).unwrap();
+    assert_eq!(reader.size(), raw_data.len() as u64);
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data).unwrap();
+    assert!(data == raw_data);
+
+    assert_eq!(run_on_image(&first_segment), expected_lines());
+}
+
+#[test]
+fn test_ewf1_invalid_table() {
+    let raw_data = read_image(&create_image(&read_control_mft(), false, &[]));
+    let dir = tempfile::tempdir().unwrap();
+    let first_segment = dir.path().join("image.E01");
+    write_ewf1(&raw_data, &first_segment, usize::MAX);
+
+    /* the number of table entries exceeds the segment */
+    let mut segment = std::fs::read(&first_segment).unwrap();
+    let table = segment.windows(8).position(|w| w == b"table\0\0\0").unwrap() + 76;
+    segment[table..table + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+    std::fs::write(&first_segment, &segment).unwrap();
+    assert!(EwfReader::open(&first_segment).is_err());
+}
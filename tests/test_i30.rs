@@ -0,0 +1,105 @@
+mod common;
+
+use common::*;
+use libmft2bodyfile::{BodyfileSink, IndexRecordIterator, InputSource, Mft2BodyfileTask, DEFAULT_INDEX_RECORD_SIZE};
+use std::fs::File;
+use std::io::{Cursor, Read};
+
+/// 2020-09-12 09:46:40 UTC
+const OLD_FILETIME: u64 = 132443776000000000;
+
+const DIRECTORY_ENTRY: u32 = 41;
+
+/// an index of the root directory, which contains the root itself, a file
+/// which does not exist in the `$MFT` and a deleted file in its slack
+fn root_index() -> Vec<u8> {
+    let root = file_reference(5, 5);
+    let mut index = indx_record(
+        &[
+            index_entry(root, &file_name(root, ".", FILETIME)),
+            index_entry(file_reference(60, 1), &file_name(root, "gone.txt", FILETIME)),
+        ],
+        &[index_entry(file_reference(61, 1), &file_name(root, "deleted.txt", OLD_FILETIME))],
+    );
+    /* an unused record */
+    index.resize(2 * DEFAULT_INDEX_RECORD_SIZE, 0);
+    index
+}
+
+fn read_output(output: &tempfile::NamedTempFile) -> Vec<String> {
+    let mut content = String::new();
+    File::open(output.path()).unwrap().read_to_string(&mut content).unwrap();
+    content.lines().map(|l| l.to_owned()).collect()
+}
+
+#[test]
+fn test_index_records() {
+    let entries: Vec<_> = IndexRecordIterator::new(Cursor::new(root_index()), DEFAULT_INDEX_RECORD_SIZE).collect();
+    let names: Vec<(&str, bool)> = entries.iter().map(|e| (e.filename().as_str(), e.is_slack())).collect();
+    assert_eq!(names, vec![(".", false), ("gone.txt", false), ("deleted.txt", true)]);
+    assert_eq!(entries[2].file_reference().entry, 61);
+    assert_eq!(entries[2].parent().entry, 5);
+}
+
+#[test]
+fn test_i30_file() {
+    let index = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(index.path(), root_index()).unwrap();
+
+    let output = tempfile::NamedTempFile::new().unwrap();
+    Mft2BodyfileTask::default()
+        .with_mft_file(get_mft_file())
+        .with_i30_sources(vec![InputSource::File(index.path().to_path_buf())])
+        .with_output(BodyfileSink::File(output.reopen().unwrap()))
+        .run()
+        .unwrap();
+    let lines = read_output(&output);
+
+    let index_lines: Vec<&String> = lines.iter().filter(|l| l.contains("($I30")).collect();
+    assert_eq!(index_lines.len(), 2, "{:?}", index_lines);
+    assert!(lines.contains(&"0|/gone.txt ($I30)|60-160-1||0|0|0|1630356522|1630356522|1630356522|1630356522".to_owned()), "{:?}", index_lines);
+    assert!(lines.contains(&"0|/deleted.txt ($I30, slack)|61-160-1||0|0|0|1599904000|1599904000|1599904000|1599904000".to_owned()), "{:?}", index_lines);
+}
+
+#[test]
+fn test_i30_from_image() {
+    let mut mft = read_control_mft();
+    let directory = file_reference(DIRECTORY_ENTRY as u64, 1);
+
+    /* the index allocation consists of 8 clusters */
+    let runs = [0x31, 0x08, (I30_LCN & 0xff) as u8, ((I30_LCN >> 8) & 0xff) as u8, (I30_LCN >> 16) as u8];
+    let mut entry = mft_entry(DIRECTORY_ENTRY, 1, &[
+        resident_attribute(0x10, "", 0, &standard_information(FILETIME)),
+        resident_attribute(0x30, "", 1, &file_name(file_reference(5, 5), "dir", FILETIME)),
+        nonresident_attribute(0xa0, "$I30", 2, 8, 8 * 512, &runs),
+    ]);
+    entry[0x16] = 3; /* allocated directory */
+    let offset = DIRECTORY_ENTRY as usize * RECORD_SIZE;
+    mft[offset..offset + RECORD_SIZE].copy_from_slice(&entry);
+
+    let index = indx_record(
+        &[index_entry(file_reference(62, 1), &file_name(directory, "file.txt", FILETIME))],
+        &[index_entry(file_reference(63, 2), &file_name(directory, "deleted.txt", OLD_FILETIME))],
+    );
+    let image = create_image(&mft, false, &[(I30_LCN, index)]);
+
+    let output = tempfile::NamedTempFile::new().unwrap();
+    Mft2BodyfileTask::default()
+        .with_image_file(Some(image.path().to_path_buf()))
+        .with_i30_from_image(true)
+        .with_output(BodyfileSink::File(output.reopen().unwrap()))
+        .run()
+        .unwrap();
+    let lines = read_output(&output);
+
+    let index_lines: Vec<&String> = lines.iter().filter(|l| l.contains("($I30")).collect();
+    assert_eq!(index_lines.len(), 2, "{:?}", index_lines);
+    assert!(index_lines.iter().any(|l| l.starts_with("0|/dir/file.txt ($I30)|62-160-1|")), "{:?}", index_lines);
+    assert!(index_lines.iter().any(|l| l.starts_with("0|/dir/deleted.txt ($I30, slack)|63-160-2|")), "{:?}", index_lines);
+}
+
+#[test]
+fn test_without_indexes() {
+    let image = create_image(&read_control_mft(), false, &[]);
+    assert!(run_on_image(image.path()).iter().all(|l| !l.contains("($I30")));
+}
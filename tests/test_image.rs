@@ -0,0 +1,167 @@
+mod common;
+
+use common::*;
+use libmft2bodyfile::{BodyfileSink, Mft2BodyfileTask, NonResidentStream, NtfsBootSector, NtfsVolume, PartitionReader, PartitionTable, PartitionTableType, SharedReader, parse_data_runs};
+use mft::MftParser;
+use std::fs::File;
+use std::io::{Read, Seek};
+
+#[test]
+fn test_volume_image() {
+    let image = create_image(&read_control_mft(), false, &[]);
+    let reader = SharedReader::new(File::open(image.path()).unwrap());
+    let partitions = PartitionTable::from_reader(&mut reader.clone()).unwrap();
+    assert_eq!(partitions.table_type(), PartitionTableType::None);
+    assert_eq!(partitions.partitions().len(), 1);
+
+    let partition = &partitions.partitions()[0];
+    let volume = NtfsVolume::new(PartitionReader::new(reader, partition.start(), partition.size())).unwrap();
+    assert_eq!(volume.boot_sector().cluster_size(), 512);
+    assert_eq!(volume.boot_sector().mft_record_size(), 1024);
+
+    let mft = volume.mft_stream().unwrap();
+    assert_eq!(mft.size(), 49152);
+    let mft_size = mft.size();
+    let parser = MftParser::from_read_seek(mft, Some(mft_size)).unwrap();
+    let mut lines: Vec<String> = Mft2BodyfileTask::fill_preprocessed_mft(parser, None)
+        .iter_entries(false)
+        .collect();
+    lines.sort();
+    assert_eq!(lines, expected_lines());
+}
+
+#[test]
+fn test_partitioned_image() {
+    let image = create_image(&read_control_mft(), true, &[]);
+    assert_eq!(run_on_image(image.path()), expected_lines());
+}
+
+#[test]
+fn test_usnjrnl_from_image() {
+    let mut mft = read_control_mft();
+    let parser = MftParser::from_buffer(mft.clone()).unwrap();
+    let extend = Mft2BodyfileTask::fill_preprocessed_mft(parser, None)
+        .find_by_path("/$EXTEND")
+        .expect("missing $Extend");
+    assert_eq!(extend.entry, 11);
+
+    /* $J consists of 16 sparse clusters, followed by one allocated cluster */
+    let runs = [0x01, 0x10, 0x31, 0x01, (USNJRNL_LCN & 0xff) as u8, ((USNJRNL_LCN >> 8) & 0xff) as u8, (USNJRNL_LCN >> 16) as u8];
+    let usnjrnl = mft_entry(USNJRNL_ENTRY, 1, &[
+        resident_attribute(0x10, "", 0, &standard_information(FILETIME)),
+        resident_attribute(0x30, "", 1, &file_name(file_reference(extend.entry, extend.sequence), "$UsnJrnl", FILETIME)),
+        nonresident_attribute(0x80, "$J", 2, 17, 17 * 512, &runs),
+    ]);
+    let offset = USNJRNL_ENTRY as usize * RECORD_SIZE;
+    mft[offset..offset + RECORD_SIZE].copy_from_slice(&usnjrnl);
+
+    let mut journal = usn_record_v2(file_reference(39, 2), file_reference(5, 5), 16 * 512, FILETIME, 0x100, "created_file.txt");
+    journal.resize(512, 0);
+
+    let image = create_image(&mft, false, &[(USNJRNL_LCN, journal)]);
+    let lines = run_on_image(image.path());
+    assert!(lines.iter().any(|l| l.contains("/$Extend/$UsnJrnl:$J|")), "{:?}", lines);
+
+    let journal_lines: Vec<&String> = lines.iter().filter(|l| l.contains("/created_file.txt ($UsnJrnl ")).collect();
+    assert_eq!(journal_lines.len(), 1, "{:?}", lines);
+    assert!(journal_lines[0].contains("reason=FILE_CREATE"));
+
+    /* the offset of the record is relative to the start of $J, including the sparse clusters */
+    assert!(journal_lines[0].contains("usn=0x2000 offset=0x2000)"), "{:?}", journal_lines);
+}
+
+/// creates a GPT disk with one valid partition and one whose last LBA is before its first LBA
+fn corrupt_gpt() -> Vec<u8> {
+    let mut disk = vec![0u8; 512 * 40];
+    disk[0x1be + 4] = 0xee;
+    disk[0x1fe] = 0x55;
+    disk[0x1ff] = 0xaa;
+
+    let header = &mut disk[512..1024];
+    header[0..8].copy_from_slice(b"EFI PART");
+    header[72..80].copy_from_slice(&2u64.to_le_bytes());
+    header[80..84].copy_from_slice(&2u32.to_le_bytes());
+    header[84..88].copy_from_slice(&128u32.to_le_bytes());
+
+    for (index, (first_lba, last_lba)) in [(34u64, 39u64), (39, 34)].iter().enumerate() {
+        let entry = &mut disk[1024 + index * 128..1024 + (index + 1) * 128];
+        entry[0] = 1;
+        entry[32..40].copy_from_slice(&first_lba.to_le_bytes());
+        entry[40..48].copy_from_slice(&last_lba.to_le_bytes());
+    }
+    disk
+}
+
+#[test]
+fn test_corrupt_gpt_entry() {
+    let partitions = PartitionTable::from_reader(&mut std::io::Cursor::new(corrupt_gpt())).unwrap();
+    assert_eq!(partitions.table_type(), PartitionTableType::Gpt);
+    assert_eq!(partitions.partitions().len(), 1);
+    assert_eq!(partitions.partitions()[0].start(), 34 * 512);
+    assert_eq!(partitions.partitions()[0].size(), 6 * 512);
+}
+
+#[test]
+fn test_invalid_boot_sector() {
+    assert!(NtfsBootSector::from_bytes(&boot_sector(1000)).is_ok());
+    for (offset, value) in [(0x0d, 0x81), (0x0d, 0xc0), (0x40, 0), (0x40, 0xc0), (0x44, 0x80), (0x44, 0xfb), (0x44, 0xe1)].iter() {
+        let mut sector = boot_sector(1000);
+        sector[*offset] = *value;
+        assert!(NtfsBootSector::from_bytes(&sector).is_err());
+    }
+}
+
+#[test]
+fn test_overflowing_data_runs() {
+    let runs = [0x11, 0x01, 0x7f, 0x81, 0x01, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f, 0x00];
+    assert!(parse_data_runs(&runs, 0).is_err());
+    let runs = [0x08, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00];
+    assert!(parse_data_runs(&runs, 1).is_err());
+
+    /* a valid data run whose byte offset does not fit into 64 bits */
+    let runs = parse_data_runs(&[0x81, 0x01, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f, 0x00], 0).unwrap();
+    let mut stream = NonResidentStream::new(std::io::Cursor::new(vec![0; 4096]), 4096, runs, 4096);
+    let mut buffer = vec![0; 4096];
+    assert_eq!(stream.read(&mut buffer).unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+    assert!(stream.seek(std::io::SeekFrom::Current(i64::MAX)).is_ok());
+    assert!(stream.seek(std::io::SeekFrom::Current(i64::MAX)).is_ok());
+    assert!(stream.seek(std::io::SeekFrom::Current(i64::MAX)).is_err());
+}
+
+/// creates an image with two partition entries, which point to the same volume
+fn image_with_two_volumes() -> tempfile::NamedTempFile {
+    let image = create_image(&read_control_mft(), true, &[]);
+    let mut disk = std::fs::read(image.path()).unwrap();
+    let (first, second) = (0x1be, 0x1ce);
+    let entry = disk[first..first + 16].to_vec();
+    disk[second..second + 16].copy_from_slice(&entry);
+    std::fs::write(image.path(), &disk).unwrap();
+    image
+}
+
+#[test]
+fn test_multiple_volumes() {
+    let image = image_with_two_volumes();
+    let lines = run_on_image(image.path());
+    let tagged = |tag: &str| -> Vec<String> {
+        let mut lines: Vec<String> = expected_lines().iter().map(|l| l.replacen("0|/", &format!("0|{}:/", tag), 1)).collect();
+        lines.sort();
+        lines
+    };
+    let mut expected = [tagged("p1"), tagged("p2")].concat();
+    expected.sort();
+    assert_eq!(lines, expected);
+}
+
+#[test]
+fn test_external_usnjrnl_with_multiple_volumes() {
+    let image = image_with_two_volumes();
+
+    let journal = tempfile::NamedTempFile::new().unwrap();
+    let result = Mft2BodyfileTask::default()
+        .with_image_file(Some(image.path().to_path_buf()))
+        .with_usnjrnl(Some(journal.path().to_path_buf()))
+        .with_output(BodyfileSink::File(tempfile::tempfile().unwrap()))
+        .run();
+    assert!(result.unwrap_err().to_string().contains("found 2 NTFS volumes"));
+}
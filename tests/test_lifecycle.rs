@@ -0,0 +1,51 @@
+mod common;
+
+use common::*;
+use libmft2bodyfile::{BodyfileSink, Mft2BodyfileTask};
+use std::io::Cursor;
+
+const FILE_CREATE: u32 = 0x00000100;
+const FILE_DELETE: u32 = 0x00000200;
+const DATA_OVERWRITE: u32 = 0x00000001;
+const RENAME_OLD_NAME: u32 = 0x00001000;
+const RENAME_NEW_NAME: u32 = 0x00002000;
+
+fn journal() -> Vec<u8> {
+    let file = file_reference(29, 2);
+    let root = file_reference(5, 5);
+    let extend = file_reference(11, 11);
+    let mut data = Vec::new();
+    data.extend(usn_record_v2(file, extend, 0x000, FILETIME, FILE_CREATE, "photo.jpg"));
+    data.extend(usn_record_v2(file, extend, 0x048, FILETIME + 10_000_000, RENAME_OLD_NAME, "photo.jpg"));
+    data.extend(usn_record_v2(file, root, 0x090, FILETIME + 10_000_000, RENAME_NEW_NAME, "Export_me.JPG"));
+    data.extend(usn_record_v2(file, root, 0x0e0, FILETIME + 20_000_000, DATA_OVERWRITE, "Export_me.JPG"));
+
+    /* /RECYCLER/.../INFO2 */
+    data.extend(usn_record_v2(file_reference(39, 1), file_reference(37, 1), 0x130, FILETIME + 30_000_000, FILE_DELETE, "INFO2"));
+    data
+}
+
+#[test]
+fn test_lifecycle_report() {
+    let report = tempfile::NamedTempFile::new().unwrap();
+    Mft2BodyfileTask::default()
+        .with_mft_file(get_mft_file())
+        .with_usnjrnl_reader(Cursor::new(journal()))
+        .with_lifecycle_report(Some(report.reopen().unwrap()))
+        .with_output(BodyfileSink::File(tempfile::tempfile().unwrap()))
+        .run()
+        .unwrap();
+
+    let content = std::fs::read_to_string(report.path()).unwrap();
+    let lines: Vec<&str> = content.lines().collect();
+    assert_eq!(lines[0], "reference,path,status,first_seen,created,renames,moves,last_data_change,deleted");
+    assert!(lines.iter().any(|l| l.starts_with("5-5,/,allocated,")), "{:?}", lines);
+
+    assert!(lines.contains(&"29-2,/Export_me.JPG,allocated,2007-08-20 13:10:23.578125,2021-08-30 20:48:42,\
+        2021-08-30 20:48:43 'photo.jpg' -> 'Export_me.JPG',2021-08-30 20:48:43 '/$Extend' -> '/',\
+        2021-08-30 20:48:44,"), "{:?}", lines);
+    assert!(lines.contains(&"39-1,/RECYCLER/S-1-5-21-3958095517-222395546-2225589205-500/INFO2,deleted,2021-08-30 20:48:45,,,,,2021-08-30 20:48:45"), "{:?}", lines);
+
+    /* a zero timestamp is no creation time */
+    assert!(lines.iter().any(|l| l.starts_with("9-9,/$Secure,allocated,2007-08-20 12:32:49.171875,,")), "{:?}", lines);
+}
@@ -0,0 +1,204 @@
+mod common;
+
+use common::*;
+use libmft2bodyfile::{BodyfileSink, InputSource, LogRecordIterator, Mft2BodyfileTask};
+use std::fs::File;
+use std::io::{Cursor, Read};
+
+const PAGE_SIZE: usize = 0x1000;
+const PAGE_DATA_OFFSET: usize = 0x40;
+
+/// 2020-09-12 09:46:40 UTC
+const OLD_FILETIME: u64 = 132443776000000000;
+
+/// the `$MFT` entry of `/Export_me.JPG` in the control `$MFT`
+const EXPORT_ME_ENTRY: u64 = 29;
+
+struct LogRecord {
+    lsn: u64,
+    redo_operation: u16,
+    undo_operation: u16,
+    redo: Vec<u8>,
+    undo: Vec<u8>,
+    /// `$MFT` entry which is modified, assuming clusters of 4096 bytes
+    entry: u64,
+    record_offset: u16,
+    attribute_offset: u16,
+}
+
+impl LogRecord {
+    fn new(lsn: u64, redo_operation: u16, undo_operation: u16, redo: Vec<u8>, undo: Vec<u8>) -> Self {
+        Self { lsn, redo_operation, undo_operation, redo, undo, entry: 0, record_offset: 0, attribute_offset: 0 }
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut client = vec![0; 0x20];
+        client[0x00..0x02].copy_from_slice(&self.redo_operation.to_le_bytes());
+        client[0x02..0x04].copy_from_slice(&self.undo_operation.to_le_bytes());
+        client[0x04..0x06].copy_from_slice(&0x20u16.to_le_bytes());
+        client[0x06..0x08].copy_from_slice(&(self.redo.len() as u16).to_le_bytes());
+        client[0x08..0x0a].copy_from_slice(&(0x20 + self.redo.len() as u16).to_le_bytes());
+        client[0x0a..0x0c].copy_from_slice(&(self.undo.len() as u16).to_le_bytes());
+        client[0x10..0x12].copy_from_slice(&self.record_offset.to_le_bytes());
+        client[0x12..0x14].copy_from_slice(&self.attribute_offset.to_le_bytes());
+        client[0x14..0x16].copy_from_slice(&((self.entry % 4 * 2) as u16).to_le_bytes());
+        client[0x18..0x20].copy_from_slice(&(self.entry / 4).to_le_bytes());
+        client.extend(&self.redo);
+        client.extend(&self.undo);
+
+        let mut record = vec![0; 0x30];
+        record[0x00..0x08].copy_from_slice(&self.lsn.to_le_bytes());
+        record[0x18..0x1c].copy_from_slice(&(client.len() as u32).to_le_bytes());
+        record[0x20..0x24].copy_from_slice(&1u32.to_le_bytes());
+        record.extend(client);
+        record
+    }
+}
+
+/// creates a `$LogFile` with two restart pages, followed by log record pages
+/// which contain `records`. Records which do not fit into a page are continued
+/// in the next page.
+fn logfile(records: &[LogRecord]) -> Vec<u8> {
+    let mut restart_page = vec![0; PAGE_SIZE];
+    restart_page[0..4].copy_from_slice(b"RSTR");
+    restart_page[0x10..0x14].copy_from_slice(&(PAGE_SIZE as u32).to_le_bytes());
+    restart_page[0x14..0x18].copy_from_slice(&(PAGE_SIZE as u32).to_le_bytes());
+    restart_page[0x18..0x1a].copy_from_slice(&0x30u16.to_le_bytes());
+    restart_page[0x30 + 0x26..0x30 + 0x28].copy_from_slice(&(PAGE_DATA_OFFSET as u16).to_le_bytes());
+    let mut data = restart_page.clone();
+    data.extend(restart_page);
+
+    let mut pages: Vec<Vec<u8>> = vec![vec![0; PAGE_SIZE]];
+    let mut offset = PAGE_DATA_OFFSET;
+    for record in records {
+        if offset + 0x30 > PAGE_SIZE {
+            pages.push(vec![0; PAGE_SIZE]);
+            offset = PAGE_DATA_OFFSET;
+        }
+        let mut bytes = &record.to_bytes()[..];
+        loop {
+            let page = pages.last_mut().unwrap();
+            page[0x08..0x10].copy_from_slice(&record.lsn.to_le_bytes());
+            let length = std::cmp::min(bytes.len(), PAGE_SIZE - offset);
+            page[offset..offset + length].copy_from_slice(&bytes[..length]);
+            offset += length;
+            bytes = &bytes[length..];
+            if bytes.is_empty() {
+                break;
+            }
+            pages.push(vec![0; PAGE_SIZE]);
+            offset = PAGE_DATA_OFFSET;
+        }
+        offset = offset.next_multiple_of(8);
+    }
+
+    for mut page in pages {
+        page[0..4].copy_from_slice(b"RCRD");
+        page[0x04..0x06].copy_from_slice(&0x28u16.to_le_bytes());
+        page[0x06..0x08].copy_from_slice(&9u16.to_le_bytes());
+        page[0x28..0x2a].copy_from_slice(&1u16.to_le_bytes());
+        for sector in 0..8 {
+            let end = (sector + 1) * 512;
+            let original = [page[end - 2], page[end - 1]];
+            page[0x2a + sector * 2..0x2c + sector * 2].copy_from_slice(&original);
+            page[end - 2..end].copy_from_slice(&1u16.to_le_bytes());
+        }
+        data.extend(page);
+    }
+    data
+}
+
+fn records() -> Vec<LogRecord> {
+    let root = file_reference(5, 5);
+
+    /* creation of /created.txt in a new $MFT entry */
+    let entry = mft_entry(42, 3, &[
+        resident_attribute(0x10, "", 0, &standard_information(FILETIME)),
+        resident_attribute(0x30, "", 1, &file_name(root, "created.txt", FILETIME)),
+    ]);
+    let created = index_entry(file_reference(42, 3), &file_name(root, "created.txt", FILETIME));
+
+    /* deletion of /removed.txt, whose $MFT entry has already been reused */
+    let removed = index_entry(file_reference(43, 1), &file_name(root, "removed.txt", OLD_FILETIME));
+
+    /* update of the modification times of /Export_me.JPG */
+    let mut timestamps = Vec::new();
+    timestamps.extend_from_slice(&OLD_FILETIME.to_le_bytes());
+    timestamps.extend_from_slice(&OLD_FILETIME.to_le_bytes());
+    let mut update = LogRecord::new(0x1003, 0x07, 0x07, timestamps, vec![0; 16]);
+    update.entry = EXPORT_ME_ENTRY;
+    update.record_offset = 0x18 + 8;
+    update.attribute_offset = 0x38;
+
+    vec![
+        /* a record without any relevant information */
+        LogRecord::new(0x1000, 0x1b, 0x00, Vec::new(), Vec::new()),
+        LogRecord::new(0x1001, 0x02, 0x00, entry, Vec::new()),
+        LogRecord::new(0x1002, 0x0c, 0x0d, created, Vec::new()),
+        update,
+        LogRecord::new(0x1004, 0x0f, 0x0e, Vec::new(), removed),
+    ]
+}
+
+fn run(logfile: Vec<u8>) -> Vec<String> {
+    let input = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(input.path(), logfile).unwrap();
+
+    let output = tempfile::NamedTempFile::new().unwrap();
+    Mft2BodyfileTask::default()
+        .with_mft_file(get_mft_file())
+        .with_logfile_source(Some(InputSource::File(input.path().to_path_buf())))
+        .with_output(BodyfileSink::File(output.reopen().unwrap()))
+        .run()
+        .unwrap();
+    let mut content = String::new();
+    File::open(output.path()).unwrap().read_to_string(&mut content).unwrap();
+    content.lines().filter(|l| l.contains("($LogFile")).map(|l| l.to_owned()).collect()
+}
+
+#[test]
+fn test_log_records() {
+    let records: Vec<_> = LogRecordIterator::new(Cursor::new(logfile(&records())))
+        .map(Result::unwrap)
+        .collect();
+    let lsns: Vec<u64> = records.iter().map(|r| r.lsn()).collect();
+    assert_eq!(lsns, vec![0x1000, 0x1001, 0x1002, 0x1003, 0x1004]);
+    assert_eq!(records[1].redo_data().len(), RECORD_SIZE);
+}
+
+#[test]
+fn test_spanning_records() {
+    /* the $MFT entries do not fit into a single page */
+    let records: Vec<LogRecord> = (0..8)
+        .map(|i| LogRecord::new(0x2000 + i, 0x02, 0x00, mft_entry(42, 3, &[]), Vec::new()))
+        .collect();
+    let data = logfile(&records);
+    assert_eq!(data.len(), 5 * PAGE_SIZE);
+
+    let lsns: Vec<u64> = LogRecordIterator::new(Cursor::new(data)).map(|r| r.unwrap().lsn()).collect();
+    assert_eq!(lsns, (0x2000..0x2008).collect::<Vec<u64>>());
+}
+
+#[test]
+fn test_logfile_events() {
+    let mut lines = run(logfile(&records()));
+    lines.sort();
+    assert_eq!(lines, vec![
+        "0|/Export_me.JPG ($LogFile op=UpdateResidentValue lsn=0x1003)|29-16-2||0|0|0|-1|1599904000|1599904000|-1",
+        "0|/created.txt ($LogFile op=AddIndexEntryRoot filename=created.txt parent='/' lsn=0x1002)|42-160-3||0|0|0|1630356522|1630356522|1630356522|1630356522",
+        "0|/created.txt ($LogFile op=InitializeFileRecordSegment filename=created.txt parent='/' lsn=0x1001)|42-16-3||0|0|0|1630356522|1630356522|1630356522|1630356522",
+        "0|/removed.txt ($LogFile op=DeleteIndexEntryAllocation filename=removed.txt parent='/' lsn=0x1004)|43-160-1||0|0|0|1599904000|1599904000|1599904000|1599904000",
+    ]);
+}
+
+#[test]
+fn test_empty_logfile() {
+    assert!(run(Vec::new()).is_empty());
+}
+
+#[test]
+fn test_overflowing_target_vcn() {
+    let mut record = LogRecord::new(0x3000, 0x02, 0x00, mft_entry(42, 3, &[]), Vec::new());
+    record.entry = u64::MAX;
+    assert!(run(logfile(&[record])).is_empty());
+}
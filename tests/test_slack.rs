@@ -0,0 +1,59 @@
+mod common;
+
+use common::*;
+use libmft2bodyfile::{BodyfileSink, Mft2BodyfileTask};
+use std::fs::File;
+use std::io::{Cursor, Read};
+
+/// 2020-09-12 09:46:40 UTC
+const OLD_FILETIME: u64 = 132443776000000000;
+
+/// appends an entry to the control `$MFT`, which contains an attribute of a
+/// former file and a copy of its current `$STANDARD_INFORMATION` in its slack
+fn mft_with_slack() -> Vec<u8> {
+    let mut mft = read_control_mft();
+    let record_number = (mft.len() / RECORD_SIZE) as u32;
+    let standard_info = resident_attribute(0x10, "", 0, &standard_information(FILETIME));
+    let mut entry = mft_entry(record_number, 2, &[
+        standard_info.clone(),
+        resident_attribute(0x30, "", 1, &file_name(file_reference(5, 5), "new_name.txt", FILETIME)),
+    ]);
+
+    let used_size = u32::from_le_bytes([entry[0x18], entry[0x19], entry[0x1a], entry[0x1b]]) as usize;
+    let old_name = resident_attribute(0x30, "", 3, &file_name(file_reference(5, 5), "old_name.txt", OLD_FILETIME));
+    entry[used_size..used_size + old_name.len()].copy_from_slice(&old_name);
+    entry[0x200..0x200 + standard_info.len()].copy_from_slice(&standard_info);
+
+    mft.extend(entry);
+    mft
+}
+
+fn run(mft_slack: bool) -> Vec<String> {
+    let output = tempfile::NamedTempFile::new().unwrap();
+    Mft2BodyfileTask::default()
+        .with_mft_reader(Cursor::new(mft_with_slack()))
+        .with_mft_slack(mft_slack)
+        .with_output(BodyfileSink::File(output.reopen().unwrap()))
+        .run()
+        .unwrap();
+    let mut content = String::new();
+    File::open(output.path()).unwrap().read_to_string(&mut content).unwrap();
+    content.lines().map(|l| l.to_owned()).collect()
+}
+
+#[test]
+fn test_slack() {
+    let lines = run(true);
+    let slack_lines: Vec<&String> = lines.iter().filter(|l| l.contains("slack)")).collect();
+    assert_eq!(slack_lines.len(), 1, "{:?}", slack_lines);
+    assert!(slack_lines[0].starts_with("0|/old_name.txt ($FILE_NAME, slack)|"), "{:?}", slack_lines);
+    assert!(slack_lines[0].ends_with("|1599904000|1599904000|1599904000|1599904000"), "{:?}", slack_lines);
+    assert!(lines.iter().any(|l| l.starts_with("0|/new_name.txt ($FILE_NAME)|")));
+}
+
+#[test]
+fn test_without_slack() {
+    let lines = run(false);
+    assert!(lines.iter().all(|l| !l.contains("slack)")));
+    assert!(lines.iter().any(|l| l.starts_with("0|/new_name.txt ($FILE_NAME)|")));
+}
@@ -0,0 +1,103 @@
+mod common;
+
+use common::*;
+use chrono::{TimeZone, Utc};
+use libmft2bodyfile::{BodyfileSink, InputSource, Mft2BodyfileTask, TimestampClass};
+use std::io::Cursor;
+
+/// 2000-01-01 00:00:00 UTC, which is earlier than the creation of the control `$MFT`
+const OLD_FILETIME: u64 = 125911584000000000;
+
+/// a `FILETIME` which Windows cannot convert, because it is negative
+const NEGATIVE_FILETIME: u64 = 0x8000_0000_0000_0000;
+
+fn entries() -> Vec<u8> {
+    let entry = |record_number, name, timestamp| mft_entry(record_number, 1, &[
+        resident_attribute(0x10, "", 0, &standard_information(timestamp)),
+        resident_attribute(0x30, "", 1, &file_name(file_reference(5, 5), name, OLD_FILETIME + 3_000_000_000_000_000)),
+    ]);
+    let mut data = entry(1000, "zero.txt", 0);
+    data.extend(entry(1001, "negative.txt", NEGATIVE_FILETIME));
+    data.extend(entry(1002, "old.txt", OLD_FILETIME));
+    data.extend(entry(1003, "future.txt", FILETIME));
+    data
+}
+
+fn run(task: Mft2BodyfileTask) -> Vec<String> {
+    let output = tempfile::NamedTempFile::new().unwrap();
+    task
+        .with_mft_file(get_mft_file())
+        .with_carve_sources(vec![InputSource::Reader(Box::new(Cursor::new(entries())))])
+        .with_acquisition_time(Some(Utc.with_ymd_and_hms(2021, 1, 1, 0, 0, 0).unwrap()))
+        .with_output(BodyfileSink::File(output.reopen().unwrap()))
+        .run()
+        .unwrap();
+    std::fs::read_to_string(output.path()).unwrap().lines().map(str::to_owned).collect()
+}
+
+#[test]
+fn test_timestamp_classes() {
+    assert_eq!(TimestampClass::of(0.into(), None, None), TimestampClass::Zero);
+    assert_eq!(TimestampClass::of(NEGATIVE_FILETIME.into(), None, None), TimestampClass::Before1601);
+    assert_eq!(TimestampClass::of(FILETIME.into(), None, Some(OLD_FILETIME.into())), TimestampClass::AfterAcquisition);
+    assert_eq!(TimestampClass::of(OLD_FILETIME.into(), Some(FILETIME.into()), None), TimestampClass::OutsideVolumeLifetime);
+    assert_eq!(TimestampClass::of(FILETIME.into(), Some(OLD_FILETIME.into()), Some(FILETIME.into())), TimestampClass::Valid);
+}
+
+#[test]
+fn test_timestamp_report() {
+    let report = tempfile::NamedTempFile::new().unwrap();
+    run(Mft2BodyfileTask::default().with_timestamp_report(Some(report.reopen().unwrap())));
+
+    let content = std::fs::read_to_string(report.path()).unwrap();
+    let lines: Vec<&str> = content.lines().collect();
+    assert_eq!(lines[0], "reference,path,attribute,field,timestamp,filetime,class");
+    for expected in [
+        "1000-1,/zero.txt,$STANDARD_INFORMATION,created,1601-01-01 00:00:00,0x0,zero",
+        "1001-1,/negative.txt,$STANDARD_INFORMATION,accessed,-27627-04-19 21:11:54.5224192,0x8000000000000000,before_1601",
+        "1002-1,/old.txt,$STANDARD_INFORMATION,created,2000-01-01 00:00:00,0x1bf53eb256d4000,outside_volume_lifetime",
+        "1003-1,/future.txt,$STANDARD_INFORMATION,modified,2021-08-30 20:48:42,0x1d79de06ac13100,after_acquisition",
+        "9-9,/$Secure,$FILE_NAME,created,1601-01-01 00:00:00,0x0,zero",
+    ].iter() {
+        assert!(lines.contains(expected), "{} not found in {:?}", expected, lines);
+    }
+
+    /* the modification time is kept if a file is copied from another volume */
+    assert!(!lines.iter().any(|l| l.starts_with("1002-1,/old.txt,$STANDARD_INFORMATION,modified,")), "{:?}", lines);
+    assert!(!lines.iter().any(|l| l.starts_with("29-2,")), "{:?}", lines);
+}
+
+#[test]
+fn test_timestamp_filter() {
+    let lines = run(Mft2BodyfileTask::default().with_timestamp_filter(vec![TimestampClass::Zero, TimestampClass::Before1601]));
+    let mut names: Vec<&str> = lines.iter().map(|l| l.split('|').nth(1).unwrap()).collect();
+    names.sort();
+    assert!(names.contains(&"/zero.txt (carved at 0x0)"), "{:?}", names);
+    assert!(names.contains(&"/negative.txt (carved at 0x400)"), "{:?}", names);
+    assert!(names.contains(&"/$Secure ($FILE_NAME)"), "{:?}", names);
+    assert!(!names.iter().any(|n| n.starts_with("/old.txt") || n.starts_with("/future.txt") || n.starts_with("/Export_me.JPG")), "{:?}", names);
+}
+
+#[test]
+fn test_volume_created_with_other_sequence_numbers() {
+    /* $MFT and $Volume have been recreated, e.g. by a third-party formatter */
+    let mut mft = read_control_mft();
+    for entry in [0, 3].iter() {
+        let offset = entry * RECORD_SIZE + 0x10;
+        mft[offset..offset + 2].copy_from_slice(&7u16.to_le_bytes());
+    }
+    let mft_file = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(mft_file.path(), &mft).unwrap();
+
+    let report = tempfile::NamedTempFile::new().unwrap();
+    Mft2BodyfileTask::default()
+        .with_mft_file(mft_file.path().to_path_buf())
+        .with_carve_sources(vec![InputSource::Reader(Box::new(Cursor::new(entries())))])
+        .with_timestamp_report(Some(report.reopen().unwrap()))
+        .with_output(BodyfileSink::File(tempfile::tempfile().unwrap()))
+        .run()
+        .unwrap();
+
+    let content = std::fs::read_to_string(report.path()).unwrap();
+    assert!(content.lines().any(|l| l == "1002-1,/old.txt,$STANDARD_INFORMATION,created,2000-01-01 00:00:00,0x1bf53eb256d4000,outside_volume_lifetime"), "{}", content);
+}
@@ -0,0 +1,82 @@
+mod common;
+
+use common::*;
+use libmft2bodyfile::{BodyfileSink, InputSource, Mft2BodyfileTask};
+use std::io::Cursor;
+
+/// 2000-01-01 00:00:00 UTC, which is earlier than the creation of the control `$MFT`
+const OLD_FILETIME: u64 = 125911584000000000;
+
+const FILE_CREATE: u32 = 0x00000100;
+
+/// a file whose `$STANDARD_INFORMATION` has been set to an older date
+fn stomped_entry() -> Vec<u8> {
+    mft_entry(1000, 3, &[
+        resident_attribute(0x10, "", 0, &standard_information(OLD_FILETIME)),
+        resident_attribute(0x30, "", 1, &file_name(file_reference(5, 5), "stomped.txt", FILETIME)),
+        resident_attribute(0x80, "", 2, b"content"),
+    ])
+}
+
+#[test]
+fn test_timestomping() {
+    /* /Export_me.JPG has been created long after its $STANDARD_INFORMATION creation time */
+    let journal = usn_record_v2(file_reference(29, 2), file_reference(5, 5), 0, FILETIME, FILE_CREATE, "Export_me.JPG");
+
+    let output = tempfile::NamedTempFile::new().unwrap();
+    let report = tempfile::NamedTempFile::new().unwrap();
+    Mft2BodyfileTask::default()
+        .with_mft_file(get_mft_file())
+        .with_usnjrnl_reader(Cursor::new(journal))
+        .with_carve_sources(vec![InputSource::Reader(Box::new(Cursor::new(stomped_entry())))])
+        .with_detect_timestomping(true)
+        .with_timestomping_report(Some(report.reopen().unwrap()))
+        .with_output(BodyfileSink::File(output.reopen().unwrap()))
+        .run()
+        .unwrap();
+
+    let content = std::fs::read_to_string(report.path()).unwrap();
+    let mut lines: Vec<&str> = content.lines().collect();
+    assert_eq!(lines.remove(0), "reference,path,rule,details");
+    lines.sort();
+    assert_eq!(lines, vec![
+        "1000-3,/stomped.txt,created_before_volume,$STANDARD_INFORMATION created 2000-01-01 00:00:00 is earlier than the creation of the volume at 2007-08-20 12:32:49.171875",
+        "1000-3,/stomped.txt,si_created_before_fn,$STANDARD_INFORMATION created 2000-01-01 00:00:00 is earlier than $FILE_NAME created 2021-08-30 20:48:42",
+        "1000-3,/stomped.txt,zero_fraction,$STANDARD_INFORMATION created or modified has no fractions of a second",
+        "29-2,/Export_me.JPG,si_created_before_usnjrnl,$STANDARD_INFORMATION created 2007-08-20 13:10:23.578125 is earlier than the FILE_CREATE record at 2021-08-30 20:48:42",
+        "31-1,/Scientific control.mp3,zero_fraction,$STANDARD_INFORMATION created or modified has no fractions of a second",
+        "32-2,/MVC-577V.MPG,zero_fraction,$STANDARD_INFORMATION created or modified has no fractions of a second",
+    ]);
+
+    let bodyfile = std::fs::read_to_string(output.path()).unwrap();
+    assert!(bodyfile.lines().any(|l| l.starts_with(
+        "0|/stomped.txt (timestomping: si_created_before_fn, zero_fraction, created_before_volume) (carved at 0x0)|1000-128-2|")), "{}", bodyfile);
+    assert!(bodyfile.lines().any(|l| l.starts_with("0|/Export_me.JPG (timestomping: si_created_before_usnjrnl)|29-128-3|")), "{}", bodyfile);
+    assert!(bodyfile.lines().any(|l| l.starts_with("0|/stomped.txt ($FILE_NAME) (carved at 0x0)|")), "{}", bodyfile);
+}
+
+#[test]
+fn test_unset_standard_information() {
+    let mut mft = read_control_mft();
+    let entry = mft_entry(41, 3, &[
+        resident_attribute(0x10, "", 0, &standard_information(0)),
+        resident_attribute(0x30, "", 1, &file_name(file_reference(5, 5), "unset.txt", FILETIME)),
+    ]);
+    mft[41 * RECORD_SIZE..42 * RECORD_SIZE].copy_from_slice(&entry);
+    let mft_file = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(mft_file.path(), &mft).unwrap();
+    let journal = usn_record_v2(file_reference(41, 3), file_reference(5, 5), 0, FILETIME, FILE_CREATE, "unset.txt");
+
+    let report = tempfile::NamedTempFile::new().unwrap();
+    Mft2BodyfileTask::default()
+        .with_mft_file(mft_file.path().to_path_buf())
+        .with_usnjrnl_reader(Cursor::new(journal))
+        .with_detect_timestomping(true)
+        .with_timestomping_report(Some(report.reopen().unwrap()))
+        .with_output(BodyfileSink::File(tempfile::tempfile().unwrap()))
+        .run()
+        .unwrap();
+
+    let content = std::fs::read_to_string(report.path()).unwrap();
+    assert!(!content.lines().any(|l| l.starts_with("41-3,")), "{}", content);
+}
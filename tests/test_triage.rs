@@ -0,0 +1,76 @@
+mod common;
+
+use common::*;
+use libmft2bodyfile::{BodyfileSink, Mft2BodyfileTask, TriageCollection};
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::Path;
+
+fn usnjrnl() -> Vec<u8> {
+    usn_record_v2(file_reference(39, 2), file_reference(5, 5), 0, FILETIME, 0x100, "created_file.txt")
+}
+
+fn run_on_collection(path: &Path) -> Vec<String> {
+    let collection = TriageCollection::open(path).unwrap();
+    let output = tempfile::NamedTempFile::new().unwrap();
+    for volume in collection.volumes() {
+        Mft2BodyfileTask::default()
+            .with_mft_file(volume.mft().to_path_buf())
+            .with_usnjrnl(volume.usnjrnl().map(Path::to_path_buf))
+            .with_volume_tag(Some(volume.tag().to_owned()))
+            .with_output(BodyfileSink::File(OpenOptions::new().append(true).open(output.path()).unwrap()))
+            .run()
+            .unwrap();
+    }
+    let mut content = String::new();
+    File::open(output.path()).unwrap().read_to_string(&mut content).unwrap();
+    content.lines().map(|l| l.to_owned()).collect()
+}
+
+#[test]
+fn test_kape_directory() {
+    let dir = tempfile::tempdir().unwrap();
+    let volume = dir.path().join("C");
+    fs::create_dir_all(volume.join("$Extend")).unwrap();
+    fs::copy(get_mft_file(), volume.join("$MFT")).unwrap();
+    File::create(volume.join("$Extend").join("$UsnJrnl%3A$J")).unwrap().write_all(&usnjrnl()).unwrap();
+    File::create(volume.join("$LogFile")).unwrap();
+
+    let collection = TriageCollection::open(dir.path()).unwrap();
+    assert_eq!(collection.volumes().len(), 1);
+    let volume = &collection.volumes()[0];
+    assert_eq!(volume.tag(), "C");
+    assert!(volume.usnjrnl().is_some());
+    assert!(volume.logfile().is_some());
+
+    let lines = run_on_collection(dir.path());
+    assert_eq!(lines.len(), expected_lines().len() + 1);
+    assert!(lines.iter().all(|l| l.split('|').nth(1).unwrap().starts_with("C:/")), "{:?}", lines);
+    assert!(lines.iter().any(|l| l.contains("/created_file.txt ($UsnJrnl ")));
+}
+
+#[test]
+#[cfg(feature = "zip")]
+fn test_velociraptor_zip() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("collection.zip");
+    let mut zip = zip::ZipWriter::new(File::create(&path).unwrap());
+    let options = zip::write::SimpleFileOptions::default();
+    for volume in ["%5C%5C.%5CC%3A", "%5C%5C.%5CD%3A"] {
+        zip.start_file(format!("uploads/ntfs/{}/$MFT", volume), options).unwrap();
+        zip.write_all(&read_control_mft()).unwrap();
+    }
+    zip.start_file("uploads/ntfs/%5C%5C.%5CD%3A/$Extend/$UsnJrnl%3A$J", options).unwrap();
+    zip.write_all(&usnjrnl()).unwrap();
+    zip.finish().unwrap();
+
+    assert!(TriageCollection::is_triage_collection(&path));
+    let collection = TriageCollection::open(&path).unwrap();
+    let volumes: Vec<(&str, bool)> = collection.volumes().iter().map(|v| (v.tag(), v.usnjrnl().is_some())).collect();
+    assert_eq!(volumes, vec![("C", false), ("D", true)]);
+
+    let lines = run_on_collection(&path);
+    let expected = expected_lines().len();
+    assert_eq!(lines.iter().filter(|l| l.split('|').nth(1).unwrap().starts_with("C:/")).count(), expected);
+    assert_eq!(lines.iter().filter(|l| l.split('|').nth(1).unwrap().starts_with("D:/")).count(), expected + 1);
+}
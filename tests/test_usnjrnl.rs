@@ -0,0 +1,243 @@
+mod common;
+
+use common::*;
+use libmft2bodyfile::{BodyfileSink, InputSource, Mft2BodyfileTask, UsnRecordIterator};
+use std::fs::File;
+use std::io::{Cursor, Read};
+
+/// the `$MFT` entry of `/Export_me.JPG` in the control `$MFT`
+const EXPORT_ME_ENTRY: u64 = 29;
+
+const DATA_OVERWRITE: u32 = 0x00000001;
+const CLOSE: u32 = 0x80000000;
+
+fn journal() -> Vec<u8> {
+    let file = file_reference(EXPORT_ME_ENTRY, 2);
+    let root = file_reference(5, 5);
+    let mut data = Vec::new();
+    data.extend(usn_record_v3(file, root, 0x00, FILETIME, DATA_OVERWRITE, "Export_me.JPG"));
+    data.extend(usn_record_v4(file, root, 0x68, DATA_OVERWRITE, &[(0, 0x1000), (0x8000, 0x200)]));
+    data.extend(usn_record_v3(file, root, 0xc8, FILETIME + 20_000_000, DATA_OVERWRITE | CLOSE, "Export_me.JPG"));
+
+    /* a record of a file system which uses 128 bit file ids */
+    let mut foreign = usn_record_v3(file, root, 0x130, FILETIME, CLOSE, "foreign.txt");
+    foreign[0x10..0x18].copy_from_slice(&1u64.to_le_bytes());
+    data.extend(foreign);
+    data
+}
+
+fn run(journal: Vec<u8>) -> Vec<String> {
+    let output = tempfile::NamedTempFile::new().unwrap();
+    Mft2BodyfileTask::default()
+        .with_mft_file(get_mft_file())
+        .with_usnjrnl_reader(Cursor::new(journal))
+        .with_output(BodyfileSink::File(output.reopen().unwrap()))
+        .run()
+        .unwrap();
+    let mut content = String::new();
+    File::open(output.path()).unwrap().read_to_string(&mut content).unwrap();
+    content.lines().filter(|l| l.contains("($UsnJrnl")).map(|l| l.to_owned()).collect()
+}
+
+#[test]
+fn test_record_versions() {
+    let records: Vec<_> = UsnRecordIterator::new(Cursor::new(journal())).map(Result::unwrap).collect();
+    let versions: Vec<u16> = records.iter().map(|r| r.major_version()).collect();
+    assert_eq!(versions, vec![3, 4, 3, 3]);
+
+    assert!(records[1].is_range_record());
+    assert_eq!(records[1].file_reference().entry, EXPORT_ME_ENTRY);
+    let extents: Vec<(i64, i64)> = records[1].extents().iter().map(|e| (e.offset(), e.length())).collect();
+    assert_eq!(extents, vec![(0, 0x1000), (0x8000, 0x200)]);
+
+    assert!(records[0].has_mft_references());
+    assert!(!records[3].has_mft_references());
+}
+
+#[test]
+fn test_range_records() {
+    let mut lines = run(journal());
+    lines.sort();
+    assert_eq!(lines, vec![
+        "0|/Export_me.JPG ($UsnJrnl reason=CLOSE+DATA_OVERWRITE attributes=ARCHIVE usn=0xc8 offset=0xc8)|29-2||0|0|0|1630356524|-1|-1|-1",
+        "0|/Export_me.JPG ($UsnJrnl reason=DATA_OVERWRITE attributes=ARCHIVE usn=0x0 offset=0x0)|29-2||0|0|0|1630356522|-1|-1|-1",
+        "0|/Export_me.JPG ($UsnJrnl reason=DATA_OVERWRITE extents=0x0+0x1000,0x8000+0x200 usn=0x68 offset=0x68)|29-2||0|0|0|1630356524|-1|-1|-1",
+    ]);
+}
+
+#[test]
+fn test_record_details() {
+    /* a file which has been hidden by a data management service */
+    let mut record = usn_record_v2(file_reference(EXPORT_ME_ENTRY, 2), file_reference(5, 5), 0, FILETIME, 0x8000, "Export_me.JPG");
+    record[0x2c..0x30].copy_from_slice(&1u32.to_le_bytes());
+    record[0x30..0x34].copy_from_slice(&0x105u32.to_le_bytes());
+    record[0x34..0x38].copy_from_slice(&0x80022u32.to_le_bytes());
+
+    let lines = run(record);
+    assert_eq!(lines, vec![
+        "0|/Export_me.JPG ($UsnJrnl reason=BASIC_INFO_CHANGE source=DATA_MANAGEMENT attributes=HIDDEN+ARCHIVE+PINNED security_id=261 usn=0x0 offset=0x0)|29-2||0|0|0|1630356522|-1|-1|-1",
+    ]);
+}
+
+#[test]
+fn test_historical_paths() {
+    const RENAME_OLD_NAME: u32 = 0x00001000;
+    const RENAME_NEW_NAME: u32 = 0x00002000;
+    let objid = file_reference(25, 1);
+    let extend = file_reference(11, 11);
+    let root = file_reference(5, 5);
+
+    let mut data = Vec::new();
+    data.extend(usn_record_v2(objid, extend, 0x100, FILETIME, DATA_OVERWRITE, "$ObjId"));
+    data.extend(usn_record_v2(extend, root, 0x200, FILETIME + 10_000_000, RENAME_OLD_NAME, "OldExtend"));
+    data.extend(usn_record_v2(extend, root, 0x260, FILETIME + 10_000_000, RENAME_NEW_NAME, "$Extend"));
+    data.extend(usn_record_v2(objid, extend, 0x300, FILETIME + 20_000_000, DATA_OVERWRITE | CLOSE, "$ObjId"));
+
+    let lines = run(data);
+    let objid_lines: Vec<&String> = lines.iter().filter(|l| l.contains("|25-1|")).collect();
+    assert_eq!(objid_lines.len(), 2, "{:?}", lines);
+    assert!(objid_lines.iter().any(|l| l.starts_with("0|/OldExtend/$ObjId ($UsnJrnl current='/$Extend/$ObjId' reason=DATA_OVERWRITE ")), "{:?}", lines);
+    assert!(objid_lines.iter().any(|l| l.starts_with("0|/$Extend/$ObjId ($UsnJrnl reason=CLOSE+DATA_OVERWRITE ")), "{:?}", lines);
+    assert!(lines.iter().any(|l| l.starts_with("0|/$Extend ($UsnJrnl renamed 'OldExtend' -> '$Extend' reason=RENAME_NEW_NAME ")), "{:?}", lines);
+}
+
+#[test]
+fn test_renames() {
+    const RENAME_OLD_NAME: u32 = 0x00001000;
+    const RENAME_NEW_NAME: u32 = 0x00002000;
+    let objid = file_reference(25, 1);
+    let extend = file_reference(11, 11);
+    let root = file_reference(5, 5);
+
+    let mut data = Vec::new();
+    data.extend(usn_record_v2(objid, root, 0x00, FILETIME, RENAME_OLD_NAME, "objid.tmp"));
+    data.extend(usn_record_v2(objid, extend, 0x50, FILETIME, RENAME_NEW_NAME, "$ObjId"));
+    data.extend(usn_record_v2(objid, extend, 0x98, FILETIME, RENAME_NEW_NAME | CLOSE, "$ObjId"));
+
+    /* a rename whose RENAME_NEW_NAME record is missing */
+    data.extend(usn_record_v2(objid, extend, 0xe0, FILETIME + 10_000_000, RENAME_OLD_NAME, "$ObjId"));
+
+    let mut lines = run(data);
+    lines.sort();
+    assert_eq!(lines, vec![
+        "0|/$Extend/$ObjId ($UsnJrnl reason=CLOSE+RENAME_NEW_NAME attributes=ARCHIVE usn=0x98 offset=0x98)|25-1||0|0|0|1630356522|-1|-1|-1",
+        "0|/$Extend/$ObjId ($UsnJrnl reason=RENAME_OLD_NAME attributes=ARCHIVE usn=0xe0 offset=0xe0)|25-1||0|0|0|1630356523|-1|-1|-1",
+        "0|/$Extend/$ObjId ($UsnJrnl renamed 'objid.tmp' -> '$ObjId' (from / to /$Extend) reason=RENAME_NEW_NAME attributes=ARCHIVE usn=0x50 offset=0x50)|25-1||0|0|0|1630356522|-1|-1|-1",
+    ]);
+}
+
+#[test]
+fn test_multiple_journals() {
+    let file = file_reference(EXPORT_ME_ENTRY, 2);
+    let root = file_reference(5, 5);
+    let old_record = usn_record_v3(file, root, 0x00, FILETIME, DATA_OVERWRITE, "Export_me.JPG");
+    let common_record = usn_record_v3(file, root, 0x68, FILETIME + 10_000_000, CLOSE, "Export_me.JPG");
+    let new_record = usn_record_v3(file, root, 0xd0, FILETIME + 20_000_000, DATA_OVERWRITE, "Export_me.JPG");
+
+    /* the live volume, where the oldest record has already been removed */
+    let live = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(live.path(), [common_record.clone(), new_record].concat()).unwrap();
+
+    /* a volume shadow copy, which does not contain the newest record */
+    let snapshot = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(snapshot.path(), [old_record, common_record].concat()).unwrap();
+
+    let output = tempfile::NamedTempFile::new().unwrap();
+    Mft2BodyfileTask::default()
+        .with_mft_file(get_mft_file())
+        .with_usnjrnl_sources(vec![
+            InputSource::File(live.path().to_path_buf()),
+            InputSource::File(snapshot.path().to_path_buf()),
+        ])
+        .with_output(BodyfileSink::File(output.reopen().unwrap()))
+        .run()
+        .unwrap();
+    let mut content = String::new();
+    File::open(output.path()).unwrap().read_to_string(&mut content).unwrap();
+    let mut lines: Vec<&str> = content.lines().filter(|l| l.contains("($UsnJrnl")).collect();
+    lines.sort();
+    assert_eq!(lines, vec![
+        "0|/Export_me.JPG ($UsnJrnl reason=CLOSE attributes=ARCHIVE usn=0x68 offset=0x0)|29-2||0|0|0|1630356523|-1|-1|-1",
+        "0|/Export_me.JPG ($UsnJrnl reason=DATA_OVERWRITE attributes=ARCHIVE usn=0x0 offset=0x0 journal=2)|29-2||0|0|0|1630356522|-1|-1|-1",
+        "0|/Export_me.JPG ($UsnJrnl reason=DATA_OVERWRITE attributes=ARCHIVE usn=0xd0 offset=0x68)|29-2||0|0|0|1630356524|-1|-1|-1",
+    ]);
+}
+
+#[test]
+fn test_journal_report() {
+    let file = file_reference(EXPORT_ME_ENTRY, 2);
+    let root = file_reference(5, 5);
+    let mut journal = usn_record_v3(file, root, 0x00, FILETIME, DATA_OVERWRITE, "Export_me.JPG");
+
+    /* an invalid record, which is followed by unused space until the end of the page */
+    let mut invalid = vec![0; 0x08];
+    invalid[0..4].copy_from_slice(&0x2000u32.to_le_bytes());
+    journal.extend(invalid);
+    journal.resize(0x1000, 0);
+
+    /* the records between both USNs have been lost */
+    journal.extend(usn_record_v3(file, root, 0x100000, FILETIME + 20_000_000, DATA_OVERWRITE | CLOSE, "Export_me.JPG"));
+    let input = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(input.path(), journal).unwrap();
+
+    let mut usnjrnl_max = Vec::new();
+    usnjrnl_max.extend_from_slice(&0x2000000u64.to_le_bytes());
+    usnjrnl_max.extend_from_slice(&0x10000u64.to_le_bytes());
+    usnjrnl_max.extend_from_slice(&0x1d79dd6a4bc1234u64.to_le_bytes());
+    usnjrnl_max.extend_from_slice(&0u64.to_le_bytes());
+    let max_file = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(max_file.path(), usnjrnl_max).unwrap();
+
+    let report = tempfile::NamedTempFile::new().unwrap();
+    Mft2BodyfileTask::default()
+        .with_mft_file(get_mft_file())
+        .with_usnjrnl(Some(input.path().to_path_buf()))
+        .with_usnjrnl_max(Some(max_file.path().to_path_buf()))
+        .with_journal_report(Some(report.reopen().unwrap()))
+        .with_output(BodyfileSink::File(tempfile::tempfile().unwrap()))
+        .run()
+        .unwrap();
+
+    let content = std::fs::read_to_string(report.path()).unwrap();
+    let lines: Vec<&str> = content.lines().collect();
+    assert_eq!(lines, vec![
+        "journal,records,errors,first_usn,last_usn,first_timestamp,last_timestamp,maximum_size,allocation_delta,gaps,reasons".to_owned(),
+        format!("{},2,1,0x0,0x100000,2021-08-30 20:48:42,2021-08-30 20:48:44,33554432,65536,0x0-0x100000,DATA_OVERWRITE=2; CLOSE=1", input.path().display()),
+    ]);
+}
+
+#[test]
+fn test_journal_only_entry_order() {
+    const RENAME_OLD_NAME: u32 = 0x00001000;
+    const RENAME_NEW_NAME: u32 = 0x00002000;
+    let file = file_reference(500, 1);
+    let root = file_reference(5, 5);
+
+    /* the clock has been turned back between both records */
+    let mut data = Vec::new();
+    data.extend(usn_record_v2(file, root, 0x00, FILETIME + 10_000_000, RENAME_OLD_NAME, "old.txt"));
+    data.extend(usn_record_v2(file, root, 0x50, FILETIME, RENAME_NEW_NAME, "new.txt"));
+
+    let lines = run(data);
+    assert_eq!(lines, vec![
+        "0|/new.txt ($UsnJrnl renamed 'old.txt' -> 'new.txt' reason=RENAME_NEW_NAME attributes=ARCHIVE usn=0x50 offset=0x50)|500-1||0|0|0|1630356522|-1|-1|-1",
+    ]);
+}
+
+#[test]
+fn test_record_at_end_of_page() {
+    let file = file_reference(EXPORT_ME_ENTRY, 2);
+    let root = file_reference(5, 5);
+
+    /* the first record ends 8 bytes before the end of the page */
+    let mut data = usn_record_v2(file, root, 0x00, FILETIME, DATA_OVERWRITE, &"a".repeat(2014));
+    assert_eq!(data.len(), 4088);
+    data.resize(0x1000, 0);
+    data.extend(usn_record_v2(file, root, 0x1000, FILETIME, DATA_OVERWRITE, "b"));
+    data.extend(usn_record_v2(file, root, 0x1040, FILETIME, DATA_OVERWRITE, "c"));
+    data.resize(0x2000, 0);
+    data.extend(usn_record_v2(file, root, 0x2000, FILETIME, DATA_OVERWRITE, "d"));
+
+    let usns: Vec<i64> = UsnRecordIterator::new(Cursor::new(data)).map(|r| r.unwrap().usn()).collect();
+    assert_eq!(usns, vec![0x00, 0x1000, 0x1040, 0x2000]);
+}
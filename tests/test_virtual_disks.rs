@@ -0,0 +1,294 @@
+mod common;
+
+use common::*;
+use libmft2bodyfile::{ImageFormat, VhdxReader, open_image};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+
+const VHD_BLOCK_SIZE: usize = 2 * 1024 * 1024;
+const VHDX_BLOCK_SIZE: usize = 1024 * 1024;
+
+fn read_image(image: &tempfile::NamedTempFile) -> Vec<u8> {
+    let mut data = Vec::new();
+    File::open(image.path()).unwrap().read_to_end(&mut data).unwrap();
+    data
+}
+
+fn utf16le(s: &str) -> Vec<u8> {
+    s.encode_utf16().flat_map(|c| c.to_le_bytes()).collect()
+}
+
+fn vhd_footer(disk_type: u32, size: u64, data_offset: u64) -> Vec<u8> {
+    let mut footer = vec![0; 512];
+    footer[0..8].copy_from_slice(b"conectix");
+    footer[8..12].copy_from_slice(&2u32.to_be_bytes());
+    footer[12..16].copy_from_slice(&0x0001_0000u32.to_be_bytes());
+    footer[16..24].copy_from_slice(&data_offset.to_be_bytes());
+    footer[40..48].copy_from_slice(&size.to_be_bytes());
+    footer[48..56].copy_from_slice(&size.to_be_bytes());
+    footer[60..64].copy_from_slice(&disk_type.to_be_bytes());
+    let checksum = !footer.iter().map(|b| *b as u32).sum::<u32>();
+    footer[64..68].copy_from_slice(&checksum.to_be_bytes());
+    footer
+}
+
+/// writes a dynamic VHD. If `parent` is given, a differencing VHD is written, which
+/// stores only those sectors of `data` which differ from `parent`.
+fn write_vhd(data: &[u8], path: &Path, parent: Option<(&[u8], &str)>) {
+    let disk_type = if parent.is_some() { 4 } else { 3 };
+    let blocks = data.len().div_ceil(VHD_BLOCK_SIZE);
+    let bat_offset = 512 + 1024;
+    let locator_offset = bat_offset + (blocks * 4).div_ceil(512) * 512;
+    let mut file = vhd_footer(disk_type, data.len() as u64, 512);
+
+    let mut header = vec![0; 1024];
+    header[0..8].copy_from_slice(b"cxsparse");
+    header[8..16].copy_from_slice(&u64::MAX.to_be_bytes());
+    header[16..24].copy_from_slice(&(bat_offset as u64).to_be_bytes());
+    header[24..28].copy_from_slice(&0x0001_0000u32.to_be_bytes());
+    header[28..32].copy_from_slice(&(blocks as u32).to_be_bytes());
+    header[32..36].copy_from_slice(&(VHD_BLOCK_SIZE as u32).to_be_bytes());
+    let mut locator = Vec::new();
+    if let Some((_, parent_name)) = parent {
+        let name: Vec<u8> = parent_name.encode_utf16().flat_map(|c| c.to_be_bytes()).collect();
+        header[64..64 + name.len()].copy_from_slice(&name);
+        locator = utf16le(&format!(".\\{}", parent_name));
+        header[576..580].copy_from_slice(b"W2ru");
+        header[580..584].copy_from_slice(&512u32.to_be_bytes());
+        header[584..588].copy_from_slice(&(locator.len() as u32).to_be_bytes());
+        header[592..600].copy_from_slice(&(locator_offset as u64).to_be_bytes());
+    }
+    file.extend(header);
+
+    let mut bat = vec![0xffu8; locator_offset - bat_offset];
+    let mut blocks_data = Vec::new();
+    let blocks_offset = locator_offset + 512;
+    for (idx, block) in data.chunks(VHD_BLOCK_SIZE).enumerate() {
+        let mut bitmap = vec![0u8; 512];
+        for (sector_idx, sector) in block.chunks(512).enumerate() {
+            let block_start = idx * VHD_BLOCK_SIZE + sector_idx * 512;
+            let is_present = match parent {
+                Some((parent_data, _)) => sector != &parent_data[block_start..block_start + sector.len()],
+                None => sector.iter().any(|b| *b != 0),
+            };
+            if is_present {
+                bitmap[sector_idx / 8] |= 0x80 >> (sector_idx % 8);
+            }
+        }
+        if bitmap.iter().all(|b| *b == 0) {
+            continue;
+        }
+        let sector = ((blocks_offset + blocks_data.len()) / 512) as u32;
+        bat[idx * 4..idx * 4 + 4].copy_from_slice(&sector.to_be_bytes());
+        blocks_data.extend(bitmap);
+        blocks_data.extend_from_slice(block);
+        blocks_data.resize(blocks_data.len() + VHD_BLOCK_SIZE - block.len(), 0);
+    }
+    file.extend(bat);
+    locator.resize(512, 0);
+    file.extend(locator);
+    file.extend(blocks_data);
+    file.extend(vhd_footer(disk_type, data.len() as u64, 512));
+    File::create(path).unwrap().write_all(&file).unwrap();
+}
+
+fn crc32c(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0x82f6_3b78 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn guid(s: &str) -> Vec<u8> {
+    let hex: Vec<u8> = s.split('-').collect::<String>().as_bytes().chunks(2)
+        .map(|c| u8::from_str_radix(std::str::from_utf8(c).unwrap(), 16).unwrap())
+        .collect();
+    let mut guid = Vec::new();
+    guid.extend(hex[0..4].iter().rev());
+    guid.extend(hex[4..6].iter().rev());
+    guid.extend(hex[6..8].iter().rev());
+    guid.extend(&hex[8..16]);
+    guid
+}
+
+fn with_crc32c(mut data: Vec<u8>) -> Vec<u8> {
+    let crc = crc32c(&data);
+    data[4..8].copy_from_slice(&crc.to_le_bytes());
+    data
+}
+
+/// writes a dynamic VHDX, which stores only the blocks which are not empty
+fn write_vhdx(data: &[u8], path: &Path) {
+    const MB: usize = 1024 * 1024;
+    let mut file = vec![0; 3 * MB];
+    file[0..8].copy_from_slice(b"vhdxfile");
+
+    let mut header = vec![0; 4096];
+    header[0..4].copy_from_slice(b"head");
+    header[8..16].copy_from_slice(&1u64.to_le_bytes());
+    header[66..68].copy_from_slice(&1u16.to_le_bytes());
+    file[0x10000..0x11000].copy_from_slice(&with_crc32c(header));
+
+    let mut regions = vec![0; 65536];
+    regions[0..4].copy_from_slice(b"regi");
+    regions[8..12].copy_from_slice(&2u32.to_le_bytes());
+    for (idx, (id, offset)) in [("2dc27766-f623-4200-9d64-115e9bfd4a08", 2 * MB), ("8b7ca206-4790-4b9a-b8fe-575f050f886e", MB)].iter().enumerate() {
+        let entry = 16 + idx * 32;
+        regions[entry..entry + 16].copy_from_slice(&guid(id));
+        regions[entry + 16..entry + 24].copy_from_slice(&(*offset as u64).to_le_bytes());
+        regions[entry + 24..entry + 28].copy_from_slice(&(MB as u32).to_le_bytes());
+        regions[entry + 28..entry + 32].copy_from_slice(&1u32.to_le_bytes());
+    }
+    file[0x30000..0x40000].copy_from_slice(&with_crc32c(regions));
+
+    let mut file_parameters = (VHDX_BLOCK_SIZE as u32).to_le_bytes().to_vec();
+    file_parameters.extend(0u32.to_le_bytes());
+    let items = [
+        ("caa16737-fa36-4d43-b3b6-33f0aa44e76b", file_parameters),
+        ("2fa54224-cd1b-4876-b211-5dbed83bf4b8", (data.len() as u64).to_le_bytes().to_vec()),
+        ("8141bf1d-a96f-4709-ba47-f233a8faab5f", 512u32.to_le_bytes().to_vec()),
+    ];
+    let metadata = &mut file[MB..2 * MB];
+    metadata[0..8].copy_from_slice(b"metadata");
+    metadata[10..12].copy_from_slice(&(items.len() as u16).to_le_bytes());
+    for (idx, (id, value)) in items.iter().enumerate() {
+        let entry = 32 + idx * 32;
+        let offset = 65536 + idx * 8;
+        metadata[entry..entry + 16].copy_from_slice(&guid(id));
+        metadata[entry + 16..entry + 20].copy_from_slice(&(offset as u32).to_le_bytes());
+        metadata[entry + 20..entry + 24].copy_from_slice(&(value.len() as u32).to_le_bytes());
+        metadata[offset..offset + value.len()].copy_from_slice(value);
+    }
+
+    for (idx, block) in data.chunks(VHDX_BLOCK_SIZE).enumerate() {
+        let entry: u64 = if block.iter().all(|b| *b == 0) {
+            0
+        } else {
+            let offset = file.len() as u64;
+            file.extend_from_slice(block);
+            file.resize(file.len() + VHDX_BLOCK_SIZE - block.len(), 0);
+            offset | 6
+        };
+        file[2 * MB + idx * 8..2 * MB + idx * 8 + 8].copy_from_slice(&entry.to_le_bytes());
+    }
+    File::create(path).unwrap().write_all(&file).unwrap();
+}
+
+fn assert_same_content(path: &Path, expected: &[u8]) {
+    let mut data = Vec::new();
+    open_image(path).unwrap().read_to_end(&mut data).unwrap();
+    assert_eq!(data.len(), expected.len());
+    assert!(data == expected);
+}
+
+#[test]
+fn test_split_raw() {
+    let raw_data = read_image(&create_image(&read_control_mft(), true, &[]));
+    let dir = tempfile::tempdir().unwrap();
+    for (idx, segment) in raw_data.chunks(10 * 1024 * 1024).enumerate() {
+        File::create(dir.path().join(format!("image.{:03}", idx + 1))).unwrap().write_all(segment).unwrap();
+    }
+    let first_segment = dir.path().join("image.001");
+    assert_eq!(ImageFormat::from_path(&first_segment).unwrap(), ImageFormat::SplitRaw);
+    assert_same_content(&first_segment, &raw_data);
+    assert_eq!(run_on_image(&first_segment), expected_lines());
+}
+
+#[test]
+fn test_numbered_files_of_different_sizes() {
+    let raw_data = read_image(&create_image(&read_control_mft(), true, &[]));
+    let dir = tempfile::tempdir().unwrap();
+    let first_segment = dir.path().join("image.001");
+    File::create(&first_segment).unwrap().write_all(&raw_data).unwrap();
+    File::create(dir.path().join("image.002")).unwrap().write_all(&raw_data[..1024]).unwrap();
+    File::create(dir.path().join("image.003")).unwrap().write_all(&raw_data[..512]).unwrap();
+    assert_eq!(ImageFormat::from_path(&first_segment).unwrap(), ImageFormat::Raw);
+    assert_eq!(run_on_image(&first_segment), expected_lines());
+}
+
+#[test]
+fn test_fixed_vhd() {
+    let raw_data = read_image(&create_image(&read_control_mft(), true, &[]));
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("image.vhd");
+    let mut data = raw_data.clone();
+    data.extend(vhd_footer(2, raw_data.len() as u64, u64::MAX));
+    File::create(&path).unwrap().write_all(&data).unwrap();
+
+    assert_eq!(ImageFormat::from_path(&path).unwrap(), ImageFormat::Vhd);
+    assert_same_content(&path, &raw_data);
+}
+
+#[test]
+fn test_dynamic_vhd() {
+    let raw_data = read_image(&create_image(&read_control_mft(), true, &[]));
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("image.vhd");
+    write_vhd(&raw_data, &path, None);
+
+    assert_eq!(ImageFormat::from_path(&path).unwrap(), ImageFormat::Vhd);
+    assert_same_content(&path, &raw_data);
+    assert_eq!(run_on_image(&path), expected_lines());
+}
+
+#[test]
+fn test_differencing_vhd() {
+    let raw_data = read_image(&create_image(&read_control_mft(), true, &[]));
+
+    /* the parent contains only every second MFT entry */
+    let mut parent_data = raw_data.clone();
+    let mft_offset = ((PARTITION_LBA + MFT_LCN) * 512) as usize;
+    for entry in parent_data[mft_offset..mft_offset + 49152].chunks_mut(RECORD_SIZE).step_by(2) {
+        entry.fill(0);
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    write_vhd(&parent_data, &dir.path().join("parent.vhd"), None);
+    let path = dir.path().join("child.vhd");
+    write_vhd(&raw_data, &path, Some((&parent_data, "parent.vhd")));
+
+    assert_same_content(&path, &raw_data);
+    assert_eq!(run_on_image(&path), expected_lines());
+}
+
+#[test]
+fn test_dynamic_vhdx() {
+    let raw_data = read_image(&create_image(&read_control_mft(), false, &[]));
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("image.vhdx");
+    write_vhdx(&raw_data, &path);
+
+    assert_eq!(ImageFormat::from_path(&path).unwrap(), ImageFormat::Vhdx);
+    assert_same_content(&path, &raw_data);
+    assert_eq!(run_on_image(&path), expected_lines());
+}
+
+#[test]
+fn test_corrupt_vhdx() {
+    const MB: usize = 1024 * 1024;
+    let raw_data = read_image(&create_image(&read_control_mft(), false, &[]));
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("image.vhdx");
+    write_vhdx(&raw_data, &path);
+    let vhdx = std::fs::read(&path).unwrap();
+
+    /* the virtual disk size is truncated to 4 bytes */
+    let mut data = vhdx.clone();
+    data[MB + 64 + 20..MB + 64 + 24].copy_from_slice(&4u32.to_le_bytes());
+    std::fs::write(&path, &data).unwrap();
+    assert!(VhdxReader::open(&path).is_err());
+
+    /* the BAT region exceeds the file */
+    let mut data = vhdx;
+    let mut regions = data[0x30000..0x40000].to_vec();
+    regions[4..8].copy_from_slice(&[0; 4]);
+    regions[16 + 24..16 + 28].copy_from_slice(&u32::MAX.to_le_bytes());
+    data[0x30000..0x40000].copy_from_slice(&with_crc32c(regions));
+    std::fs::write(&path, &data).unwrap();
+    assert!(VhdxReader::open(&path).is_err());
+}